@@ -0,0 +1,292 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::{anyhow, Context};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, signature::Signature, signer::Signer,
+    transaction::Result as TransactionResult,
+};
+
+use crate::client::vaa_verification_bundle::VaaSignatureVerificationBundle;
+
+/// controls how strictly `submit_in_order` waits for each transaction to land before
+/// sending the next one, trading latency for safety
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConfirmationStrategy {
+    /// commitment level a transaction must reach before it is considered confirmed
+    pub commitment: CommitmentConfig,
+    /// time to wait between signature status checks
+    pub poll_interval: Duration,
+    /// maximum time to wait for a single transaction to be confirmed
+    pub timeout: Duration,
+}
+
+impl Default for ConfirmationStrategy {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            poll_interval: Duration::from_millis(500),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// signs and sends each transaction of the bundle in order, waiting for every transaction
+/// to be confirmed according to `strategy` before sending the next one
+///
+/// `signers` must include the fee payer, and the signature set account, each transaction is
+/// signed by the subset of `signers` it requires
+pub async fn submit_in_order(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    bundle: VaaSignatureVerificationBundle,
+    signers: &[&dyn Signer],
+    strategy: ConfirmationStrategy,
+) -> anyhow::Result<Vec<Signature>> {
+    let mut signatures = Vec::with_capacity(bundle.txs.len());
+    for (idx, mut tx) in bundle.txs.into_iter().enumerate() {
+        let blockhash = rpc
+            .get_latest_blockhash()
+            .await
+            .with_context(|| "failed to get latest blockhash")?;
+        let required =
+            &tx.message.account_keys[..tx.message.header.num_required_signatures as usize];
+        let tx_signers = signers
+            .iter()
+            .copied()
+            .filter(|signer| required.contains(&signer.pubkey()))
+            .collect::<Vec<_>>();
+        tx.try_sign(&tx_signers, blockhash)
+            .with_context(|| format!("failed to sign transaction {idx}"))?;
+        let signature = rpc
+            .send_transaction(&tx)
+            .await
+            .with_context(|| format!("failed to send transaction {idx}"))?;
+        wait_for_confirmation(strategy, || {
+            rpc.get_signature_status_with_commitment(&signature, strategy.commitment)
+        })
+        .await
+        .with_context(|| format!("transaction {idx} ({signature}) was not confirmed"))?;
+        signatures.push(signature);
+    }
+    Ok(signatures)
+}
+
+/// the outcome of submitting one bundle through `submit_bundles_round_robin`
+#[derive(Debug)]
+pub struct BundleSubmission {
+    /// index within the pool of the connection the bundle was submitted on
+    pub connection: usize,
+    /// signatures of the bundle's transactions, in submission order
+    pub signatures: anyhow::Result<Vec<Signature>>,
+}
+
+/// submits independent bundles across a pool of rpc connections, assigning bundle `i` to
+/// connection `i % pool.len()`
+///
+/// every transaction of a bundle is sent on the same connection using `submit_in_order`, and
+/// bundles sharing a connection are submitted one after another, while the connections
+/// themselves are driven concurrently. results are returned in the order of `bundles`
+pub async fn submit_bundles_round_robin(
+    pool: &[solana_client::nonblocking::rpc_client::RpcClient],
+    bundles: Vec<VaaSignatureVerificationBundle>,
+    signers: &[&dyn Signer],
+    strategy: ConfirmationStrategy,
+) -> anyhow::Result<Vec<BundleSubmission>> {
+    if pool.is_empty() {
+        return Err(anyhow!("rpc connection pool is empty"));
+    }
+    let mut assigned = vec![Vec::new(); pool.len()];
+    for (idx, bundle) in bundles.into_iter().enumerate() {
+        assigned[idx % pool.len()].push((idx, bundle));
+    }
+    let results = futures::future::join_all(pool.iter().zip(assigned).enumerate().map(
+        |(connection, (rpc, bundles))| async move {
+            let mut results = Vec::with_capacity(bundles.len());
+            for (idx, bundle) in bundles {
+                let signatures = submit_in_order(rpc, bundle, signers, strategy).await;
+                results.push((
+                    idx,
+                    BundleSubmission {
+                        connection,
+                        signatures,
+                    },
+                ));
+            }
+            results
+        },
+    ))
+    .await;
+    let mut results = results.into_iter().flatten().collect::<Vec<_>>();
+    results.sort_by_key(|(idx, _)| *idx);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// repeatedly invokes `get_status` every `poll_interval` until it reports a status, returning
+/// an error if the transaction failed or the timeout elapses
+pub async fn wait_for_confirmation<F, Fut, E>(
+    strategy: ConfirmationStrategy,
+    mut get_status: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<TransactionResult<()>>, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let started = tokio::time::Instant::now();
+    loop {
+        match get_status()
+            .await
+            .with_context(|| "failed to get signature status")?
+        {
+            Some(status) => {
+                return status.map_err(|err| anyhow!("transaction failed {err:#?}"));
+            }
+            None if started.elapsed() >= strategy.timeout => {
+                return Err(anyhow!(
+                    "timed out after {:?} waiting for {:?} commitment",
+                    strategy.timeout,
+                    strategy.commitment.commitment
+                ));
+            }
+            None => tokio::time::sleep(strategy.poll_interval).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use solana_program::system_instruction;
+    use solana_sdk::{signature::Keypair, transaction::Transaction, transaction::TransactionError};
+
+    use super::*;
+    fn strategy() -> ConfirmationStrategy {
+        ConfirmationStrategy {
+            commitment: CommitmentConfig::finalized(),
+            poll_interval: Duration::from_millis(20),
+            timeout: Duration::from_millis(100),
+        }
+    }
+    #[tokio::test]
+    async fn test_wait_for_confirmation_timeout() {
+        let polls = AtomicUsize::new(0);
+        let started = std::time::Instant::now();
+        let err = wait_for_confirmation(strategy(), || async {
+            polls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, std::io::Error>(None)
+        })
+        .await
+        .unwrap_err();
+        let elapsed = started.elapsed();
+        assert!(err.to_string().starts_with("timed out"));
+        assert!(elapsed >= strategy().timeout);
+        // an initial poll, then one poll per interval until the timeout elapses
+        let polls = polls.load(Ordering::SeqCst);
+        assert!((5..=7).contains(&polls), "unexpected poll count {polls}");
+    }
+    #[tokio::test]
+    async fn test_wait_for_confirmation_status() {
+        let polls = AtomicUsize::new(0);
+        wait_for_confirmation(strategy(), || async {
+            let poll = polls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, std::io::Error>((poll == 2).then_some(Ok(())))
+        })
+        .await
+        .unwrap();
+        assert_eq!(polls.load(Ordering::SeqCst), 3);
+
+        let err = wait_for_confirmation(strategy(), || async {
+            Ok::<_, std::io::Error>(Some(Err(TransactionError::AccountInUse)))
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().starts_with("transaction failed"));
+    }
+    #[tokio::test]
+    async fn test_submit_in_order() {
+        let payer = Keypair::new();
+        let tx = |lamports| {
+            Transaction::new_with_payer(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    &payer.pubkey(),
+                    lamports,
+                )],
+                Some(&payer.pubkey()),
+            )
+        };
+        let bundle = VaaSignatureVerificationBundle {
+            txs: vec![tx(1), tx(2)],
+            ..Default::default()
+        };
+
+        let rpc =
+            solana_client::nonblocking::rpc_client::RpcClient::new_mock("succeeds".to_string());
+        // signers not required by a transaction are skipped
+        let unused = Keypair::new();
+        let signatures = submit_in_order(&rpc, bundle.clone(), &[&payer, &unused], strategy())
+            .await
+            .unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert_ne!(signatures[0], signatures[1]);
+
+        let rpc = solana_client::nonblocking::rpc_client::RpcClient::new_mock(
+            "sig_not_found".to_string(),
+        );
+        assert!(submit_in_order(&rpc, bundle, &[&payer], strategy())
+            .await
+            .is_err());
+    }
+    #[tokio::test]
+    async fn test_submit_bundles_round_robin() {
+        let payer = Keypair::new();
+        let bundle = |lamports: u64| VaaSignatureVerificationBundle {
+            txs: (0..3)
+                .map(|i| {
+                    Transaction::new_with_payer(
+                        &[system_instruction::transfer(
+                            &payer.pubkey(),
+                            &payer.pubkey(),
+                            lamports * 10 + i,
+                        )],
+                        Some(&payer.pubkey()),
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let bundles = (1..=3).map(bundle).collect::<Vec<_>>();
+        // the second connection never confirms anything, so only bundles routed to it fail
+        let pool = [
+            solana_client::nonblocking::rpc_client::RpcClient::new_mock("succeeds".to_string()),
+            solana_client::nonblocking::rpc_client::RpcClient::new_mock(
+                "sig_not_found".to_string(),
+            ),
+        ];
+        let results = submit_bundles_round_robin(&pool, bundles.clone(), &[&payer], strategy())
+            .await
+            .unwrap();
+        assert_eq!(
+            results.iter().map(|r| r.connection).collect::<Vec<_>>(),
+            vec![0, 1, 0]
+        );
+        assert!(results[1].signatures.is_err());
+        let blockhash = pool[0].get_latest_blockhash().await.unwrap();
+        for (result, bundle) in [(&results[0], &bundles[0]), (&results[2], &bundles[2])] {
+            // signatures come back in the bundle's transaction order
+            let signatures = result.signatures.as_ref().unwrap();
+            assert_eq!(signatures.len(), bundle.txs.len());
+            for (signature, tx) in signatures.iter().zip(&bundle.txs) {
+                let mut tx = tx.clone();
+                tx.sign(&[&payer], blockhash);
+                assert_eq!(*signature, tx.signatures[0]);
+            }
+        }
+
+        assert!(
+            submit_bundles_round_robin(&[], bundles, &[&payer], strategy())
+                .await
+                .is_err()
+        );
+    }
+}
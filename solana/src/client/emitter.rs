@@ -0,0 +1,97 @@
+use anyhow::{anyhow, Context};
+use solana_program::pubkey::Pubkey;
+
+use crate::{state::emitter::Emitter, utils::derivations::derive_emitter};
+
+/// fetches and unpacks the emitter account owned by `program_id`, verifying that the
+/// stored bump and owner derive the account's own address
+pub async fn load_emitter(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    program_id: Pubkey,
+) -> anyhow::Result<Emitter> {
+    let (emitter_pda, _) = derive_emitter(program_id);
+    let data = rpc
+        .get_account_data(&emitter_pda)
+        .await
+        .with_context(|| "failed to get emitter account")?;
+    let emitter = Emitter::from_account_data(&data).with_context(|| "failed to unpack emitter")?;
+    emitter
+        .verify_self(emitter_pda)
+        .map_err(|err| anyhow!("emitter {emitter_pda} failed verification {err:#?}"))?;
+    Ok(emitter)
+}
+
+/// parses the sequence out of the data of a core bridge sequence account
+///
+/// the account holds the sequence the emitter will assign to its next message, so the last
+/// published message was assigned the returned value minus one
+pub fn parse_emitter_sequence(data: &[u8]) -> anyhow::Result<u64> {
+    let sequence = data
+        .get(0..8)
+        .ok_or_else(|| anyhow!("sequence account data too short {}", data.len()))?;
+    Ok(u64::from_le_bytes(sequence.try_into()?))
+}
+
+/// fetches the sequence account `sequence_pda` and parses the sequence the emitter will
+/// assign to its next message, see `parse_emitter_sequence` and `Emitter::derive_sequence`
+pub async fn read_emitter_sequence(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    sequence_pda: Pubkey,
+) -> anyhow::Result<u64> {
+    let data = rpc
+        .get_account_data(&sequence_pda)
+        .await
+        .with_context(|| "failed to get sequence account")?;
+    parse_emitter_sequence(&data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_parse_emitter_sequence() {
+        // SequenceTracker { sequence: 420 }
+        let data = [0xa4, 0x01, 0, 0, 0, 0, 0, 0];
+        assert_eq!(parse_emitter_sequence(&data).unwrap(), 420);
+        assert_eq!(
+            parse_emitter_sequence(&crate::testing::mock_sequence_account(69)).unwrap(),
+            69
+        );
+        assert!(parse_emitter_sequence(&data[0..7]).is_err());
+    }
+    #[tokio::test]
+    async fn test_read_emitter_sequence() {
+        let data = crate::testing::mock_sequence_account(69);
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetAccountInfo,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "data": [solana_sdk::bs58::encode(&data).into_string(), "base58"],
+                    "lamports": 1,
+                    "owner": crate::WORMHOLE_PROGRAM_ID.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                },
+            }),
+        );
+        let rpc = solana_client::nonblocking::rpc_client::RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        );
+        let (emitter_pda, nonce) = derive_emitter(crate::WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID);
+        let emitter = Emitter {
+            owner: crate::WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID,
+            nonce,
+            next_publishable_nonce: 0,
+            padding: [0_u8; 32],
+        };
+        let (sequence_pda, _) = emitter.derive_sequence();
+        assert_eq!(
+            sequence_pda,
+            crate::utils::derivations::derive_sequence(emitter_pda).0
+        );
+        assert_eq!(read_emitter_sequence(&rpc, sequence_pda).await.unwrap(), 69);
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Context};
+use solana_program::pubkey::Pubkey;
+use wormhole_explorer_client::endpoints::vaa::ExplorerVaa;
+
+use crate::instructions::post_vaa::PostVAADataIx;
+
+/// decodes an emitter address as reported by the explorer, either as 64 hex characters
+/// (optionally 0x prefixed) or as a base58 encoded solana address
+pub fn decode_emitter_address(address: &str) -> anyhow::Result<[u8; 32]> {
+    let stripped = address.strip_prefix("0x").unwrap_or(address);
+    let decoded = if stripped.len() == 64 {
+        hex::decode(stripped).with_context(|| format!("invalid hex emitter address {address}"))?
+    } else {
+        solana_sdk::bs58::decode(address)
+            .into_vec()
+            .with_context(|| format!("invalid base58 emitter address {address}"))?
+    };
+    decoded
+        .try_into()
+        .map_err(|_| anyhow!("emitter address {address} is not 32 bytes"))
+}
+
+impl TryFrom<&ExplorerVaa> for PostVAADataIx {
+    type Error = anyhow::Error;
+    /// parses the signed vaa returned by the explorer, checking it against the emitter
+    /// and sequence the explorer reported alongside it
+    fn try_from(value: &ExplorerVaa) -> Result<Self, Self::Error> {
+        let (vaa, _) = PostVAADataIx::from_signed_vaa(&value.vaa)
+            .with_context(|| "failed to parse explorer vaa")?;
+        let emitter_address = decode_emitter_address(&value.emitter_addr)?;
+        if emitter_address != vaa.emitter_address {
+            return Err(anyhow!(
+                "explorer emitter {} does not match vaa emitter {}",
+                value.emitter_addr,
+                hex::encode(vaa.emitter_address)
+            ));
+        }
+        if value.sequence != vaa.sequence {
+            return Err(anyhow!(
+                "explorer sequence {} does not match vaa sequence {}",
+                value.sequence,
+                vaa.sequence
+            ));
+        }
+        Ok(vaa)
+    }
+}
+
+/// derives the posted vaa account the explorer vaa is stored in once posted, allowing a single
+/// account lookup to check whether it was already posted, see `posted_vaa_status`
+///
+/// the account is derived from the keccak256 hash of the vaa body, like
+/// `PostVAADataIx::derive_posted_vaa_account`
+pub fn posted_vaa_address(explorer_vaa: &ExplorerVaa) -> anyhow::Result<Pubkey> {
+    Ok(PostVAADataIx::try_from(explorer_vaa)?
+        .derive_posted_vaa_account()
+        .0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn explorer_vaa() -> ExplorerVaa {
+        serde_json::from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/explorer/ethereum_token_transfer.json"
+        )))
+        .unwrap()
+    }
+    #[test]
+    fn test_decode_emitter_address() {
+        let hex_address = "0000000000000000000000003ee18b2214aff97000d974cf647e7c347e8fa585";
+        let expected: [u8; 32] = hex::decode(hex_address).unwrap().try_into().unwrap();
+        assert_eq!(decode_emitter_address(hex_address).unwrap(), expected);
+        assert_eq!(
+            decode_emitter_address(&format!("0x{hex_address}")).unwrap(),
+            expected
+        );
+        let emitter = crate::utils::derivations::derive_token_bridge_emitter().0;
+        assert_eq!(
+            decode_emitter_address(&emitter.to_string()).unwrap(),
+            emitter.to_bytes()
+        );
+        assert!(decode_emitter_address("0xzz").is_err());
+        assert!(decode_emitter_address("abc").is_err());
+    }
+    #[test]
+    fn test_post_vaa_from_explorer_vaa() {
+        let explorer_vaa = explorer_vaa();
+        let vaa = PostVAADataIx::try_from(&explorer_vaa).unwrap();
+        assert_eq!(vaa.version, 1);
+        assert_eq!(vaa.guardian_set_index, 3);
+        assert_eq!(vaa.emitter_chain, 2);
+        assert_eq!(vaa.sequence, 140000);
+        assert_eq!(vaa.consistency_level, 1);
+        assert_eq!(
+            hex::encode(vaa.hash_vaa()),
+            "885d2e9aa653e2ceb4f009d3e595eea7348a6e6af30a53f9cd419ad96404b056"
+        );
+
+        let mut mismatched = explorer_vaa.clone();
+        mismatched.sequence += 1;
+        assert!(PostVAADataIx::try_from(&mismatched).is_err());
+        let mut malformed = explorer_vaa.clone();
+        malformed.emitter_addr = "not an address".to_string();
+        assert!(PostVAADataIx::try_from(&malformed).is_err());
+        let mut truncated = explorer_vaa;
+        truncated.vaa.truncate(20);
+        assert!(PostVAADataIx::try_from(&truncated).is_err());
+    }
+    #[test]
+    fn test_posted_vaa_address() {
+        let explorer_vaa = explorer_vaa();
+        let address = posted_vaa_address(&explorer_vaa).unwrap();
+        assert_eq!(
+            address.to_string(),
+            "8Gy5BWThgDBjsbcxu9fMj7RC5T3ngGYvjuFEVbx8XL2s"
+        );
+        // derived from the keccak256 hash of the body, not the double hash guardians sign
+        let body_hash =
+            hex::decode("885d2e9aa653e2ceb4f009d3e595eea7348a6e6af30a53f9cd419ad96404b056")
+                .unwrap();
+        assert_eq!(
+            address,
+            crate::utils::derivations::derive_posted_vaa(&body_hash).0
+        );
+    }
+}
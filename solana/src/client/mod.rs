@@ -5,3 +5,31 @@ pub mod secp256k1_helpers;
 
 /// creates the transaction bundle needed to verify a signed VAA
 pub mod vaa_verification_bundle;
+
+/// helpers for determining whether a vaa has already been posted on-chain
+pub mod posted_vaa;
+
+/// helpers for loading program emitter accounts
+pub mod emitter;
+
+/// submits verification bundles, waiting for each transaction to be confirmed
+pub mod bundle_submitter;
+
+/// conversions from vaas returned by the wormholescan explorer
+pub mod explorer_vaa;
+
+/// sends transactions which publish messages, paying the current core bridge fee
+pub mod send_message;
+
+/// polls for, posts and consumes vaas published by an emitter
+pub mod relayer;
+
+pub use bundle_submitter::{
+    submit_bundles_round_robin, submit_in_order, BundleSubmission, ConfirmationStrategy,
+};
+pub use emitter::{load_emitter, read_emitter_sequence};
+pub use explorer_vaa::posted_vaa_address;
+pub use posted_vaa::{posted_vaa_status, PostedVaaStatus};
+pub use relayer::{EmitterFilter, ProcessReport, Relayer, SkippedVaa};
+pub use send_message::{load_bridge_fee, send_with_fee_retry};
+pub use vaa_verification_bundle::GuardianSetExt;
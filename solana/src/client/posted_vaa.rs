@@ -0,0 +1,161 @@
+use anyhow::Context;
+use borsh::BorshDeserialize;
+use solana_sdk::account::Account;
+
+use crate::{
+    instructions::post_vaa::PostVAADataIx,
+    state::vaa::{MessageData, PostedVAAData},
+};
+
+/// the on-chain state of the account used to store a posted vaa
+#[derive(Clone, Debug)]
+pub enum PostedVaaStatus {
+    /// the vaa has never been posted, and the full verification flow must be run
+    NotPosted,
+    /// the vaa is posted and can be consumed
+    Posted(MessageData),
+    /// the vaa was posted but the account has since been closed, so it must be reposted
+    Closed,
+}
+
+/// fetches the posted vaa account for `vaa` and returns its status
+///
+/// when the account doesn't exist, its transaction history is used to distinguish
+/// a closed account from one which was never created
+pub async fn posted_vaa_status(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    vaa: &PostVAADataIx,
+) -> anyhow::Result<PostedVaaStatus> {
+    let (posted_vaa, _) = vaa.derive_posted_vaa_account();
+    let account = rpc
+        .get_account_with_commitment(&posted_vaa, rpc.commitment())
+        .await
+        .with_context(|| "failed to get posted vaa account")?
+        .value;
+    let has_history = match account {
+        Some(_) => false,
+        None => !rpc
+            .get_signatures_for_address(&posted_vaa)
+            .await
+            .with_context(|| "failed to get posted vaa account signatures")?
+            .is_empty(),
+    };
+    classify_posted_vaa_account(account.as_ref(), has_history)
+}
+
+/// determines the status of a posted vaa account, where `has_history` indicates whether
+/// any transactions have ever referenced the account
+pub fn classify_posted_vaa_account(
+    account: Option<&Account>,
+    has_history: bool,
+) -> anyhow::Result<PostedVaaStatus> {
+    match account {
+        Some(account) if account.lamports > 0 && !account.data.is_empty() => {
+            let posted_vaa = PostedVAAData::try_from_slice(&account.data[..])
+                .with_context(|| "failed to parse posted vaa account")?;
+            Ok(PostedVaaStatus::Posted(posted_vaa.message))
+        }
+        // an account with no lamports or data is pending garbage collection after being closed
+        Some(_) => Ok(PostedVaaStatus::Closed),
+        None if has_history => Ok(PostedVaaStatus::Closed),
+        None => Ok(PostedVaaStatus::NotPosted),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use borsh::BorshSerialize;
+
+    use super::*;
+    use crate::WORMHOLE_PROGRAM_ID;
+    #[test]
+    fn test_classify_posted_vaa_account() {
+        let posted_vaa = PostedVAAData::new(MessageData {
+            sequence: 69,
+            payload: b"Hello World".to_vec(),
+            ..Default::default()
+        });
+        let account = Account {
+            lamports: 42,
+            data: posted_vaa.try_to_vec().unwrap(),
+            owner: WORMHOLE_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        match classify_posted_vaa_account(Some(&account), false).unwrap() {
+            PostedVaaStatus::Posted(message) => {
+                assert_eq!(message.sequence, 69);
+                assert_eq!(message.payload, b"Hello World".to_vec());
+            }
+            status => panic!("unexpected status {status:?}"),
+        }
+
+        let closed = Account {
+            lamports: 0,
+            data: vec![],
+            ..account.clone()
+        };
+        assert!(matches!(
+            classify_posted_vaa_account(Some(&closed), true).unwrap(),
+            PostedVaaStatus::Closed
+        ));
+        assert!(matches!(
+            classify_posted_vaa_account(None, true).unwrap(),
+            PostedVaaStatus::Closed
+        ));
+        assert!(matches!(
+            classify_posted_vaa_account(None, false).unwrap(),
+            PostedVaaStatus::NotPosted
+        ));
+
+        let garbage = Account {
+            data: vec![1, 2, 3, 4],
+            ..account
+        };
+        assert!(classify_posted_vaa_account(Some(&garbage), false).is_err());
+    }
+    /// checks the posted vaa derivation against an account posted on mainnet, configured
+    /// through the environment:
+    ///
+    /// - `MAINNET_RPC_URL`: solana mainnet rpc, defaulting to the public endpoint
+    /// - `POSTED_VAA_ADDRESS`: a posted vaa account created by the core bridge on mainnet, ie:
+    ///   the `PostedVAA` account of a redeem transaction listed on
+    ///   https://wormholescan.io/#/tx/<TX_HASH>
+    ///
+    /// the vaa body is rebuilt from the account data, so the derivation is checked against
+    /// the core bridge rather than against this crate's own hashing
+    #[cfg(feature = "network-tests")]
+    #[tokio::test]
+    async fn test_posted_vaa_address_mainnet() {
+        use crate::instructions::post_vaa::hash_vaa_double;
+        use crate::utils::derivations::derive_posted_vaa;
+        let rpc = solana_client::nonblocking::rpc_client::RpcClient::new(
+            std::env::var("MAINNET_RPC_URL")
+                .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+        );
+        let posted_vaa: solana_sdk::pubkey::Pubkey = std::env::var("POSTED_VAA_ADDRESS")
+            .expect("POSTED_VAA_ADDRESS is not set")
+            .parse()
+            .unwrap();
+        let account = rpc.get_account(&posted_vaa).await.unwrap();
+        assert_eq!(account.owner, WORMHOLE_PROGRAM_ID);
+        let message = PostedVAAData::try_from_slice(&account.data)
+            .unwrap()
+            .message;
+        let vaa = PostVAADataIx {
+            version: message.vaa_version,
+            // not part of the hashed body
+            guardian_set_index: 0,
+            timestamp: message.vaa_time,
+            nonce: message.nonce,
+            emitter_chain: message.emitter_chain,
+            emitter_address: message.emitter_address,
+            sequence: message.sequence,
+            consistency_level: message.consistency_level,
+            payload: message.payload,
+        };
+        // the core bridge seeds the account with the single hash of the body
+        assert_eq!(vaa.derive_posted_vaa_account().0, posted_vaa);
+        assert_ne!(derive_posted_vaa(&hash_vaa_double(&vaa)).0, posted_vaa);
+    }
+}
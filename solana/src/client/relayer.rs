@@ -0,0 +1,540 @@
+use std::{collections::BTreeSet, future::Future, time::Duration};
+
+use anyhow::Context;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use wormhole_explorer_client::endpoints::vaa::ExplorerVaa;
+
+use crate::{
+    client::{
+        bundle_submitter::{submit_in_order, ConfirmationStrategy},
+        posted_vaa::{posted_vaa_status, PostedVaaStatus},
+        vaa_verification_bundle::{
+            ensure_guardian_set_active, load_clock, load_guardian_set_account,
+            VaaSignatureVerificationBundle, DEFAULT_BATCH_SIZE,
+        },
+    },
+    instructions::post_vaa::PostVAADataIx,
+    message_payload::Payload,
+    state::signed_vaa::{SignedVaa, VaaBody},
+};
+
+/// selects the vaas a relayer processes by the emitter which published them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EmitterFilter {
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+}
+
+impl EmitterFilter {
+    /// returns true if the vaa body was published by this emitter
+    pub fn matches(&self, body: &VaaBody) -> bool {
+        body.emitter_chain == self.emitter_chain && body.emitter_address == self.emitter_address
+    }
+}
+
+/// default number of processed sequences a relayer tracks, see `Relayer::max_tracked_sequences`
+pub const DEFAULT_MAX_TRACKED_SEQUENCES: usize = 10_000;
+
+/// a vaa skipped by `Relayer::process` because it can never be consumed
+#[derive(Debug)]
+pub struct SkippedVaa {
+    /// sequence of the vaa, `None` if it could not be parsed
+    pub sequence: Option<u64>,
+    pub error: anyhow::Error,
+}
+
+/// outcome of a `Relayer::process` call
+#[derive(Debug, Default)]
+pub struct ProcessReport {
+    /// number of vaas posted and consumed
+    pub consumed: usize,
+    /// vaas which could not be parsed or whose payload could not be decoded
+    pub skipped: Vec<SkippedVaa>,
+}
+
+/// polls for vaas published by a single emitter, posting each one on-chain and handing its
+/// payload to a consume callback, at most once per sequence
+pub struct Relayer {
+    pub filter: EmitterFilter,
+    /// time to wait between polls of the vaa source
+    pub poll_interval: Duration,
+    /// the most processed sequences kept in memory, once exceeded the lowest sequences are
+    /// pruned and every sequence up to the highest pruned one is treated as processed
+    pub max_tracked_sequences: usize,
+    /// sequences which have been posted and consumed, or skipped
+    processed: BTreeSet<u64>,
+    /// the highest sequence pruned from `processed`
+    pruned_through: Option<u64>,
+}
+
+impl Relayer {
+    pub fn new(filter: EmitterFilter, poll_interval: Duration) -> Self {
+        Self {
+            filter,
+            poll_interval,
+            max_tracked_sequences: DEFAULT_MAX_TRACKED_SEQUENCES,
+            processed: BTreeSet::new(),
+            pruned_through: None,
+        }
+    }
+    /// returns true if the vaa with `sequence` has already been consumed or skipped
+    pub fn is_processed(&self, sequence: u64) -> bool {
+        self.pruned_through
+            .is_some_and(|pruned_through| sequence <= pruned_through)
+            || self.processed.contains(&sequence)
+    }
+    fn mark_processed(&mut self, sequence: u64) {
+        self.processed.insert(sequence);
+        while self.processed.len() > self.max_tracked_sequences {
+            self.pruned_through = self.processed.pop_first();
+        }
+    }
+    /// posts and consumes every unprocessed vaa in `vaas` matching the emitter filter,
+    /// returning the number of vaas consumed along with the vaas which were skipped
+    ///
+    /// `vaas` are signed vaas in their wire format, `post` runs the verify + post flow for a
+    /// vaa (see `post_signed_vaa`), after which `consume` is invoked with the decoded payload.
+    /// a vaa is only marked as processed once `consume` succeeds, and an error posting or
+    /// consuming a vaa stops processing so it is retried by the next call
+    ///
+    /// vaas which can't be parsed, or whose payload can't be decoded, are skipped without
+    /// being posted, so a malformed vaa doesn't stop the vaas after it from being relayed.
+    /// skipped vaas with a known sequence are marked as processed and only reported once
+    pub async fn process<P, PFut, C>(
+        &mut self,
+        vaas: Vec<Vec<u8>>,
+        post: &mut P,
+        consume: &mut C,
+    ) -> anyhow::Result<ProcessReport>
+    where
+        P: FnMut(SignedVaa) -> PFut,
+        PFut: Future<Output = anyhow::Result<()>>,
+        C: FnMut(Payload, &SignedVaa) -> anyhow::Result<()>,
+    {
+        let mut report = ProcessReport::default();
+        for bytes in vaas {
+            let vaa = match SignedVaa::parse(&bytes) {
+                Ok(vaa) => vaa,
+                Err(err) => {
+                    report.skipped.push(SkippedVaa {
+                        sequence: None,
+                        error: anyhow::Error::new(err).context("failed to parse vaa"),
+                    });
+                    continue;
+                }
+            };
+            if !self.filter.matches(&vaa.body) || self.is_processed(vaa.body.sequence) {
+                continue;
+            }
+            let sequence = vaa.body.sequence;
+            let payload = match vaa.decode_payload() {
+                Ok(payload) => payload,
+                Err(err) => {
+                    self.mark_processed(sequence);
+                    report.skipped.push(SkippedVaa {
+                        sequence: Some(sequence),
+                        error: anyhow::Error::new(err)
+                            .context(format!("failed to decode vaa {sequence} payload")),
+                    });
+                    continue;
+                }
+            };
+            post(vaa.clone())
+                .await
+                .with_context(|| format!("failed to post vaa {sequence}"))?;
+            consume(payload, &vaa).with_context(|| format!("failed to consume vaa {sequence}"))?;
+            self.mark_processed(sequence);
+            report.consumed += 1;
+        }
+        Ok(report)
+    }
+    /// repeatedly fetches vaas with `fetch` and processes them (see `process`), waiting
+    /// `poll_interval` between polls, until `shutdown` completes or an error occurs
+    ///
+    /// the relayer keeps track of processed sequences, so `run` may be called again after
+    /// an error without consuming any vaa twice. skipped vaas are dropped, use `process`
+    /// directly to inspect them
+    pub async fn run<F, FFut, P, PFut, C>(
+        &mut self,
+        mut fetch: F,
+        mut post: P,
+        mut consume: C,
+        shutdown: impl Future<Output = ()>,
+    ) -> anyhow::Result<()>
+    where
+        F: FnMut() -> FFut,
+        FFut: Future<Output = anyhow::Result<Vec<Vec<u8>>>>,
+        P: FnMut(SignedVaa) -> PFut,
+        PFut: Future<Output = anyhow::Result<()>>,
+        C: FnMut(Payload, &SignedVaa) -> anyhow::Result<()>,
+    {
+        tokio::pin!(shutdown);
+        loop {
+            let poll = async {
+                let vaas = fetch().await.with_context(|| "failed to fetch vaas")?;
+                self.process(vaas, &mut post, &mut consume).await?;
+                tokio::time::sleep(self.poll_interval).await;
+                Ok::<_, anyhow::Error>(())
+            };
+            tokio::select! {
+                _ = &mut shutdown => return Ok(()),
+                result = poll => result?,
+            }
+        }
+    }
+}
+
+/// runs the full verify + post flow for `vaa` using a freshly generated signature set,
+/// returning the signature of the post_vaa transaction, or `None` without sending anything
+/// if the vaa is already posted
+pub async fn post_signed_vaa(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    payer: &Keypair,
+    vaa: &SignedVaa,
+    batch_size: usize,
+    strategy: ConfirmationStrategy,
+) -> anyhow::Result<Option<Signature>> {
+    let post_vaa = PostVAADataIx::from(vaa);
+    if let PostedVaaStatus::Posted(_) = posted_vaa_status(rpc, &post_vaa).await? {
+        return Ok(None);
+    }
+    let (guardian_set_key, _) = post_vaa.derive_guardian_set();
+    let guardian_set = load_guardian_set_account(guardian_set_key, rpc).await?;
+    let clock = load_clock(rpc).await?;
+    ensure_guardian_set_active(&guardian_set, clock.unix_timestamp as u32)?;
+
+    let (mut bundle, signature_set) = VaaSignatureVerificationBundle::with_fresh_signature_set(
+        payer.pubkey(),
+        &guardian_set,
+        &vaa.header.signatures,
+        vaa.body.digest(),
+        batch_size,
+    )?;
+    bundle.append_post_vaa_tx(payer.pubkey(), signature_set.pubkey(), post_vaa)?;
+    let signatures = submit_in_order(rpc, bundle, &[payer, &signature_set], strategy).await?;
+    // the post_vaa transaction is sent last
+    Ok(signatures.last().copied())
+}
+
+/// fetches the vaa emitted by the source chain transaction `tx_hash` and posts it like
+/// `post_signed_vaa`, confirming every transaction before sending the next, returning the
+/// signature of the post_vaa transaction or `None` if the vaa was already posted
+///
+/// `fetch_vaa` looks up the vaa emitted by a transaction hash, typically through the
+/// wormholescan explorer
+pub async fn relay_vaa<F, Fut>(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    fetch_vaa: F,
+    tx_hash: &str,
+    payer: &Keypair,
+) -> anyhow::Result<Option<Signature>>
+where
+    F: FnOnce(&str) -> Fut,
+    Fut: Future<Output = anyhow::Result<ExplorerVaa>>,
+{
+    let explorer_vaa = fetch_vaa(tx_hash)
+        .await
+        .with_context(|| format!("failed to fetch vaa emitted by {tx_hash}"))?;
+    // cross checks the emitter and sequence reported by the explorer
+    PostVAADataIx::try_from(&explorer_vaa)?;
+    let vaa = SignedVaa::parse(&explorer_vaa.vaa).with_context(|| "failed to parse vaa")?;
+    post_signed_vaa(
+        rpc,
+        payer,
+        &vaa,
+        DEFAULT_BATCH_SIZE,
+        ConfirmationStrategy::default(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use borsh::BorshSerialize;
+
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::state::signed_vaa::VaaHeader;
+
+    const EMITTER: [u8; 32] = [7_u8; 32];
+    fn vaa(emitter_address: [u8; 32], sequence: u64) -> Vec<u8> {
+        SignedVaa {
+            header: VaaHeader {
+                version: 1,
+                guardian_set_index: 3,
+                signatures: vec![],
+            },
+            body: VaaBody {
+                timestamp: 1700000069,
+                nonce: 0,
+                emitter_chain: 1,
+                emitter_address,
+                sequence,
+                consistency_level: 32,
+                payload: Payload::new(1, sequence.to_le_bytes().to_vec())
+                    .unwrap()
+                    .try_to_vec()
+                    .unwrap(),
+            },
+        }
+        .to_signed_vaa_bytes()
+        .unwrap()
+    }
+    #[tokio::test]
+    async fn test_relayer_run() {
+        let mut relayer = Relayer::new(
+            EmitterFilter {
+                emitter_chain: 1,
+                emitter_address: EMITTER,
+            },
+            Duration::from_millis(10),
+        );
+        let polls = RefCell::new(0);
+        let posted = RefCell::new(Vec::new());
+        let mut consumed = Vec::new();
+        relayer
+            .run(
+                || async {
+                    *polls.borrow_mut() += 1;
+                    // every poll returns the same vaas, along with one from another emitter
+                    Ok(vec![vaa(EMITTER, 1), vaa([8_u8; 32], 2), vaa(EMITTER, 2)])
+                },
+                |vaa: SignedVaa| {
+                    posted.borrow_mut().push(vaa.body.sequence);
+                    async { Ok(()) }
+                },
+                |payload: Payload, vaa: &SignedVaa| {
+                    assert_eq!(payload.data, vaa.body.sequence.to_le_bytes());
+                    consumed.push(vaa.body.sequence);
+                    Ok(())
+                },
+                tokio::time::sleep(Duration::from_millis(100)),
+            )
+            .await
+            .unwrap();
+        assert!(*polls.borrow() > 1);
+        assert_eq!(*posted.borrow(), vec![1, 2]);
+        assert_eq!(consumed, vec![1, 2]);
+        assert!(relayer.is_processed(1));
+        assert!(relayer.is_processed(2));
+    }
+    #[tokio::test]
+    async fn test_relayer_process_failure() {
+        let mut relayer = Relayer::new(
+            EmitterFilter {
+                emitter_chain: 1,
+                emitter_address: EMITTER,
+            },
+            Duration::from_millis(10),
+        );
+        let mut post = |_: SignedVaa| async { Ok(()) };
+        // a vaa is not marked as processed when it fails to be consumed
+        let err = relayer
+            .process(vec![vaa(EMITTER, 1)], &mut post, &mut |_, _| {
+                Err(anyhow!("consumer unavailable"))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "failed to consume vaa 1");
+        assert!(!relayer.is_processed(1));
+        assert_eq!(
+            relayer
+                .process(vec![vaa(EMITTER, 1)], &mut post, &mut |_, _| Ok(()))
+                .await
+                .unwrap()
+                .consumed,
+            1
+        );
+        assert!(relayer.is_processed(1));
+    }
+    #[tokio::test]
+    async fn test_relayer_skips_malformed_vaas() {
+        let mut relayer = Relayer::new(
+            EmitterFilter {
+                emitter_chain: 1,
+                emitter_address: EMITTER,
+            },
+            Duration::from_millis(10),
+        );
+        let mut undecodable = SignedVaa::parse(&vaa(EMITTER, 2)).unwrap();
+        undecodable.body.payload = vec![1, 0xff];
+        let vaas = vec![
+            vaa(EMITTER, 1),
+            b"garbage".to_vec(),
+            undecodable.to_signed_vaa_bytes().unwrap(),
+            vaa(EMITTER, 3),
+        ];
+        let mut posted = Vec::new();
+        let mut consumed = Vec::new();
+        let report = relayer
+            .process(
+                vaas.clone(),
+                &mut |vaa: SignedVaa| {
+                    posted.push(vaa.body.sequence);
+                    async { Ok(()) }
+                },
+                &mut |_, vaa: &SignedVaa| {
+                    consumed.push(vaa.body.sequence);
+                    Ok(())
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.consumed, 2);
+        assert_eq!(posted, vec![1, 3]);
+        assert_eq!(consumed, vec![1, 3]);
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(report.skipped[0].sequence, None);
+        assert_eq!(report.skipped[0].error.to_string(), "failed to parse vaa");
+        assert_eq!(report.skipped[1].sequence, Some(2));
+        assert_eq!(
+            report.skipped[1].error.to_string(),
+            "failed to decode vaa 2 payload"
+        );
+
+        // the undecodable vaa is only reported once
+        let report = relayer
+            .process(vaas, &mut |_| async { Ok(()) }, &mut |_, _| Ok(()))
+            .await
+            .unwrap();
+        assert_eq!(report.consumed, 0);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(relayer.is_processed(2));
+    }
+    #[tokio::test]
+    async fn test_relayer_prunes_processed_sequences() {
+        let mut relayer = Relayer::new(
+            EmitterFilter {
+                emitter_chain: 1,
+                emitter_address: EMITTER,
+            },
+            Duration::from_millis(10),
+        );
+        relayer.max_tracked_sequences = 2;
+        let report = relayer
+            .process(
+                vec![vaa(EMITTER, 5), vaa(EMITTER, 7), vaa(EMITTER, 6)],
+                &mut |_| async { Ok(()) },
+                &mut |_, _| Ok(()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.consumed, 3);
+        assert_eq!(relayer.processed.len(), 2);
+        // sequences up to the pruned one are treated as processed
+        assert!(relayer.is_processed(4));
+        assert!(relayer.is_processed(5));
+        assert!(relayer.is_processed(7));
+        assert!(!relayer.is_processed(8));
+    }
+    fn explorer_vaa() -> ExplorerVaa {
+        serde_json::from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/explorer/ethereum_token_transfer.json"
+        )))
+        .unwrap()
+    }
+    #[tokio::test]
+    async fn test_relay_vaa_already_posted() {
+        let posted_vaa = crate::state::vaa::PostedVAAData::new(Default::default());
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetAccountInfo,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "data": [
+                        solana_sdk::bs58::encode(posted_vaa.try_to_vec().unwrap()).into_string(),
+                        "base58"
+                    ],
+                    "lamports": 1,
+                    "owner": crate::WORMHOLE_PROGRAM_ID.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                },
+            }),
+        );
+        let rpc = solana_client::nonblocking::rpc_client::RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        );
+        let tx_hash = "0x1234";
+        let signature = relay_vaa(
+            &rpc,
+            |hash: &str| {
+                assert_eq!(hash, tx_hash);
+                async { Ok(explorer_vaa()) }
+            },
+            tx_hash,
+            &Keypair::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(signature, None);
+
+        let err = relay_vaa(
+            &rpc,
+            |_: &str| async { Err(anyhow!("not found")) },
+            tx_hash,
+            &Keypair::new(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "failed to fetch vaa emitted by 0x1234");
+    }
+    /// fetches the vaa emitted by the source chain transaction `tx_hash` from the explorer
+    #[cfg(feature = "network-tests")]
+    async fn fetch_vaa_by_tx_hash(
+        explorer: &wormhole_explorer_client::ExplorerClient,
+        tx_hash: &str,
+    ) -> anyhow::Result<ExplorerVaa> {
+        explorer
+            .fetch(wormhole_explorer_client::endpoints::vaa::VaaRequest {
+                tx_hash: Some(tx_hash.to_string()),
+                ..Default::default()
+            })
+            .await?
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("no vaa found"))
+    }
+    /// relays a vaa end to end, configured through the environment:
+    ///
+    /// - `RELAY_RPC_URL`: solana rpc the vaa is posted to
+    /// - `RELAY_PAYER`: path to the keypair paying for the transactions
+    /// - `RELAY_TX_HASH`: hash of the source chain transaction which emitted the vaa
+    #[cfg(feature = "network-tests")]
+    #[tokio::test]
+    async fn test_relay_vaa_network() {
+        use crate::client::{posted_vaa::classify_posted_vaa_account, posted_vaa_address};
+        let env = |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{name} is not set"));
+        let rpc = solana_client::nonblocking::rpc_client::RpcClient::new(env("RELAY_RPC_URL"));
+        let payer = solana_sdk::signature::read_keypair_file(env("RELAY_PAYER")).unwrap();
+        let explorer = wormhole_explorer_client::ExplorerClient::new(
+            "https://api.wormholescan.io".parse().unwrap(),
+        );
+        let tx_hash = env("RELAY_TX_HASH");
+        let fetch_vaa = |tx_hash: &str| {
+            let tx_hash = tx_hash.to_string();
+            let explorer = &explorer;
+            async move { fetch_vaa_by_tx_hash(explorer, &tx_hash).await }
+        };
+        relay_vaa(&rpc, fetch_vaa, &tx_hash, &payer).await.unwrap();
+
+        // the vaa is posted, whether by this relay or an earlier one
+        let explorer_vaa = fetch_vaa_by_tx_hash(&explorer, &tx_hash).await.unwrap();
+        let account = rpc
+            .get_account(&posted_vaa_address(&explorer_vaa).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(account.owner, crate::WORMHOLE_PROGRAM_ID);
+        assert!(matches!(
+            classify_posted_vaa_account(Some(&account), false).unwrap(),
+            PostedVaaStatus::Posted(_)
+        ));
+    }
+}
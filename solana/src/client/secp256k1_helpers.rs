@@ -2,6 +2,9 @@ use solana_sdk::secp256k1_instruction::{
     SecpSignatureOffsets, HASHED_PUBKEY_SERIALIZED_SIZE, SIGNATURE_OFFSETS_SERIALIZED_SIZE,
     SIGNATURE_SERIALIZED_SIZE,
 };
+use solana_sdk::transaction::Transaction;
+use thiserror::Error;
+use wormhole_core_bridge_solana::state::GuardianSet;
 
 #[derive(Clone, Copy)]
 /// A struct to hold the values specified in the `SecpSignatureOffsets` struct.
@@ -24,6 +27,131 @@ impl Default for SecpSignature {
     }
 }
 
+impl SecpSignature {
+    /// creates a secp256k1 signature, normalizing ethereum style recovery ids of 27/28 to the
+    /// 0/1 expected by the secp256k1 program
+    pub fn with_normalized_recovery_id(
+        signature: [u8; SIGNATURE_SERIALIZED_SIZE],
+        recovery_id: u8,
+        eth_address: [u8; HASHED_PUBKEY_SERIALIZED_SIZE],
+        message: [u8; 32],
+    ) -> Self {
+        Self {
+            signature,
+            recovery_id: if recovery_id >= 27 {
+                recovery_id - 27
+            } else {
+                recovery_id
+            },
+            eth_address,
+            message,
+        }
+    }
+    /// recovers the signer of the signature off-chain, returning true if it matches
+    /// `eth_address`, which catches malformed signatures before paying to verify them on-chain
+    ///
+    /// like the secp256k1 program, the signature is over the keccak256 hash of `message`.
+    /// returns an error if no public key can be recovered from the signature
+    pub fn verify_locally(&self) -> anyhow::Result<bool> {
+        Ok(self.recover_eth_address()? == self.eth_address)
+    }
+    /// recovers the eth address of the signer of the signature off-chain, independently of
+    /// `eth_address`
+    pub fn recover_eth_address(&self) -> anyhow::Result<[u8; HASHED_PUBKEY_SERIALIZED_SIZE]> {
+        let hash = solana_program::keccak::hash(&self.message);
+        let pubkey = solana_program::secp256k1_recover::secp256k1_recover(
+            &hash.0,
+            self.recovery_id,
+            &self.signature,
+        )
+        .map_err(|err| anyhow::anyhow!("failed to recover signer {err}"))?;
+        let pubkey_hash = solana_program::keccak::hash(&pubkey.to_bytes());
+        let mut eth_address = [0_u8; HASHED_PUBKEY_SERIALIZED_SIZE];
+        eth_address.copy_from_slice(&pubkey_hash.0[12..]);
+        Ok(eth_address)
+    }
+}
+
+/// returned when a secp256k1 signature references an eth address which is not
+/// a member of the guardian set it is being verified against
+#[derive(Debug, Error, PartialEq)]
+#[error("eth address {} is not in guardian set {guardian_set_index}", hex::encode(.eth_address))]
+pub struct UnknownGuardianAddress {
+    pub eth_address: [u8; HASHED_PUBKEY_SERIALIZED_SIZE],
+    pub guardian_set_index: u32,
+}
+
+/// confirms that the eth address of every signature in `secp_signatures`, as packed into the
+/// secp256k1 instruction, is a key in `guardian_set`, which catches signatures being verified
+/// against the wrong guardian set
+///
+/// this only checks `eth_address`, so it is only meaningful when the addresses were sourced
+/// independently of `guardian_set`, ie: recovered through `SecpSignature::recover_eth_address`
+pub fn verify_secp_addresses_in_set(
+    secp_signatures: &[SecpSignature],
+    guardian_set: &GuardianSet,
+) -> Result<(), UnknownGuardianAddress> {
+    for secp_signature in secp_signatures {
+        if !guardian_set.keys.contains(&secp_signature.eth_address) {
+            return Err(UnknownGuardianAddress {
+                eth_address: secp_signature.eth_address,
+                guardian_set_index: guardian_set.index,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// returned when the instruction indices within a secp256k1 instruction's signature offsets
+/// don't point at the position of that secp256k1 instruction within the transaction
+#[derive(Debug, Error, PartialEq)]
+pub enum SecpIndexMismatch {
+    #[error("transaction has no secp256k1 instruction")]
+    MissingSecpInstruction,
+    #[error("secp256k1 instruction {position} has malformed signature offsets")]
+    MalformedOffsets { position: usize },
+    #[error("secp256k1 instruction {position} offsets reference instruction {referenced}")]
+    IndexMismatch { position: usize, referenced: u8 },
+}
+
+/// confirms that every signature offset of every secp256k1 instruction in `tx` references
+/// the secp256k1 instruction itself for the signature, eth address and message data, which
+/// is where `make_secp256k1_instruction_data` packs them
+pub fn validate_secp_index_consistency(tx: &Transaction) -> Result<(), SecpIndexMismatch> {
+    let mut found = false;
+    for (position, ix) in tx.message.instructions.iter().enumerate() {
+        if tx.message.account_keys.get(ix.program_id_index as usize)
+            != Some(&solana_sdk::secp256k1_program::ID)
+        {
+            continue;
+        }
+        found = true;
+        let count = *ix
+            .data
+            .first()
+            .ok_or(SecpIndexMismatch::MalformedOffsets { position })? as usize;
+        let offsets = ix
+            .data
+            .get(1..1 + count * SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            .ok_or(SecpIndexMismatch::MalformedOffsets { position })?;
+        for offsets in offsets.chunks(SIGNATURE_OFFSETS_SERIALIZED_SIZE) {
+            // signature_instruction_index, eth_address_instruction_index, message_instruction_index
+            for referenced in [offsets[2], offsets[5], offsets[10]] {
+                if referenced as usize != position {
+                    return Err(SecpIndexMismatch::IndexMismatch {
+                        position,
+                        referenced,
+                    });
+                }
+            }
+        }
+    }
+    if !found {
+        return Err(SecpIndexMismatch::MissingSecpInstruction);
+    }
+    Ok(())
+}
+
 /// Create the instruction data for a secp256k1 instruction.
 ///
 /// `instruction_index` is the index the secp256k1 instruction will appear
@@ -34,7 +162,14 @@ pub fn make_secp256k1_instruction_data(
     signatures: &[SecpSignature],
     instruction_index: u8,
 ) -> anyhow::Result<Vec<u8>> {
-    assert!(signatures.len() <= u8::max_value().into());
+    // the number of signatures is stored in a single byte
+    if signatures.len() > u8::MAX as usize {
+        return Err(anyhow::anyhow!(
+            "too many signatures: {} > {}",
+            signatures.len(),
+            u8::MAX
+        ));
+    }
 
     // We're going to pack all the signatures into the secp256k1 instruction data.
     // Before our signatures though is the signature offset structures
@@ -92,3 +227,141 @@ pub fn make_secp256k1_instruction_data(
 
     Ok(instr_data)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::mock_guardian_set;
+    #[test]
+    fn test_verify_locally() {
+        use crate::testing::{mock_guardian_sign, mock_guardians};
+        let guardians = mock_guardians(2);
+        let digest = [9_u8; 32];
+        let signature = mock_guardian_sign(&guardians[0], digest);
+        let mut raw_sig = [0_u8; SIGNATURE_SERIALIZED_SIZE];
+        raw_sig.copy_from_slice(&signature[..64]);
+        let secp_signature = SecpSignature::with_normalized_recovery_id(
+            raw_sig,
+            signature[64],
+            guardians[0].eth_address,
+            digest,
+        );
+        assert!(secp_signature.verify_locally().unwrap());
+
+        // signed by a different guardian
+        let wrong_guardian = SecpSignature {
+            eth_address: guardians[1].eth_address,
+            ..secp_signature
+        };
+        assert!(!wrong_guardian.verify_locally().unwrap());
+        // signature over a different digest
+        let tampered = SecpSignature {
+            message: [8_u8; 32],
+            ..secp_signature
+        };
+        assert!(!tampered.verify_locally().unwrap());
+        // not a valid signature at all
+        let malformed = SecpSignature {
+            signature: [0_u8; SIGNATURE_SERIALIZED_SIZE],
+            ..secp_signature
+        };
+        assert!(malformed.verify_locally().is_err());
+    }
+    #[test]
+    fn test_normalized_recovery_id() {
+        let signatures = [0, 1, 27, 28]
+            .map(|recovery_id| {
+                SecpSignature::with_normalized_recovery_id(
+                    [7_u8; SIGNATURE_SERIALIZED_SIZE],
+                    recovery_id,
+                    [8_u8; HASHED_PUBKEY_SERIALIZED_SIZE],
+                    [9_u8; 32],
+                )
+            })
+            .to_vec();
+        let data = make_secp256k1_instruction_data(&signatures, 0).unwrap();
+        let recovery_ids = (0..signatures.len())
+            .map(|i| {
+                let offsets = 1 + i * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+                let signature_offset =
+                    u16::from_le_bytes([data[offsets], data[offsets + 1]]) as usize;
+                data[signature_offset + SIGNATURE_SERIALIZED_SIZE]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(recovery_ids, vec![0, 1, 0, 1]);
+    }
+    #[test]
+    fn test_make_secp256k1_instruction_data_too_many_signatures() {
+        let signatures = vec![SecpSignature::default(); 256];
+        assert_eq!(
+            make_secp256k1_instruction_data(&signatures, 0)
+                .unwrap_err()
+                .to_string(),
+            "too many signatures: 256 > 255"
+        );
+        let data = make_secp256k1_instruction_data(&signatures[..255], 0).unwrap();
+        assert_eq!(data[0], 255);
+    }
+    #[test]
+    fn test_verify_secp_addresses_in_set() {
+        let guardian_set = mock_guardian_set(3, vec![[1_u8; 20], [2_u8; 20], [3_u8; 20]], 0);
+        let mut secp_signatures = vec![
+            SecpSignature {
+                eth_address: [1_u8; 20],
+                ..Default::default()
+            },
+            SecpSignature {
+                eth_address: [3_u8; 20],
+                ..Default::default()
+            },
+        ];
+        assert!(verify_secp_addresses_in_set(&secp_signatures, &guardian_set).is_ok());
+        // the packed address is checked, regardless of who signed
+        secp_signatures.push(SecpSignature {
+            eth_address: [4_u8; 20],
+            ..Default::default()
+        });
+        assert_eq!(
+            verify_secp_addresses_in_set(&secp_signatures, &guardian_set),
+            Err(UnknownGuardianAddress {
+                eth_address: [4_u8; 20],
+                guardian_set_index: 3,
+            })
+        );
+    }
+    #[test]
+    fn test_validate_secp_index_consistency() {
+        use solana_program::{instruction::Instruction, pubkey::Pubkey, system_instruction};
+        let payer = Pubkey::new_unique();
+        let secp_signatures = vec![SecpSignature::default(); 2];
+        let secp_ix = |instruction_index| {
+            Instruction::new_with_bytes(
+                solana_sdk::secp256k1_program::ID,
+                &make_secp256k1_instruction_data(&secp_signatures, instruction_index).unwrap(),
+                vec![],
+            )
+        };
+        let other_ix = system_instruction::transfer(&payer, &payer, 1);
+
+        let tx = Transaction::new_with_payer(&[secp_ix(0), other_ix.clone()], Some(&payer));
+        assert_eq!(validate_secp_index_consistency(&tx), Ok(()));
+        let tx = Transaction::new_with_payer(&[other_ix.clone(), secp_ix(1)], Some(&payer));
+        assert_eq!(validate_secp_index_consistency(&tx), Ok(()));
+
+        // secp instruction placed after the instruction its offsets claim it is at
+        let tx = Transaction::new_with_payer(&[other_ix.clone(), secp_ix(0)], Some(&payer));
+        assert_eq!(
+            validate_secp_index_consistency(&tx),
+            Err(SecpIndexMismatch::IndexMismatch {
+                position: 1,
+                referenced: 0
+            })
+        );
+
+        let tx = Transaction::new_with_payer(&[other_ix], Some(&payer));
+        assert_eq!(
+            validate_secp_index_consistency(&tx),
+            Err(SecpIndexMismatch::MissingSecpInstruction)
+        );
+    }
+}
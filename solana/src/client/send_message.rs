@@ -0,0 +1,151 @@
+use std::future::Future;
+
+use anyhow::{anyhow, Context};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::{signature::Signature, signers::Signers, transaction::Transaction};
+
+use crate::{
+    instructions::send_message::parse_bridge_fee, utils::derivations::derive_core_bridge_config,
+};
+
+/// fetches the message fee currently configured in the core bridge config account
+pub async fn load_bridge_fee(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+) -> anyhow::Result<u64> {
+    let data = rpc
+        .get_account_data(&derive_core_bridge_config().0)
+        .await
+        .with_context(|| "failed to get core bridge config")?;
+    parse_bridge_fee(&data).ok_or_else(|| anyhow!("failed to parse core bridge config"))
+}
+
+/// sends a transaction containing the instructions returned by `build_instructions`, which is
+/// given the current message fee, typically an instruction invoking a program which wraps
+/// `send_message`
+///
+/// governance may change the fee after it was read but before the transaction lands, so when
+/// the transaction fails and the fee has since changed, it is rebuilt and retried once with
+/// the updated fee
+pub async fn send_with_fee_retry<T: Signers + ?Sized>(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    signers: &T,
+    payer: Pubkey,
+    build_instructions: impl Fn(u64) -> Vec<Instruction>,
+) -> anyhow::Result<Signature> {
+    retry_on_fee_change(
+        || load_bridge_fee(rpc),
+        |fee| {
+            let instructions = build_instructions(fee);
+            async move {
+                let blockhash = rpc
+                    .get_latest_blockhash()
+                    .await
+                    .with_context(|| "failed to get latest blockhash")?;
+                let tx = Transaction::new_signed_with_payer(
+                    &instructions,
+                    Some(&payer),
+                    signers,
+                    blockhash,
+                );
+                rpc.send_and_confirm_transaction(&tx)
+                    .await
+                    .with_context(|| format!("failed to send transaction with fee {fee}"))
+            }
+        },
+    )
+    .await
+}
+
+/// reads the fee and sends with it, retrying once if sending fails and the fee read
+/// afterwards differs from the one used
+pub async fn retry_on_fee_change<R, RFut, S, SFut>(
+    mut read_fee: R,
+    mut send: S,
+) -> anyhow::Result<Signature>
+where
+    R: FnMut() -> RFut,
+    RFut: Future<Output = anyhow::Result<u64>>,
+    S: FnMut(u64) -> SFut,
+    SFut: Future<Output = anyhow::Result<Signature>>,
+{
+    let fee = read_fee().await?;
+    let err = match send(fee).await {
+        Ok(signature) => return Ok(signature),
+        Err(err) => err,
+    };
+    let updated_fee = read_fee().await?;
+    if updated_fee == fee {
+        return Err(err);
+    }
+    send(updated_fee)
+        .await
+        .with_context(|| format!("retry after fee changed from {fee} to {updated_fee} failed"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+    #[tokio::test]
+    async fn test_retry_on_fee_change() {
+        // the fee is raised from 100 to 250 after the first read
+        let fees = Mutex::new(vec![250, 100]);
+        let sent = Mutex::new(vec![]);
+        let signature = retry_on_fee_change(
+            || async { Ok(fees.lock().unwrap().pop().unwrap_or(250)) },
+            |fee| {
+                sent.lock().unwrap().push(fee);
+                async move {
+                    if fee < 250 {
+                        return Err(anyhow!("insufficient fees"));
+                    }
+                    Ok(Signature::new_unique())
+                }
+            },
+        )
+        .await;
+        assert!(signature.is_ok());
+        assert_eq!(*sent.lock().unwrap(), vec![100, 250]);
+    }
+    #[tokio::test]
+    async fn test_retry_on_fee_change_unchanged_fee() {
+        let sent = Mutex::new(vec![]);
+        let err = retry_on_fee_change(
+            || async { Ok(100) },
+            |fee| {
+                sent.lock().unwrap().push(fee);
+                async { Err(anyhow!("some other failure")) }
+            },
+        )
+        .await
+        .unwrap_err();
+        // the original error is returned without retrying
+        assert_eq!(err.to_string(), "some other failure");
+        assert_eq!(*sent.lock().unwrap(), vec![100]);
+    }
+    #[tokio::test]
+    async fn test_load_bridge_fee() {
+        let mut data = vec![0_u8; 24];
+        data[16..24].copy_from_slice(&2500_u64.to_le_bytes());
+        let mut mocks = std::collections::HashMap::new();
+        mocks.insert(
+            solana_client::rpc_request::RpcRequest::GetAccountInfo,
+            serde_json::json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "data": [solana_sdk::bs58::encode(&data).into_string(), "base58"],
+                    "lamports": 1,
+                    "owner": crate::WORMHOLE_PROGRAM_ID.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                },
+            }),
+        );
+        let rpc = solana_client::nonblocking::rpc_client::RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            mocks,
+        );
+        assert_eq!(load_bridge_fee(&rpc).await.unwrap(), 2500);
+    }
+}
@@ -1,14 +1,40 @@
 use crate::instructions::verify_signature::{
     create_verify_signature_ix, VerifySignaturesData, MAX_LEN_GUARDIAN_KEYS,
 };
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use borsh::BorshDeserialize;
-use solana_program::{instruction::Instruction, pubkey::Pubkey};
-use solana_sdk::transaction::Transaction;
+use solana_program::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    sysvar,
+};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    message::{v0, VersionedMessage},
+    secp256k1_instruction::{
+        HASHED_PUBKEY_SERIALIZED_SIZE, SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_SERIALIZED_SIZE,
+    },
+    signature::{Keypair, Signer},
+    transaction::{Transaction, VersionedTransaction},
+};
 use wormhole_core_bridge_solana::state::GuardianSet;
 use wormhole_explorer_client::{self, endpoints::vaa::ExplorerVaa};
 
-use crate::client::secp256k1_helpers::{make_secp256k1_instruction_data, SecpSignature};
+use crate::instructions::post_vaa::{create_post_vaa_ix, PostVAADataIx};
+use crate::state::signed_vaa::{GuardianSignature, SignedVaa};
+
+use crate::client::secp256k1_helpers::{
+    make_secp256k1_instruction_data, validate_secp_index_consistency, verify_secp_addresses_in_set,
+    SecpSignature,
+};
+use crate::client::{
+    explorer_vaa::posted_vaa_address,
+    posted_vaa::{classify_posted_vaa_account, PostedVaaStatus},
+};
 
 /// contains the start, and end indices of the the signed vaa guardian_set
 /// that are to be used in a verify_signature instruction
@@ -26,6 +52,128 @@ pub struct SignatureBatchParameters {
 #[derive(Clone, Default)]
 pub struct VaaSignatureVerificationBundle {
     pub txs: Vec<Transaction>,
+    /// non-fatal issues found while building the bundle, such as the guardian set
+    /// nearing its expiration
+    pub warnings: Vec<String>,
+}
+
+/// the largest batch size whose secp256k1 + verify_signature transaction fits within the
+/// 1232 byte transaction size limit
+///
+/// every signature adds 128 bytes of secp256k1 instruction data (offsets, signature, recovery
+/// id, eth address and the digest), on top of roughly 450 bytes for the signatures, account
+/// keys and verify_signature instruction, so 6 signatures need 1224 bytes while 7 need 1352.
+/// see `estimate_verification_bundle`, and use a batch size of 5 when adding compute budget
+/// instructions with `with_compute_budget`
+pub const DEFAULT_BATCH_SIZE: usize = 6;
+
+/// returns the batch size to use for `batch_size`, defaulting to `DEFAULT_BATCH_SIZE` and
+/// clamped to `MAX_LEN_GUARDIAN_KEYS`, as a batch never holds more signatures than there are
+/// guardians
+///
+/// larger batch sizes than the default may be usable with versioned transactions and address
+/// lookup tables, while smaller ones reduce the compute used by each transaction
+pub fn resolve_batch_size(batch_size: Option<usize>) -> anyhow::Result<usize> {
+    match batch_size {
+        None => Ok(DEFAULT_BATCH_SIZE),
+        Some(0) => Err(anyhow!("batch size must be at least 1")),
+        Some(batch_size) => Ok(batch_size.min(MAX_LEN_GUARDIAN_KEYS)),
+    }
+}
+
+/// the most signatures a vaa can carry for verification, one per guardian of the largest
+/// guardian set the verify_signature instruction supports
+pub const MAX_VAA_SIGNATURES: usize = MAX_LEN_GUARDIAN_KEYS;
+
+/// returns an error if `signatures` can't be verified against `guardian_set`, because a
+/// guardian index doesn't fit the verify_signature instruction's signers or the guardian set,
+/// or because there are more signatures than guardians
+pub fn ensure_signatures_fit_guardian_set(
+    signatures: &[GuardianSignature],
+    guardian_set: &GuardianSet,
+) -> anyhow::Result<()> {
+    for signature in signatures {
+        let guardian_index = signature.guardian_set_index as usize;
+        if guardian_index >= MAX_LEN_GUARDIAN_KEYS {
+            return Err(anyhow!(
+                "guardian index {guardian_index} exceeds the maximum guardian index {}",
+                MAX_LEN_GUARDIAN_KEYS - 1
+            ));
+        }
+        if guardian_index >= guardian_set.keys.len() {
+            return Err(anyhow!(
+                "guardian index {guardian_index} is out of range for guardian set {} of {} keys",
+                guardian_set.index,
+                guardian_set.keys.len()
+            ));
+        }
+    }
+    let max_signatures = guardian_set.keys.len().min(MAX_VAA_SIGNATURES);
+    if signatures.len() > max_signatures {
+        return Err(anyhow!(
+            "vaa has {} signatures but at most {max_signatures} can be verified against guardian set {}",
+            signatures.len(),
+            guardian_set.index
+        ));
+    }
+    Ok(())
+}
+
+/// compute unit limit recommended for verification bundles, enough for a secp256k1 +
+/// verify_signature transaction verifying a full batch of signatures with headroom
+pub const RECOMMENDED_COMPUTE_UNIT_LIMIT: u32 = 400_000;
+
+/// how long before a guardian set expires the bundle builder starts warning about it
+pub const GUARDIAN_SET_EXPIRY_WARNING_SECS: u32 = 24 * 60 * 60;
+
+/// helpers for checking the lifetime of a guardian set
+pub trait GuardianSetExt {
+    /// returns true if the guardian set expired before `now`, where an expiration
+    /// time of 0 indicates the set is active and never expires
+    fn is_expired(&self, now: u32) -> bool;
+    /// returns true if the guardian set expires within `window_secs` of `now`, including
+    /// sets which have already expired
+    fn expires_within(&self, now: u32, window_secs: u32) -> bool;
+}
+
+impl GuardianSetExt for GuardianSet {
+    fn is_expired(&self, now: u32) -> bool {
+        let expiration_time: u32 = self.expiration_time.into();
+        expiration_time != 0 && expiration_time < now
+    }
+    fn expires_within(&self, now: u32, window_secs: u32) -> bool {
+        let expiration_time: u32 = self.expiration_time.into();
+        expiration_time != 0 && expiration_time <= now.saturating_add(window_secs)
+    }
+}
+
+/// returns an error if `guardian_set` expired before `now`, as the core bridge would
+/// reject any signatures verified against it
+pub fn ensure_guardian_set_active(guardian_set: &GuardianSet, now: u32) -> anyhow::Result<()> {
+    if guardian_set.is_expired(now) {
+        return Err(anyhow!(
+            "guardian set {} expired at timestamp {}",
+            guardian_set.index,
+            u32::from(guardian_set.expiration_time)
+        ));
+    }
+    Ok(())
+}
+
+/// returns a warning if `guardian_set` expires within `window_secs` of `now`, giving relayers
+/// time to switch to the new guardian set
+pub fn guardian_set_expiry_warning(
+    guardian_set: &GuardianSet,
+    now: u32,
+    window_secs: u32,
+) -> Option<String> {
+    guardian_set.expires_within(now, window_secs).then(|| {
+        format!(
+            "guardian set {} expires at timestamp {}",
+            guardian_set.index,
+            u32::from(guardian_set.expiration_time)
+        )
+    })
 }
 
 /// parses a wormhole VAA into the instructions needed to verify it on chain
@@ -37,17 +185,192 @@ pub async fn create_vaa_verification_instructions(
     wormhole_signature_account: Pubkey,
     rpc: &solana_client::nonblocking::rpc_client::RpcClient,
     explorer_vaa: &ExplorerVaa,
-    // the number of signatures that can be batched into a single secp256k1 verification instruction
-    batch_size: usize,
+    // the number of signatures that can be batched into a single secp256k1 verification
+    // instruction, see `resolve_batch_size`
+    batch_size: Option<usize>,
 ) -> anyhow::Result<VaaSignatureVerificationBundle> {
+    create_vaa_verification_instructions_if_needed(
+        payer,
+        wormhole_signature_account,
+        rpc,
+        explorer_vaa,
+        batch_size,
+        false,
+    )
+    .await?
+    .ok_or_else(|| anyhow!("verification bundle was not built"))
+}
+
+/// builds the instructions needed to verify a vaa like `create_vaa_verification_instructions`,
+/// returning `None` without building anything if `skip_if_posted` is set and the vaa is
+/// already posted on-chain, as verifying and posting it again would waste fees and fail
+pub async fn create_vaa_verification_instructions_if_needed(
+    payer: Pubkey,
+    wormhole_signature_account: Pubkey,
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    explorer_vaa: &ExplorerVaa,
+    batch_size: Option<usize>,
+    skip_if_posted: bool,
+) -> anyhow::Result<Option<VaaSignatureVerificationBundle>> {
+    let batch_size = resolve_batch_size(batch_size)?;
+    if skip_if_posted {
+        let posted_vaa = posted_vaa_address(explorer_vaa)?;
+        let account = rpc
+            .get_account_with_commitment(&posted_vaa, rpc.commitment())
+            .await
+            .with_context(|| "failed to get posted vaa account")?
+            .value;
+        if let PostedVaaStatus::Posted(_) = classify_posted_vaa_account(account.as_ref(), false)? {
+            return Ok(None);
+        }
+    }
     let deser_vaa = explorer_vaa.deser_vaa()?;
-    let signature_length = deser_vaa.header.signatures.len();
     let verification_hash = deser_vaa.body.digest();
     let (guardian_set_key, _) =
         crate::utils::derivations::derive_guardian_set(deser_vaa.header.guardian_set_index);
-    let mut guardian_set = load_guardian_set_account(guardian_set_key, rpc).await?;
+    let guardian_set = load_guardian_set_account(guardian_set_key, rpc).await?;
+    // avoid building transactions that will fail on-chain if the vaa was signed by an old set
+    let clock = load_clock(rpc).await?;
+    let now = clock.unix_timestamp as u32;
+    ensure_guardian_set_active(&guardian_set, now)?;
+
+    let signatures = deser_vaa
+        .header
+        .signatures
+        .iter()
+        .map(|signature| GuardianSignature {
+            guardian_set_index: signature.guardian_set_index,
+            signature: signature.signature,
+        })
+        .collect::<Vec<_>>();
 
-    let batches = get_batches(deser_vaa.header.signatures.len(), batch_size);
+    let mut tx_bundle = build_verification_bundle(
+        payer,
+        wormhole_signature_account,
+        &guardian_set,
+        &signatures,
+        verification_hash.0,
+        batch_size,
+    )?;
+    tx_bundle.warnings.extend(guardian_set_expiry_warning(
+        &guardian_set,
+        now,
+        GUARDIAN_SET_EXPIRY_WARNING_SECS,
+    ));
+    Ok(Some(tx_bundle))
+}
+
+/// parses a wormhole VAA into every transaction needed to consume it, the secp256k1 +
+/// verify_signature transactions followed by a final post_vaa transaction, all of which
+/// use `wormhole_signature_account` as the signature set
+///
+/// the returned transactions must be broadcast in order
+pub async fn create_full_vaa_consumption_bundle(
+    payer: Pubkey,
+    wormhole_signature_account: Pubkey,
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    explorer_vaa: &ExplorerVaa,
+    batch_size: Option<usize>,
+) -> anyhow::Result<VaaSignatureVerificationBundle> {
+    let signed_vaa = SignedVaa::parse(&explorer_vaa.vaa)?;
+    let mut tx_bundle = create_vaa_verification_instructions(
+        payer,
+        wormhole_signature_account,
+        rpc,
+        explorer_vaa,
+        batch_size,
+    )
+    .await?;
+    tx_bundle.append_post_vaa_tx(payer, wormhole_signature_account, (&signed_vaa).into())?;
+    Ok(tx_bundle)
+}
+
+/// creates the verification transactions like `create_vaa_verification_instructions`, as
+/// signed v0 transactions loading the non-signer accounts from `lookup_table`, see
+/// `VaaSignatureVerificationBundle::to_v0_transactions`
+pub async fn create_vaa_verification_v0_transactions(
+    payer: &Keypair,
+    signature_set: &Keypair,
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+    explorer_vaa: &ExplorerVaa,
+    batch_size: Option<usize>,
+    lookup_table: &AddressLookupTableAccount,
+) -> anyhow::Result<Vec<VersionedTransaction>> {
+    let bundle = create_vaa_verification_instructions(
+        payer.pubkey(),
+        signature_set.pubkey(),
+        rpc,
+        explorer_vaa,
+        batch_size,
+    )
+    .await?;
+    let recent_blockhash = rpc
+        .get_latest_blockhash()
+        .await
+        .with_context(|| "failed to get latest blockhash")?;
+    bundle.to_v0_transactions(payer, signature_set, lookup_table, recent_blockhash)
+}
+
+/// builds the secp256k1 + verify_signature transactions for a vaa with the given signatures
+/// and body digest, which was signed by `guardian_set`
+///
+/// each transaction verifies one batch of at most `batch_size` signatures, the verify_signature
+/// instruction's `signers[guardian_index]` holds the position of that guardian's signature
+/// within the same transaction's secp256k1 instruction, or -1 if it is not part of the batch
+pub fn build_verification_bundle(
+    payer: Pubkey,
+    wormhole_signature_account: Pubkey,
+    guardian_set: &GuardianSet,
+    signatures: &[GuardianSignature],
+    digest: [u8; 32],
+    batch_size: usize,
+) -> anyhow::Result<VaaSignatureVerificationBundle> {
+    build_bundle(
+        payer,
+        wormhole_signature_account,
+        guardian_set,
+        signatures,
+        digest,
+        batch_size,
+        false,
+    )
+}
+
+/// builds the bundle like `build_verification_bundle`, first recovering the signer of every
+/// signature off-chain (see `SecpSignature::verify_locally`) and failing fast if any does not
+/// match its guardian key, instead of paying for transactions which would fail on-chain
+pub fn build_locally_verified_bundle(
+    payer: Pubkey,
+    wormhole_signature_account: Pubkey,
+    guardian_set: &GuardianSet,
+    signatures: &[GuardianSignature],
+    digest: [u8; 32],
+    batch_size: usize,
+) -> anyhow::Result<VaaSignatureVerificationBundle> {
+    build_bundle(
+        payer,
+        wormhole_signature_account,
+        guardian_set,
+        signatures,
+        digest,
+        batch_size,
+        true,
+    )
+}
+
+fn build_bundle(
+    payer: Pubkey,
+    wormhole_signature_account: Pubkey,
+    guardian_set: &GuardianSet,
+    signatures: &[GuardianSignature],
+    digest: [u8; 32],
+    batch_size: usize,
+    verify_locally: bool,
+) -> anyhow::Result<VaaSignatureVerificationBundle> {
+    ensure_valid_batch_size(batch_size)?;
+    ensure_signatures_fit_guardian_set(signatures, guardian_set)?;
+    let signature_length = signatures.len();
+    let batches = get_batches(signature_length, batch_size);
 
     let mut tx_bundle = VaaSignatureVerificationBundle::new(batches);
 
@@ -57,31 +380,71 @@ pub async fn create_vaa_verification_instructions(
         // that were involved in signing the vaa
         let mut signature_status: [i8; MAX_LEN_GUARDIAN_KEYS] = [-1_i8; MAX_LEN_GUARDIAN_KEYS];
         // holds each individual guardian's signature of the vaa
-        let mut signatures = Vec::with_capacity(batch_size);
+        let mut signatures_batch = Vec::with_capacity(batch_size);
         // public keys of guardians
         let mut guardian_keys = Vec::with_capacity(batch_size);
         // contains signature information in the format needed by the secp256k1 program
         let mut secp_signatures = Vec::with_capacity(batch_size);
         for j in 0..(batch_params.end - batch_params.start) {
-            let guardian_signature = &deser_vaa.header.signatures[j + batch_params.start];
+            // j is the position of the signature within this batch's secp256k1 instruction
+            assert!(j < batch_size);
+            let guardian_signature = &signatures[j + batch_params.start];
+            let guardian_index = guardian_signature.guardian_set_index as usize;
+            // in range, see `ensure_signatures_fit_guardian_set`
+            let guardian_key = guardian_set.keys[guardian_index];
+            // removed guardians are zeroed, and would fail verification on-chain opaquely
+            if guardian_key == [0_u8; 20] {
+                return Err(anyhow!(
+                    "guardian index {guardian_index} has empty key in set {}",
+                    guardian_set.index
+                ));
+            }
             // set the sig verification status based on the index of the guardian
             // in the actual gaurdian_set account, where this is used by the
             // wormhole program verify_signatures function
-            signature_status[guardian_signature.guardian_set_index as usize] = j as i8;
+            signature_status[guardian_index] = j as i8;
             // this sets the signature of the guardian based on the order in which they
             // signed the vaa, this is used for the secp256k1 program instruction
-            signatures.push(guardian_signature.signature);
-            // guardian set keys are stored as a vector and don't need to be used after this, so we can avoid the clone
-            let guardian_key = std::mem::take(
-                &mut guardian_set.keys[guardian_signature.guardian_set_index as usize],
-            );
+            signatures_batch.push(guardian_signature.signature);
             guardian_keys.push(guardian_key);
-            secp_signatures.push(SecpSignature {
-                signature: guardian_signature.raw_sig(),
-                recovery_id: guardian_signature.recovery_id(),
-                eth_address: guardian_key,
-                message: verification_hash.0,
+            // explorers may return ethereum style recovery ids
+            secp_signatures.push(SecpSignature::with_normalized_recovery_id(
+                guardian_signature.raw_sig(),
+                guardian_signature.recovery_id(),
+                guardian_key,
+                digest,
+            ))
+        }
+        // the signers are recovered independently of the loaded guardian set, which catches
+        // vaas checked against the wrong set before anything is sent
+        let recovered_signers = secp_signatures
+            .iter()
+            .zip(&signatures[batch_params.start..batch_params.end])
+            .map(|(secp_signature, guardian_signature)| {
+                let guardian_index = guardian_signature.guardian_set_index;
+                Ok(SecpSignature {
+                    eth_address: secp_signature.recover_eth_address().with_context(|| {
+                        format!("failed to recover guardian {guardian_index} signer")
+                    })?,
+                    ..*secp_signature
+                })
             })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        verify_secp_addresses_in_set(&recovered_signers, guardian_set)?;
+        if verify_locally {
+            for (secp_signature, guardian_signature) in secp_signatures
+                .iter()
+                .zip(&signatures[batch_params.start..batch_params.end])
+            {
+                let guardian_index = guardian_signature.guardian_set_index;
+                if !secp_signature.verify_locally().with_context(|| {
+                    format!("failed to verify guardian {guardian_index} signature")
+                })? {
+                    return Err(anyhow!(
+                        "guardian {guardian_index} signature does not match its key"
+                    ));
+                }
+            }
         }
         // we will always be executing this in instruction index 0 due to requirements of wormhole's verify_signature instruction
         let secp_instruction_data = make_secp256k1_instruction_data(&secp_signatures, 0)?;
@@ -90,22 +453,46 @@ pub async fn create_vaa_verification_instructions(
             &secp_instruction_data,
             vec![],
         );
+        let verify_signatures_data = VerifySignaturesData {
+            signers: signature_status,
+        };
+        verify_signatures_data.validate_against_set(guardian_set.keys.len())?;
         let verify_sig_ix = create_verify_signature_ix(
             payer,
-            deser_vaa.header.guardian_set_index,
+            guardian_set.index,
             wormhole_signature_account,
-            VerifySignaturesData {
-                signers: signature_status,
-            },
+            verify_signatures_data,
         )
         .with_context(|| "failed to create verify_signature instruction")?;
         let tx = Transaction::new_with_payer(&[secp256k1_ix, verify_sig_ix], Some(&payer));
+        validate_secp_index_consistency(&tx)?;
         tx_bundle.txs.push(tx);
     }
 
     Ok(tx_bundle)
 }
 
+/// reconstructs the instructions compiled into `message`
+fn decompile_instructions(message: &Message) -> Vec<Instruction> {
+    message
+        .instructions
+        .iter()
+        .map(|ix| Instruction {
+            program_id: message.account_keys[ix.program_id_index as usize],
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|idx| AccountMeta {
+                    pubkey: message.account_keys[*idx as usize],
+                    is_signer: message.is_signer(*idx as usize),
+                    is_writable: message.is_writable(*idx as usize),
+                })
+                .collect(),
+            data: ix.data.clone(),
+        })
+        .collect()
+}
+
 /// loads the guardian set account which contains the actual public keys
 /// of the guardians that were used to verify sign the VAA
 pub async fn load_guardian_set_account(
@@ -119,12 +506,102 @@ pub async fn load_guardian_set_account(
     GuardianSet::try_from_slice(&account_data[..]).with_context(|| "failed to parse account data")
 }
 
+/// loads the clock sysvar used by the core bridge to check guardian set expiration
+pub async fn load_clock(
+    rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+) -> anyhow::Result<Clock> {
+    let account = rpc
+        .get_account(&sysvar::clock::id())
+        .await
+        .with_context(|| "failed to get clock sysvar")?;
+    solana_sdk::account::from_account(&account).ok_or_else(|| anyhow!("failed to parse clock"))
+}
+
 /// returns the number of batched secp256k1 ix + verify_signature ix that must be
 /// sent before a VAA can be posted
 pub fn get_batches(signature_length: usize, batch_size: usize) -> usize {
     (signature_length as f64 / batch_size as f64).ceil() as usize
 }
 
+/// returns an error for batch sizes which can't be used to build a bundle
+fn ensure_valid_batch_size(batch_size: usize) -> anyhow::Result<()> {
+    // positions within a batch are stored as i8 by the verify_signature instruction
+    if batch_size == 0 || batch_size > i8::MAX as usize {
+        return Err(anyhow!("invalid batch size {batch_size}"));
+    }
+    Ok(())
+}
+
+/// estimated shape of a verification bundle, see `estimate_verification_bundle`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleEstimate {
+    pub num_transactions: usize,
+    /// serialized size of each secp256k1 + verify_signature transaction, in bundle order
+    pub bytes_per_tx: Vec<usize>,
+    pub total_signatures: usize,
+}
+
+impl BundleEstimate {
+    /// returns the combined serialized size of every transaction
+    pub fn total_bytes(&self) -> usize {
+        self.bytes_per_tx.iter().sum()
+    }
+}
+
+/// serialized size of a verification transaction excluding the secp256k1 instruction data
+///
+/// two signatures (payer, signature set), the message header, eight account keys, the recent
+/// blockhash, and the verify_signature instruction with its 6 accounts and 20 bytes of data
+const VERIFY_SIGNATURES_TX_OVERHEAD: usize = (1 + 2 * 64)
+    + 3
+    + (1 + 8 * 32)
+    + 32
+    + 1
+    + (1 + 1)
+    + (1 + 1 + 6 + 1 + 1 + MAX_LEN_GUARDIAN_KEYS);
+
+/// number of bytes used by the compact-u16 encoding of `len`
+fn compact_u16_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+/// estimates the number and serialized size of the transactions `build_verification_bundle`
+/// creates for a vaa with `signature_count` signatures, without needing the signatures or
+/// guardian set
+///
+/// the estimate excludes any compute budget instructions and the post_vaa transaction, and
+/// fails for the same batch sizes as `build_verification_bundle`
+pub fn estimate_verification_bundle(
+    signature_count: usize,
+    batch_size: usize,
+) -> anyhow::Result<BundleEstimate> {
+    ensure_valid_batch_size(batch_size)?;
+    let num_transactions = get_batches(signature_count, batch_size);
+    let bytes_per_tx = (0..num_transactions)
+        .map(|i| {
+            let params = SignatureBatchParameters::new(i, signature_count, batch_size);
+            // see make_secp256k1_instruction_data for the layout
+            let secp_data_len = 1
+                + (params.end - params.start)
+                    * (SIGNATURE_OFFSETS_SERIALIZED_SIZE
+                        + SIGNATURE_SERIALIZED_SIZE
+                        + 1
+                        + HASHED_PUBKEY_SERIALIZED_SIZE
+                        + 32);
+            VERIFY_SIGNATURES_TX_OVERHEAD + compact_u16_len(secp_data_len) + secp_data_len
+        })
+        .collect();
+    Ok(BundleEstimate {
+        num_transactions,
+        bytes_per_tx,
+        total_signatures: signature_count,
+    })
+}
+
 impl SignatureBatchParameters {
     pub fn new(loop_iteration: usize, signature_length: usize, batch_size: usize) -> Self {
         Self {
@@ -138,8 +615,149 @@ impl VaaSignatureVerificationBundle {
     pub fn new(batch_size: usize) -> Self {
         Self {
             txs: Vec::with_capacity(batch_size),
+            warnings: Vec::new(),
         }
     }
+    /// builds the bundle like `build_verification_bundle`, using a freshly generated keypair
+    /// as the signature set account
+    ///
+    /// the signature set is a signer of every verify_signature instruction, so the returned
+    /// keypair must co-sign every transaction, see `sign_all`
+    pub fn with_fresh_signature_set(
+        payer: Pubkey,
+        guardian_set: &GuardianSet,
+        signatures: &[GuardianSignature],
+        digest: [u8; 32],
+        batch_size: usize,
+    ) -> anyhow::Result<(Self, Keypair)> {
+        let signature_set = Keypair::new();
+        let bundle = build_verification_bundle(
+            payer,
+            signature_set.pubkey(),
+            guardian_set,
+            signatures,
+            digest,
+            batch_size,
+        )?;
+        Ok((bundle, signature_set))
+    }
+    /// appends the post_vaa transaction which consumes the signatures verified by the
+    /// preceding transactions into `signature_set`
+    pub fn append_post_vaa_tx(
+        &mut self,
+        payer: Pubkey,
+        signature_set: Pubkey,
+        vaa: PostVAADataIx,
+    ) -> anyhow::Result<()> {
+        let post_vaa_ix = create_post_vaa_ix(vaa, payer, signature_set)
+            .ok_or_else(|| anyhow!("failed to create post_vaa instruction"))?;
+        self.txs
+            .push(Transaction::new_with_payer(&[post_vaa_ix], Some(&payer)));
+        Ok(())
+    }
+    /// prepends a compute unit limit instruction, and a compute unit price instruction if
+    /// `price_micro_lamports` is set, to every transaction in the bundle, replacing any
+    /// compute budget instructions added by a previous call
+    ///
+    /// transactions are rebuilt, so this must be called before signing. the secp256k1
+    /// instructions move back by the prepended instructions, so their signature offsets are
+    /// updated to reference their new position, see `RECOMMENDED_COMPUTE_UNIT_LIMIT`
+    pub fn with_compute_budget(
+        &mut self,
+        units: u32,
+        price_micro_lamports: Option<u64>,
+    ) -> anyhow::Result<()> {
+        for tx in self.txs.iter_mut() {
+            let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(units)];
+            instructions
+                .extend(price_micro_lamports.map(ComputeBudgetInstruction::set_compute_unit_price));
+            instructions.extend(
+                decompile_instructions(&tx.message)
+                    .into_iter()
+                    .filter(|ix| ix.program_id != solana_sdk::compute_budget::ID),
+            );
+            let mut has_secp = false;
+            for (position, ix) in instructions.iter_mut().enumerate() {
+                if ix.program_id != solana_sdk::secp256k1_program::ID {
+                    continue;
+                }
+                has_secp = true;
+                let count = *ix
+                    .data
+                    .first()
+                    .ok_or_else(|| anyhow!("empty secp256k1 instruction"))?
+                    as usize;
+                if ix.data.len() < 1 + count * SIGNATURE_OFFSETS_SERIALIZED_SIZE {
+                    return Err(anyhow!("malformed secp256k1 instruction offsets"));
+                }
+                for offsets in ix.data[1..1 + count * SIGNATURE_OFFSETS_SERIALIZED_SIZE]
+                    .chunks_mut(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+                {
+                    // signature_instruction_index, eth_address_instruction_index, message_instruction_index
+                    offsets[2] = position as u8;
+                    offsets[5] = position as u8;
+                    offsets[10] = position as u8;
+                }
+            }
+            let payer = tx.message.account_keys[0];
+            *tx = Transaction::new_with_payer(&instructions, Some(&payer));
+            if has_secp {
+                validate_secp_index_consistency(tx)?;
+            }
+        }
+        Ok(())
+    }
+    /// signs every transaction with both of its required signers, the fee payer and the
+    /// signature set account
+    ///
+    /// the post_vaa transaction (see `append_post_vaa_tx`) only reads the signature set,
+    /// so it is signed by the payer alone
+    pub fn sign_all(&mut self, payer: &Keypair, signature_set: &Keypair, recent_blockhash: Hash) {
+        for tx in self.txs.iter_mut() {
+            let signers =
+                &tx.message.account_keys[..tx.message.header.num_required_signatures as usize];
+            if signers.contains(&signature_set.pubkey()) {
+                tx.partial_sign(&[payer, signature_set], recent_blockhash);
+            } else {
+                tx.partial_sign(&[payer], recent_blockhash);
+            }
+        }
+    }
+    /// recompiles the transactions as v0 transactions signed by `payer` and `signature_set`,
+    /// loading accounts found in `lookup_table` (ie the guardian set and the instructions,
+    /// rent and system program accounts) through the table instead of the static account keys
+    ///
+    /// signers and invoked programs can't be loaded from a lookup table, so only the other
+    /// accounts are taken from it, saving roughly 90 bytes per verification transaction
+    pub fn to_v0_transactions(
+        &self,
+        payer: &Keypair,
+        signature_set: &Keypair,
+        lookup_table: &AddressLookupTableAccount,
+        recent_blockhash: Hash,
+    ) -> anyhow::Result<Vec<VersionedTransaction>> {
+        self.txs
+            .iter()
+            .map(|tx| {
+                let message = v0::Message::try_compile(
+                    &payer.pubkey(),
+                    &decompile_instructions(&tx.message),
+                    std::slice::from_ref(lookup_table),
+                    recent_blockhash,
+                )
+                .with_context(|| "failed to compile v0 message")?;
+                let message = VersionedMessage::V0(message);
+                let signers = &message.static_account_keys()
+                    [..message.header().num_required_signatures as usize];
+                let tx = if signers.contains(&signature_set.pubkey()) {
+                    VersionedTransaction::try_new(message, &[payer, signature_set])
+                } else {
+                    VersionedTransaction::try_new(message, &[payer])
+                };
+                tx.with_context(|| "failed to sign v0 transaction")
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -150,6 +768,645 @@ mod test {
         let num_batches = get_batches(13, 3);
         assert_eq!(num_batches, 5);
     }
+    #[test]
+    fn test_resolve_batch_size() {
+        use crate::testing::mock_quorum_vaa;
+        assert_eq!(resolve_batch_size(None).unwrap(), DEFAULT_BATCH_SIZE);
+        assert_eq!(
+            resolve_batch_size(Some(100)).unwrap(),
+            MAX_LEN_GUARDIAN_KEYS
+        );
+        assert!(resolve_batch_size(Some(0)).is_err());
+
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        for (batch_size, expected_txs) in [(Some(1), 13), (Some(3), 5), (Some(7), 2), (None, 3)] {
+            let bundle = build_verification_bundle(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                &guardian_set,
+                &vaa.header.signatures,
+                vaa.body.digest(),
+                resolve_batch_size(batch_size).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(bundle.txs.len(), expected_txs);
+            assert_eq!(
+                bundle.txs.len(),
+                get_batches(13, resolve_batch_size(batch_size).unwrap())
+            );
+        }
+    }
+    #[test]
+    fn test_build_verification_bundle_multi_batch() {
+        use crate::testing::mock_quorum_vaa;
+        // signatures are ordered by guardian index, skipping a few guardians
+        let signers = [0, 1, 3, 4, 6, 7, 9, 11, 12, 13, 14, 16, 17];
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &signers);
+        let bundle = build_verification_bundle(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            7,
+        )
+        .unwrap();
+        assert_eq!(bundle.txs.len(), 2);
+
+        let mut seen = [false; MAX_LEN_GUARDIAN_KEYS];
+        for (tx, batch) in bundle.txs.iter().zip(signers.chunks(7)) {
+            let secp_data = &tx.message.instructions[0].data;
+            let verify_data = &tx.message.instructions[1].data;
+            let signer_status = &verify_data[verify_data.len() - MAX_LEN_GUARDIAN_KEYS..];
+            assert_eq!(secp_data[0] as usize, batch.len());
+            for (guardian_index, status) in signer_status.iter().enumerate() {
+                let position = *status as i8;
+                if position < 0 {
+                    assert!(!batch.contains(&(guardian_index as u8)));
+                    continue;
+                }
+                // every guardian is verified by exactly one transaction
+                assert!(!std::mem::replace(&mut seen[guardian_index], true));
+                // the position must point at this guardian's address in the secp instruction
+                let offsets = 1 + position as usize * 11;
+                let eth_address_offset =
+                    u16::from_le_bytes([secp_data[offsets + 3], secp_data[offsets + 4]]) as usize;
+                assert_eq!(
+                    secp_data[eth_address_offset..eth_address_offset + 20],
+                    guardian_set.keys[guardian_index]
+                );
+                assert_eq!(batch[position as usize] as usize, guardian_index);
+            }
+        }
+        for (guardian_index, seen) in seen.iter().enumerate() {
+            assert_eq!(*seen, signers.contains(&(guardian_index as u8)));
+        }
+    }
+    #[test]
+    fn test_estimate_verification_bundle() {
+        use crate::testing::mock_quorum_vaa;
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        let payer = Keypair::new();
+        let (mut bundle, signature_set) = VaaSignatureVerificationBundle::with_fresh_signature_set(
+            payer.pubkey(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            7,
+        )
+        .unwrap();
+        bundle.sign_all(&payer, &signature_set, Hash::new_unique());
+
+        let estimate = estimate_verification_bundle(13, 7).unwrap();
+        assert_eq!(estimate.num_transactions, bundle.txs.len());
+        assert_eq!(estimate.total_signatures, 13);
+        for (estimated, tx) in estimate.bytes_per_tx.iter().zip(&bundle.txs) {
+            let actual = bincode::serialize(tx).unwrap().len();
+            assert!(estimated.abs_diff(actual) <= 8, "{estimated} != {actual}");
+        }
+        assert!(estimate.bytes_per_tx[0] > estimate.bytes_per_tx[1]);
+        assert_eq!(
+            estimate.total_bytes(),
+            estimate.bytes_per_tx[0] + estimate.bytes_per_tx[1]
+        );
+        assert_eq!(
+            estimate_verification_bundle(13, 0).unwrap_err().to_string(),
+            "invalid batch size 0"
+        );
+        assert!(estimate_verification_bundle(13, 128).is_err());
+    }
+    #[test]
+    fn test_default_batch_size() {
+        let fits = |batch_size| {
+            estimate_verification_bundle(batch_size, batch_size)
+                .unwrap()
+                .bytes_per_tx[0]
+                <= solana_sdk::packet::PACKET_DATA_SIZE
+        };
+        assert!(fits(DEFAULT_BATCH_SIZE));
+        assert!(!fits(DEFAULT_BATCH_SIZE + 1));
+    }
+    #[test]
+    fn test_build_verification_bundle_ethereum_recovery_ids() {
+        use crate::testing::mock_quorum_vaa;
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        let mut ethereum_signatures = vaa.header.signatures.clone();
+        for signature in &mut ethereum_signatures {
+            signature.signature[64] += 27;
+        }
+        let (payer, signature_set) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let build = |signatures: &[GuardianSignature]| {
+            build_verification_bundle(
+                payer,
+                signature_set,
+                &guardian_set,
+                signatures,
+                vaa.body.digest(),
+                DEFAULT_BATCH_SIZE,
+            )
+            .unwrap()
+        };
+        // 27/28 recovery ids produce the same instructions as 0/1
+        for (tx, expected) in build(&ethereum_signatures)
+            .txs
+            .iter()
+            .zip(build(&vaa.header.signatures).txs)
+        {
+            assert_eq!(tx.message, expected.message);
+        }
+    }
+    #[test]
+    fn test_build_verification_bundle_index_out_of_range() {
+        use crate::testing::{mock_guardian_set, mock_guardian_signed_vaa, mock_guardians};
+        let guardians = mock_guardians(19);
+        // the vaa was signed by guardian 13, which is missing from the loaded set
+        let guardian_set = mock_guardian_set(
+            3,
+            guardians[..13]
+                .iter()
+                .map(|guardian| guardian.eth_address)
+                .collect(),
+            0,
+        );
+        let vaa = mock_guardian_signed_vaa(3, &guardians, &(0..14).collect::<Vec<_>>());
+        let err = build_verification_bundle(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            DEFAULT_BATCH_SIZE,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "guardian index 13 is out of range for guardian set 3 of 13 keys"
+        );
+    }
+    #[test]
+    fn test_build_verification_bundle_signature_limits() {
+        use crate::testing::{mock_guardian_set, mock_guardian_signed_vaa, mock_quorum_vaa};
+        // a guardian set larger than the verify_signature instruction supports, which would
+        // index past the end of the signers
+        let (guardians, guardian_set, vaa) = mock_quorum_vaa(3, 21, &[0, 1, 20]);
+        let err = build_verification_bundle(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            DEFAULT_BATCH_SIZE,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "guardian index 20 exceeds the maximum guardian index 18"
+        );
+
+        let guardian_set = mock_guardian_set(
+            3,
+            guardians[..13]
+                .iter()
+                .map(|guardian| guardian.eth_address)
+                .collect(),
+            0,
+        );
+        let mut vaa = mock_guardian_signed_vaa(3, &guardians, &(0..13).collect::<Vec<_>>());
+        assert!(ensure_signatures_fit_guardian_set(&vaa.header.signatures, &guardian_set).is_ok());
+        // a duplicated signature exceeds the size of the guardian set
+        vaa.header.signatures.push(vaa.header.signatures[0]);
+        let err = build_verification_bundle(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            DEFAULT_BATCH_SIZE,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "vaa has 14 signatures but at most 13 can be verified against guardian set 3"
+        );
+    }
+    #[test]
+    fn test_build_verification_bundle_empty_guardian_key() {
+        use crate::testing::{mock_guardian_set, mock_guardian_signed_vaa, mock_guardians};
+        let guardians = mock_guardians(19);
+        let mut keys = guardians
+            .iter()
+            .map(|guardian| guardian.eth_address)
+            .collect::<Vec<_>>();
+        keys[4] = [0_u8; 20];
+        let guardian_set = mock_guardian_set(3, keys, 0);
+        let vaa = mock_guardian_signed_vaa(3, &guardians, &(0..13).collect::<Vec<_>>());
+        let err = build_verification_bundle(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            DEFAULT_BATCH_SIZE,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "guardian index 4 has empty key in set 3");
+    }
+    #[test]
+    fn test_build_verification_bundle_wrong_guardian_set() {
+        use crate::client::secp256k1_helpers::UnknownGuardianAddress;
+        use crate::testing::{mock_guardian_set, mock_guardian_signed_vaa, mock_guardians};
+        let guardians = mock_guardians(38);
+        // the vaa was signed by the first 19 guardians, but a set with the same index and
+        // size holding the keys of the other 19 was loaded
+        let guardian_set = mock_guardian_set(
+            3,
+            guardians[19..]
+                .iter()
+                .map(|guardian| guardian.eth_address)
+                .collect(),
+            0,
+        );
+        let vaa = mock_guardian_signed_vaa(3, &guardians[..19], &(0..13).collect::<Vec<_>>());
+        let err = build_verification_bundle(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            DEFAULT_BATCH_SIZE,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            UnknownGuardianAddress {
+                eth_address: guardians[0].eth_address,
+                guardian_set_index: 3,
+            }
+            .to_string()
+        );
+    }
+    #[test]
+    fn test_build_locally_verified_bundle() {
+        use crate::testing::mock_quorum_vaa;
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        let build = |signatures: &[GuardianSignature]| {
+            build_locally_verified_bundle(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                &guardian_set,
+                signatures,
+                vaa.body.digest(),
+                DEFAULT_BATCH_SIZE,
+            )
+        };
+        assert_eq!(build(&vaa.header.signatures).unwrap().txs.len(), 3);
+
+        // guardian 8 signed with guardian 9's key
+        let mut signatures = vaa.header.signatures.clone();
+        signatures[8].signature = signatures[9].signature;
+        assert_eq!(
+            build(&signatures).err().unwrap().to_string(),
+            "guardian 8 signature does not match its key"
+        );
+        // the unchecked builder doesn't notice
+        assert!(build_verification_bundle(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guardian_set,
+            &signatures,
+            vaa.body.digest(),
+            DEFAULT_BATCH_SIZE,
+        )
+        .is_ok());
+    }
+    #[test]
+    fn test_sign_all() {
+        use crate::testing::mock_quorum_vaa;
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        let payer = Keypair::new();
+        let (mut bundle, signature_set) = VaaSignatureVerificationBundle::with_fresh_signature_set(
+            payer.pubkey(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            7,
+        )
+        .unwrap();
+        assert_eq!(bundle.txs.len(), 2);
+        assert!(bundle.txs.iter().all(|tx| !tx.is_signed()));
+
+        bundle.sign_all(&payer, &signature_set, Hash::new_unique());
+        for tx in &bundle.txs {
+            assert!(tx.is_signed());
+            assert!(tx.verify().is_ok());
+            let signers =
+                &tx.message.account_keys[..tx.message.header.num_required_signatures as usize];
+            assert_eq!(signers.len(), 2);
+            assert!(signers.contains(&payer.pubkey()));
+            assert!(signers.contains(&signature_set.pubkey()));
+        }
+    }
+    #[test]
+    fn test_with_compute_budget() {
+        use crate::testing::mock_quorum_vaa;
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        let payer = Keypair::new();
+        let (mut bundle, signature_set) = VaaSignatureVerificationBundle::with_fresh_signature_set(
+            payer.pubkey(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            7,
+        )
+        .unwrap();
+        bundle
+            .append_post_vaa_tx(payer.pubkey(), signature_set.pubkey(), (&vaa).into())
+            .unwrap();
+        let original = bundle.clone();
+
+        bundle
+            .with_compute_budget(RECOMMENDED_COMPUTE_UNIT_LIMIT, None)
+            .unwrap();
+        // calling again replaces the previous compute budget instructions
+        bundle
+            .with_compute_budget(RECOMMENDED_COMPUTE_UNIT_LIMIT, Some(1_000))
+            .unwrap();
+        assert_eq!(bundle.txs.len(), 3);
+        for (tx, original) in bundle.txs.iter().zip(&original.txs) {
+            let program_id = |ix: &solana_sdk::instruction::CompiledInstruction| {
+                tx.message.account_keys[ix.program_id_index as usize]
+            };
+            let instructions = &tx.message.instructions;
+            assert_eq!(instructions.len(), original.message.instructions.len() + 2);
+            assert_eq!(program_id(&instructions[0]), solana_sdk::compute_budget::ID);
+            assert_eq!(program_id(&instructions[1]), solana_sdk::compute_budget::ID);
+            assert_eq!(
+                decompile_instructions(&tx.message)[..2],
+                [
+                    ComputeBudgetInstruction::set_compute_unit_limit(
+                        RECOMMENDED_COMPUTE_UNIT_LIMIT
+                    ),
+                    ComputeBudgetInstruction::set_compute_unit_price(1_000),
+                ]
+            );
+            // the original instructions follow unchanged, apart from the secp256k1 offsets
+            for (ix, original_ix) in decompile_instructions(&tx.message)[2..]
+                .iter()
+                .zip(decompile_instructions(&original.message))
+            {
+                assert_eq!(ix.program_id, original_ix.program_id);
+                assert_eq!(ix.accounts, original_ix.accounts);
+                if ix.program_id != solana_sdk::secp256k1_program::ID {
+                    assert_eq!(ix.data, original_ix.data);
+                }
+            }
+        }
+        for tx in &bundle.txs[..2] {
+            assert_eq!(validate_secp_index_consistency(tx), Ok(()));
+        }
+        bundle.sign_all(&payer, &signature_set, Hash::new_unique());
+        assert!(bundle.txs.iter().all(|tx| tx.verify().is_ok()));
+    }
+    #[test]
+    fn test_to_v0_transactions() {
+        use crate::testing::mock_quorum_vaa;
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        let payer = Keypair::new();
+        let (mut bundle, signature_set) = VaaSignatureVerificationBundle::with_fresh_signature_set(
+            payer.pubkey(),
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            DEFAULT_BATCH_SIZE,
+        )
+        .unwrap();
+        bundle
+            .append_post_vaa_tx(payer.pubkey(), signature_set.pubkey(), (&vaa).into())
+            .unwrap();
+        let table_accounts = vec![
+            crate::utils::derivations::derive_guardian_set(3).0,
+            sysvar::instructions::id(),
+            sysvar::rent::id(),
+            solana_program::system_program::id(),
+        ];
+        let lookup_table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: table_accounts.clone(),
+        };
+        let txs = bundle
+            .to_v0_transactions(&payer, &signature_set, &lookup_table, Hash::new_unique())
+            .unwrap();
+        assert_eq!(txs.len(), bundle.txs.len());
+        for (tx, legacy) in txs.iter().zip(&bundle.txs[..3]) {
+            assert!(tx.verify_with_results().iter().all(|verified| *verified));
+            let VersionedMessage::V0(message) = &tx.message else {
+                panic!("expected a v0 message");
+            };
+            // the static accounts are loaded through the lookup table
+            assert_eq!(message.address_table_lookups.len(), 1);
+            let lookup = &message.address_table_lookups[0];
+            assert_eq!(lookup.account_key, lookup_table.key);
+            assert!(lookup.writable_indexes.is_empty());
+            let mut readonly_indexes = lookup.readonly_indexes.clone();
+            readonly_indexes.sort();
+            assert_eq!(readonly_indexes, vec![0, 1, 2, 3]);
+            for account in &table_accounts {
+                assert!(!message.account_keys.contains(account));
+            }
+            assert_eq!(
+                message.account_keys,
+                vec![
+                    payer.pubkey(),
+                    signature_set.pubkey(),
+                    solana_sdk::secp256k1_program::ID,
+                    crate::WORMHOLE_PROGRAM_ID,
+                ]
+            );
+            // the secp256k1 instruction must stay first for verify_signatures to find it
+            assert_eq!(message.instructions[0].program_id_index, 2);
+            assert_eq!(
+                message.instructions[0].data,
+                legacy.message.instructions[0].data
+            );
+            assert!(
+                bincode::serialize(tx).unwrap().len() + 64
+                    < bincode::serialize(legacy).unwrap().len()
+            );
+        }
+    }
+    #[test]
+    fn test_append_post_vaa_tx() {
+        use crate::testing::mock_quorum_vaa;
+        let (_, guardian_set, vaa) = mock_quorum_vaa(3, 19, &(0..13).collect::<Vec<_>>());
+        let payer = Pubkey::new_unique();
+        let (mut bundle, signature_set) = VaaSignatureVerificationBundle::with_fresh_signature_set(
+            payer,
+            &guardian_set,
+            &vaa.header.signatures,
+            vaa.body.digest(),
+            7,
+        )
+        .unwrap();
+        bundle
+            .append_post_vaa_tx(payer, signature_set.pubkey(), (&vaa).into())
+            .unwrap();
+        assert_eq!(bundle.txs.len(), 3);
+
+        let expected = create_post_vaa_ix((&vaa).into(), payer, signature_set.pubkey()).unwrap();
+        let tx = bundle.txs.last().unwrap();
+        assert_eq!(tx.message.instructions.len(), 1);
+        let ix = &tx.message.instructions[0];
+        assert_eq!(
+            tx.message.account_keys[ix.program_id_index as usize],
+            expected.program_id
+        );
+        let accounts = ix
+            .accounts
+            .iter()
+            .map(|idx| tx.message.account_keys[*idx as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            accounts,
+            expected
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(ix.data, expected.data);
+        // the post_vaa tx consumes the signature set written by the verify_signature txs
+        for tx in &bundle.txs[..2] {
+            assert!(tx.message.account_keys.contains(&signature_set.pubkey()));
+        }
+    }
+    #[test]
+    fn test_sign_all_with_post_vaa_tx() {
+        let payer = Keypair::new();
+        let signature_set = Keypair::new();
+        let mut bundle = VaaSignatureVerificationBundle::new(1);
+        bundle
+            .append_post_vaa_tx(
+                payer.pubkey(),
+                signature_set.pubkey(),
+                (&crate::testing::mock_guardian_signed_vaa(3, &[], &[])).into(),
+            )
+            .unwrap();
+        bundle.sign_all(&payer, &signature_set, Hash::new_unique());
+        assert!(bundle.txs[0].verify().is_ok());
+    }
+    #[test]
+    fn test_guardian_set_expiration() {
+        let active = crate::testing::mock_guardian_set(3, vec![[1_u8; 20]], 0);
+        assert!(!active.is_expired(u32::MAX));
+        assert!(ensure_guardian_set_active(&active, u32::MAX).is_ok());
+
+        let expired = crate::testing::mock_guardian_set(2, vec![[1_u8; 20]], 1_700_000_000);
+        assert!(!expired.is_expired(1_700_000_000));
+        assert!(expired.is_expired(1_700_000_001));
+        assert!(ensure_guardian_set_active(&expired, 1_600_000_000).is_ok());
+        assert_eq!(
+            ensure_guardian_set_active(&expired, 1_700_000_001)
+                .unwrap_err()
+                .to_string(),
+            "guardian set 2 expired at timestamp 1700000000"
+        );
+    }
+    #[test]
+    fn test_guardian_set_expires_within() {
+        let day = GUARDIAN_SET_EXPIRY_WARNING_SECS;
+        let active = crate::testing::mock_guardian_set(3, vec![[1_u8; 20]], 0);
+        assert!(!active.expires_within(u32::MAX, day));
+        assert_eq!(guardian_set_expiry_warning(&active, u32::MAX, day), None);
+
+        let expiring = crate::testing::mock_guardian_set(2, vec![[1_u8; 20]], 1_700_000_000);
+        // outside the window
+        assert!(!expiring.expires_within(1_700_000_000 - day - 1, day));
+        assert_eq!(
+            guardian_set_expiry_warning(&expiring, 1_700_000_000 - day - 1, day),
+            None
+        );
+        // inside the window
+        assert!(expiring.expires_within(1_700_000_000 - day, day));
+        assert!(expiring.expires_within(1_700_000_000 - 60, day));
+        assert_eq!(
+            guardian_set_expiry_warning(&expiring, 1_700_000_000 - 60, day).unwrap(),
+            "guardian set 2 expires at timestamp 1700000000"
+        );
+        // already expired
+        assert!(expiring.expires_within(1_700_000_001, day));
+    }
+    #[tokio::test]
+    async fn test_create_vaa_verification_instructions_if_needed() {
+        use borsh::BorshSerialize;
+        let explorer_vaa: ExplorerVaa = serde_json::from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/explorer/ethereum_token_transfer.json"
+        )))
+        .unwrap();
+        let mock_rpc = |account: serde_json::Value| {
+            let mut mocks = std::collections::HashMap::new();
+            mocks.insert(
+                solana_client::rpc_request::RpcRequest::GetAccountInfo,
+                serde_json::json!({ "context": { "slot": 1 }, "value": account }),
+            );
+            solana_client::nonblocking::rpc_client::RpcClient::new_mock_with_mocks(
+                "succeeds".to_string(),
+                mocks,
+            )
+        };
+        let posted_vaa = crate::state::vaa::PostedVAAData::new(Default::default());
+        let rpc = mock_rpc(serde_json::json!({
+            "data": [
+                solana_sdk::bs58::encode(posted_vaa.try_to_vec().unwrap()).into_string(),
+                "base58"
+            ],
+            "lamports": 1,
+            "owner": crate::WORMHOLE_PROGRAM_ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        }));
+        let (payer, signature_set) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // the vaa is already posted, so nothing is built
+        assert!(create_vaa_verification_instructions_if_needed(
+            payer,
+            signature_set,
+            &rpc,
+            &explorer_vaa,
+            None,
+            true,
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+        // otherwise the guardian set is loaded to build the bundle. the mock rpc only answers
+        // the posted vaa lookup, so building fails loading the guardian set rather than
+        // short-circuiting or failing the posted vaa lookup
+        let rpc = mock_rpc(serde_json::Value::Null);
+        let err = create_vaa_verification_instructions_if_needed(
+            payer,
+            signature_set,
+            &rpc,
+            &explorer_vaa,
+            None,
+            true,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "failed to get account data");
+        let (guardian_set_key, _) =
+            crate::utils::derivations::derive_guardian_set(explorer_vaa.guardian_set_index);
+        assert!(err
+            .root_cause()
+            .to_string()
+            .contains(&guardian_set_key.to_string()));
+    }
     #[tokio::test]
     async fn test_load_guardian_set_account() {
         let rpc = solana_client::nonblocking::rpc_client::RpcClient::new("..".to_string());
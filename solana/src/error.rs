@@ -0,0 +1,86 @@
+use solana_program::{log::sol_log, program_error::ProgramError};
+
+/// errors returned by the on-chain instructions, surfaced to callers as `ProgramError::Custom`
+///
+/// discriminants are stable and must not be reordered, clients match on the numeric code
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[repr(u32)]
+pub enum WormholeLiteError {
+    #[error("invalid emitter")]
+    InvalidEmitter = 0,
+    #[error("invalid sequence")]
+    InvalidSequence = 1,
+    #[error("invalid message")]
+    InvalidMessage = 2,
+    #[error("invalid clock")]
+    InvalidClock = 3,
+    #[error("invalid rent")]
+    InvalidRent = 4,
+    #[error("invalid system program")]
+    InvalidSystemProgram = 5,
+    #[error("invalid core bridge program")]
+    InvalidCoreBridgeProgram = 6,
+    #[error("invalid emitter owner")]
+    InvalidEmitterOwner = 7,
+    #[error("invalid bridge config owner")]
+    InvalidBridgeConfigOwner = 8,
+    #[error("account already in use")]
+    EmitterAlreadyInitialized = 9,
+    #[error("account not rent exempt")]
+    EmitterNotRentExempt = 10,
+    #[error("emitter publishable nonce overflow")]
+    NonceOverflow = 11,
+    #[error("invalid bridge config")]
+    InvalidBridgeConfig = 12,
+    #[error("invalid guardian set")]
+    InvalidGuardianSet = 13,
+    #[error("invalid guardian set owner")]
+    InvalidGuardianSetOwner = 14,
+    #[error("invalid posted vaa")]
+    InvalidPostedVaa = 15,
+    #[error("message account already in use")]
+    MessageAccountInUse = 16,
+    #[error("emitter padding is not zeroed")]
+    InvalidEmitterPadding = 17,
+    #[error("vaa already claimed")]
+    VaaAlreadyClaimed = 18,
+    #[error("invalid claim")]
+    InvalidClaim = 19,
+    #[error("fee is less than the core bridge message fee")]
+    InsufficientFee = 20,
+}
+
+impl WormholeLiteError {
+    /// numeric code carried by `ProgramError::Custom`
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+    /// logs the error message along with its code, returning the error for convenience
+    pub fn log(self) -> Self {
+        sol_log(&format!("{self} (error code {})", self.code()));
+        self
+    }
+}
+
+impl From<WormholeLiteError> for ProgramError {
+    fn from(value: WormholeLiteError) -> Self {
+        ProgramError::Custom(value.code())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(
+            ProgramError::from(WormholeLiteError::InvalidEmitter),
+            ProgramError::Custom(0)
+        );
+        assert_eq!(
+            ProgramError::from(WormholeLiteError::NonceOverflow),
+            ProgramError::Custom(11)
+        );
+        assert_eq!(WormholeLiteError::InvalidClock.log().code(), 3);
+    }
+}
@@ -0,0 +1,251 @@
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::AccountMeta,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+
+use crate::{error::WormholeLiteError, state::claim::Claim, utils::derivations::derive_claim};
+
+pub struct TransactionAccountKeys {
+    /// account used to pay for the claim account
+    pub payer: Pubkey,
+    /// the claim account, see `derive_claim`
+    pub claim: Pubkey,
+    /// system program
+    pub system_program: Pubkey,
+}
+
+impl TransactionAccountKeys {
+    /// returns the keys used by `program_id` to claim the vaa emitted by
+    /// `emitter_chain`/`emitter_address` with `sequence`
+    pub fn for_vaa(
+        program_id: Pubkey,
+        payer: Pubkey,
+        emitter_chain: u16,
+        emitter_address: &[u8; 32],
+        sequence: u64,
+    ) -> Self {
+        Self {
+            payer,
+            claim: derive_claim(program_id, emitter_chain, emitter_address, sequence).0,
+            system_program: system_program::id(),
+        }
+    }
+    /// returns a vector of AccountMeta objects for sending a tx from an rpc client
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(self.claim, false),
+            AccountMeta::new_readonly(self.system_program, false),
+        ]
+    }
+}
+
+/// on-chain object pointing to the accounts used to claim a vaa
+pub struct ClaimVaaAccounts<'info> {
+    pub payer: AccountInfo<'info>,
+    pub claim: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+impl<'info> TryFrom<&[AccountInfo<'info>]> for ClaimVaaAccounts<'info> {
+    type Error = ProgramError;
+    /// returns `ProgramError::NotEnoughAccountKeys` if fewer than 3 accounts are given
+    fn try_from(value: &[AccountInfo<'info>]) -> Result<Self, Self::Error> {
+        let account = |index: usize| {
+            value
+                .get(index)
+                .cloned()
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        Ok(Self {
+            payer: account(0)?,
+            claim: account(1)?,
+            system_program: account(2)?,
+        })
+    }
+}
+
+impl<'info> ClaimVaaAccounts<'info> {
+    pub fn validate(&self, expected_pda: Pubkey, program_id: Pubkey) -> bool {
+        self.try_validate(expected_pda, program_id).is_ok()
+    }
+    /// validates the accounts, returning `WormholeLiteError::VaaAlreadyClaimed` if the claim
+    /// account already exists
+    pub fn try_validate(
+        &self,
+        expected_pda: Pubkey,
+        program_id: Pubkey,
+    ) -> Result<(), WormholeLiteError> {
+        if self.claim.key.ne(&expected_pda) {
+            return Err(WormholeLiteError::InvalidClaim.log());
+        }
+        if self.system_program.key.ne(&system_program::id()) {
+            return Err(WormholeLiteError::InvalidSystemProgram.log());
+        }
+        if self.claim.owner.eq(&program_id) || !self.claim.data_is_empty() {
+            return Err(WormholeLiteError::VaaAlreadyClaimed.log());
+        }
+        Ok(())
+    }
+}
+
+/// marks the vaa emitted by `emitter_chain`/`emitter_address` with `sequence` as consumed by
+/// creating its claim account, failing with `WormholeLiteError::VaaAlreadyClaimed` if the vaa
+/// was claimed before
+///
+/// the vaa itself isn't verified, callers must check it was posted by the core bridge and
+/// claim it in the same instruction which processes it
+pub fn claim_vaa<'info>(
+    program_id: Pubkey,
+    accounts: &[AccountInfo<'info>],
+    emitter_chain: u16,
+    emitter_address: [u8; 32],
+    sequence: u64,
+) -> ProgramResult {
+    let account_infos = ClaimVaaAccounts::try_from(accounts)?;
+    let claim = Claim {
+        emitter_chain,
+        emitter_address,
+        sequence,
+    };
+    let (claim_pda, claim_nonce) = claim.derive(program_id);
+
+    account_infos.try_validate(claim_pda, program_id)?;
+
+    let claim_seeds: &[&[u8]] = &[
+        b"claim",
+        &emitter_chain.to_le_bytes(),
+        &emitter_address,
+        &sequence.to_le_bytes(),
+        &[claim_nonce],
+    ];
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(Claim::LEN);
+    let current_lamports = account_infos.claim.lamports();
+    if current_lamports == 0 {
+        invoke_signed(
+            &system_instruction::create_account(
+                account_infos.payer.key,
+                account_infos.claim.key,
+                lamports,
+                Claim::LEN as u64,
+                &program_id,
+            ),
+            &[account_infos.payer.clone(), account_infos.claim.clone()],
+            &[claim_seeds],
+        )?;
+    } else {
+        // create_account fails on funded accounts, which would let anyone block a claim by
+        // transferring lamports to the pda
+        if current_lamports < lamports {
+            invoke(
+                &system_instruction::transfer(
+                    account_infos.payer.key,
+                    account_infos.claim.key,
+                    lamports - current_lamports,
+                ),
+                &[account_infos.payer.clone(), account_infos.claim.clone()],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(account_infos.claim.key, Claim::LEN as u64),
+            std::slice::from_ref(&account_infos.claim),
+            &[claim_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(account_infos.claim.key, &program_id),
+            std::slice::from_ref(&account_infos.claim),
+            &[claim_seeds],
+        )?;
+    }
+    Claim::pack(claim, &mut account_infos.claim.data.borrow_mut())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_transaction_account_keys() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let accts = TransactionAccountKeys::for_vaa(program_id, payer, 2, &[7_u8; 32], 69);
+        assert_eq!(accts.claim, derive_claim(program_id, 2, &[7_u8; 32], 69).0);
+        assert_eq!(
+            accts.to_account_metas(),
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(accts.claim, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ]
+        );
+    }
+    #[test]
+    fn test_claimed_vaa_rejected() {
+        let program_id = Pubkey::new_unique();
+        let sys_id = system_program::id();
+        let accts =
+            TransactionAccountKeys::for_vaa(program_id, Pubkey::new_unique(), 2, &[7_u8; 32], 69);
+        let mut lamports = [1_000_000_u64; 3];
+        let mut data: [Vec<u8>; 3] = Default::default();
+        data[1] = vec![0_u8; Claim::LEN];
+        let [payer_lamports, claim_lamports, system_lamports] = &mut lamports;
+        let [payer_data, claim_data, system_data] = &mut data;
+        let account_infos = vec![
+            AccountInfo::new(
+                &accts.payer,
+                true,
+                true,
+                payer_lamports,
+                payer_data,
+                &sys_id,
+                false,
+                0,
+            ),
+            // a claim account created by an earlier claim
+            AccountInfo::new(
+                &accts.claim,
+                false,
+                true,
+                claim_lamports,
+                claim_data,
+                &program_id,
+                false,
+                0,
+            ),
+            AccountInfo::new(
+                &accts.system_program,
+                false,
+                false,
+                system_lamports,
+                system_data,
+                &sys_id,
+                true,
+                0,
+            ),
+        ];
+        assert_eq!(
+            claim_vaa(program_id, &account_infos, 2, [7_u8; 32], 69),
+            Err(WormholeLiteError::VaaAlreadyClaimed.into())
+        );
+        // the claim of a different vaa is not the claim account
+        assert_eq!(
+            claim_vaa(program_id, &account_infos, 2, [7_u8; 32], 70),
+            Err(WormholeLiteError::InvalidClaim.into())
+        );
+        assert_eq!(
+            claim_vaa(program_id, &account_infos[..2], 2, [7_u8; 32], 69),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+        let accounts = ClaimVaaAccounts::try_from(&account_infos[..]).unwrap();
+        assert!(!accounts.validate(accts.claim, program_id));
+    }
+}
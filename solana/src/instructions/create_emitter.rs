@@ -2,7 +2,6 @@ use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
     instruction::AccountMeta,
-    log::sol_log,
     program::invoke_signed,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack},
@@ -12,7 +11,7 @@ use solana_program::{
     sysvar::Sysvar,
 };
 
-use crate::state::emitter::Emitter;
+use crate::{error::WormholeLiteError, state::emitter::Emitter};
 
 pub struct TransactionAccountKeys {
     /// account used to pay for fees
@@ -40,13 +39,21 @@ pub struct InitializeEmitterAccounts<'info> {
     pub system_program: AccountInfo<'info>,
 }
 
-impl<'info> From<&[AccountInfo<'info>]> for InitializeEmitterAccounts<'info> {
-    fn from(value: &[AccountInfo<'info>]) -> Self {
-        Self {
-            payer: value.get(0).unwrap().clone(),
-            emitter: value.get(1).unwrap().clone(),
-            system_program: value.get(2).unwrap().clone(),
-        }
+impl<'info> TryFrom<&[AccountInfo<'info>]> for InitializeEmitterAccounts<'info> {
+    type Error = ProgramError;
+    /// returns `ProgramError::NotEnoughAccountKeys` if fewer than 3 accounts are given
+    fn try_from(value: &[AccountInfo<'info>]) -> Result<Self, Self::Error> {
+        let account = |index: usize| {
+            value
+                .get(index)
+                .cloned()
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        Ok(Self {
+            payer: account(0)?,
+            emitter: account(1)?,
+            system_program: account(2)?,
+        })
     }
 }
 
@@ -61,21 +68,22 @@ impl<'info> From<InitializeEmitterAccounts<'info>> for TransactionAccountKeys {
 }
 
 impl<'info> InitializeEmitterAccounts<'info> {
+    /// constructs the accounts from the start of `value`, panicking if the slice is too short
+    #[deprecated(note = "use `InitializeEmitterAccounts::try_from` instead")]
+    pub fn from(value: &[AccountInfo<'info>]) -> Self {
+        Self::try_from(value).unwrap()
+    }
     pub fn validate(&self, expected_pda: Pubkey) -> bool {
+        self.try_validate(expected_pda).is_ok()
+    }
+    pub fn try_validate(&self, expected_pda: Pubkey) -> Result<(), WormholeLiteError> {
         if self.emitter.key.ne(&expected_pda) {
-            sol_log("invalid emitter");
-            return false;
+            return Err(WormholeLiteError::InvalidEmitter.log());
         }
         if self.system_program.key.ne(&system_program::id()) {
-            sol_log("invalid system program");
-            return false;
-        }
-        return true;
-    }
-    pub fn try_validate(&self, expected_pda: Pubkey) {
-        if !self.validate(expected_pda) {
-            panic!("validation failed");
+            return Err(WormholeLiteError::InvalidSystemProgram.log());
         }
+        Ok(())
     }
 }
 
@@ -83,12 +91,52 @@ pub fn initialize_emitter<'info>(
     program_id: Pubkey,
     accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
-    let account_infos = InitializeEmitterAccounts::from(accounts);
+    let account_infos = InitializeEmitterAccounts::try_from(accounts)?;
 
     let (emitter_pda, emitter_nonce) = crate::utils::derivations::derive_emitter(program_id);
 
-    account_infos.try_validate(emitter_pda);
+    account_infos.try_validate(emitter_pda)?;
+
+    create_emitter_account(
+        program_id,
+        &account_infos,
+        emitter_nonce,
+        &[Emitter::seed(), &[emitter_nonce]],
+    )
+}
+
+/// initializes one of several emitters owned by the program, see `derive_indexed_emitter`
+pub fn initialize_indexed_emitter<'info>(
+    program_id: Pubkey,
+    accounts: &[AccountInfo<'info>],
+    emitter_index: u16,
+) -> ProgramResult {
+    let account_infos = InitializeEmitterAccounts::try_from(accounts)?;
+
+    let (emitter_pda, emitter_nonce) =
+        crate::utils::derivations::derive_indexed_emitter(program_id, emitter_index);
+
+    account_infos.try_validate(emitter_pda)?;
+
+    create_emitter_account(
+        program_id,
+        &account_infos,
+        emitter_nonce,
+        &[
+            Emitter::seed(),
+            &emitter_index.to_le_bytes(),
+            &[emitter_nonce],
+        ],
+    )
+}
 
+/// creates and initializes the emitter account, signing with `emitter_seeds`
+fn create_emitter_account(
+    program_id: Pubkey,
+    account_infos: &InitializeEmitterAccounts<'_>,
+    emitter_nonce: u8,
+    emitter_seeds: &[&[u8]],
+) -> ProgramResult {
     let rent = Rent::get()?;
     let lamports = rent.minimum_balance(Emitter::LEN);
 
@@ -101,24 +149,25 @@ pub fn initialize_emitter<'info>(
             &program_id,
         ),
         &[account_infos.payer.clone(), account_infos.emitter.clone()],
-        &[&[Emitter::seed(), &[emitter_nonce]]],
+        &[emitter_seeds],
     )?;
 
     let mut account = Emitter::unpack_unchecked(&account_infos.emitter.data.borrow())?;
     if account.is_initialized() {
-        sol_log("account already in use");
-        return Err(ProgramError::AccountAlreadyInitialized);
+        return Err(WormholeLiteError::EmitterAlreadyInitialized.log().into());
     }
 
     if !rent.is_exempt(
         account_infos.emitter.lamports(),
         account_infos.emitter.data_len(),
     ) {
-        sol_log("account not rent exempt");
-        return Err(ProgramError::AccountNotRentExempt);
+        return Err(WormholeLiteError::EmitterNotRentExempt.log().into());
     }
     account.owner = program_id;
     account.nonce = emitter_nonce;
+    // create_account zero-fills the data, but zero the padding explicitly so the invariant
+    // checked by `Emitter::validate_padding` doesn't depend on it
+    account.padding = [0_u8; 32];
     Emitter::pack(account, &mut account_infos.emitter.data.borrow_mut())?;
     Ok(())
 }
@@ -194,8 +243,18 @@ mod test {
             0,
         );
         let account_infos = vec![payer, emitter, system_program];
-        let emitter_accounts = InitializeEmitterAccounts::from(&account_infos[..]);
+        let emitter_accounts = InitializeEmitterAccounts::try_from(&account_infos[..]).unwrap();
         assert!(emitter_accounts.validate(emitter_pda));
         assert!(!emitter_accounts.validate(system_program::id()));
+        assert_eq!(
+            emitter_accounts.try_validate(system_program::id()),
+            Err(WormholeLiteError::InvalidEmitter)
+        );
+        for len in 0..account_infos.len() {
+            assert_eq!(
+                InitializeEmitterAccounts::try_from(&account_infos[..len]).err(),
+                Some(ProgramError::NotEnoughAccountKeys)
+            );
+        }
     }
 }
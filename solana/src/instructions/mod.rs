@@ -1,3 +1,5 @@
+/// instruction for marking a vaa as consumed
+pub mod claim_vaa;
 /// instruction for creating the emitter account
 pub mod create_emitter;
 /// instruction used for posting VAA data, and verifying it
@@ -6,3 +8,5 @@ pub mod post_vaa;
 pub mod send_message;
 /// instruction used for verifying signature data
 pub mod verify_signature;
+/// validation of the accounts used to post a verified vaa
+pub mod verify_message;
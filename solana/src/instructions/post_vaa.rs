@@ -7,9 +7,17 @@ use solana_program::{
     pubkey::Pubkey,
     sysvar,
 };
-use wormhole_anchor_sdk::wormhole::Instruction as WormholeIx;
+use thiserror::Error;
+use wormhole_anchor_sdk::wormhole::{Finality, Instruction as WormholeIx};
 
-use crate::WORMHOLE_PROGRAM_ID;
+use crate::{
+    state::signed_vaa::{
+        GuardianSignature, SignedVaa, VaaParseError, VaaSerializeError, VAA_HEADER_LEN,
+        VAA_SIGNATURE_LEN,
+    },
+    utils::chain::Chain,
+    WORMHOLE_PROGRAM_ID,
+};
 
 /// The actual VAA which we are posting to the bridge and verifying
 ///
@@ -33,19 +41,268 @@ pub struct PostVAADataIx {
     pub timestamp: u32,
     pub nonce: u32,
     pub emitter_chain: u16,
+    /// hex encoded when serialized with serde
+    #[serde(with = "hex_bytes")]
     pub emitter_address: [u8; 32],
     pub sequence: u64,
     pub consistency_level: u8,
+    /// hex encoded when serialized with serde
+    #[serde(with = "hex_bytes")]
     pub payload: Vec<u8>,
 }
 
+/// serde helpers encoding byte strings as lowercase hex, keeping vaas stored in json
+/// configs human readable. decoding accepts an optional `0x` prefix
+mod hex_bytes {
+    use std::fmt;
+
+    use serde::{
+        de::{self, Visitor},
+        Deserializer, Serializer,
+    };
+
+    pub fn serialize<S, T>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<[u8]>,
+    {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>>,
+    {
+        struct HexVisitor;
+        impl<'de> Visitor<'de> for HexVisitor {
+            type Value = Vec<u8>;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a hex encoded byte string")
+            }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                hex::decode(value.trim_start_matches("0x")).map_err(E::custom)
+            }
+        }
+        let bytes = deserializer.deserialize_str(HexVisitor)?;
+        let len = bytes.len();
+        T::try_from(bytes)
+            .map_err(|_| de::Error::invalid_length(len, &"a byte string of the expected length"))
+    }
+}
+
+/// the consistency level of a vaa, describing how final the emitting transaction was when
+/// the guardians observed it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConsistencyLevel {
+    /// observed once the transaction was confirmed, encoded as 1
+    Confirmed,
+    /// observed once the transaction was finalized, encoded as 32
+    Finalized,
+    /// any other value, whose meaning depends on the emitter chain
+    Custom(u8),
+}
+
+impl From<u8> for ConsistencyLevel {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => ConsistencyLevel::Confirmed,
+            32 => ConsistencyLevel::Finalized,
+            value => ConsistencyLevel::Custom(value),
+        }
+    }
+}
+
+impl From<ConsistencyLevel> for u8 {
+    fn from(value: ConsistencyLevel) -> Self {
+        match value {
+            ConsistencyLevel::Confirmed => 1,
+            ConsistencyLevel::Finalized => 32,
+            ConsistencyLevel::Custom(value) => value,
+        }
+    }
+}
+
+impl From<Finality> for ConsistencyLevel {
+    /// the core bridge records confirmed messages with a consistency level of 1, and
+    /// finalized messages with a consistency level of 32
+    fn from(value: Finality) -> Self {
+        match value {
+            Finality::Confirmed => ConsistencyLevel::Confirmed,
+            Finality::Finalized => ConsistencyLevel::Finalized,
+        }
+    }
+}
+
+/// errors returned when building a `PostVAADataIx`
+#[derive(Debug, Error, PartialEq)]
+pub enum PostVAADataIxBuilderError {
+    #[error("missing required field {0}")]
+    MissingField(&'static str),
+    #[error("invalid emitter address {0:?}, expected 32 hex encoded bytes")]
+    InvalidEmitterAddress(String),
+}
+
+/// builds a `PostVAADataIx` field by field, defaulting to a version 1 vaa with a finalized
+/// consistency level, every other field must be set before calling `build`
+#[derive(Clone, Debug)]
+pub struct PostVAADataIxBuilder {
+    version: u8,
+    guardian_set_index: Option<u32>,
+    timestamp: Option<u32>,
+    nonce: Option<u32>,
+    emitter_chain: Option<u16>,
+    emitter_address: Option<[u8; 32]>,
+    sequence: Option<u64>,
+    consistency_level: ConsistencyLevel,
+    payload: Option<Vec<u8>>,
+}
+
+impl PostVAADataIxBuilder {
+    /// sets the vaa version, defaults to 1
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+    /// sets the index of the guardian set which signed the vaa
+    pub fn guardian_set_index(mut self, guardian_set_index: u32) -> Self {
+        self.guardian_set_index = Some(guardian_set_index);
+        self
+    }
+    /// sets the unix timestamp of the block the message was published in
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+    /// sets the nonce (batch id) the message was published with, not to be confused with
+    /// `sequence`
+    pub fn nonce(mut self, nonce: u32) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+    /// sets the wormhole chain id of the chain which emitted the message
+    pub fn emitter_chain(mut self, emitter_chain: u16) -> Self {
+        self.emitter_chain = Some(emitter_chain);
+        self
+    }
+    /// sets the wormhole formatted (32 byte) address of the emitter, see
+    /// `emitter_address_from_hex` for setting it from a hex string
+    pub fn emitter_address(mut self, emitter_address: [u8; 32]) -> Self {
+        self.emitter_address = Some(emitter_address);
+        self
+    }
+    /// sets the emitter address from 32 hex encoded bytes, optionally prefixed with `0x`
+    pub fn emitter_address_from_hex(
+        self,
+        emitter_address: &str,
+    ) -> Result<Self, PostVAADataIxBuilderError> {
+        let decoded = hex::decode(emitter_address.trim_start_matches("0x"))
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| {
+                PostVAADataIxBuilderError::InvalidEmitterAddress(emitter_address.to_string())
+            })?;
+        Ok(self.emitter_address(decoded))
+    }
+    /// sets the sequence of the message, assigned by the core bridge per emitter
+    pub fn sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+    /// sets the consistency level, defaults to `ConsistencyLevel::Finalized`
+    pub fn consistency_level(mut self, consistency_level: ConsistencyLevel) -> Self {
+        self.consistency_level = consistency_level;
+        self
+    }
+    /// sets the raw payload of the message
+    pub fn payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+    /// returns the vaa, or the first required field which was not set
+    pub fn build(self) -> Result<PostVAADataIx, PostVAADataIxBuilderError> {
+        use PostVAADataIxBuilderError::MissingField;
+        Ok(PostVAADataIx {
+            version: self.version,
+            guardian_set_index: self
+                .guardian_set_index
+                .ok_or(MissingField("guardian_set_index"))?,
+            timestamp: self.timestamp.ok_or(MissingField("timestamp"))?,
+            nonce: self.nonce.ok_or(MissingField("nonce"))?,
+            emitter_chain: self.emitter_chain.ok_or(MissingField("emitter_chain"))?,
+            emitter_address: self
+                .emitter_address
+                .ok_or(MissingField("emitter_address"))?,
+            sequence: self.sequence.ok_or(MissingField("sequence"))?,
+            consistency_level: self.consistency_level.into(),
+            payload: self.payload.ok_or(MissingField("payload"))?,
+        })
+    }
+}
+
 impl PostVAADataIx {
+    /// returns a builder which takes the consistency level as a `ConsistencyLevel`
+    pub fn builder() -> PostVAADataIxBuilder {
+        PostVAADataIxBuilder {
+            version: 1,
+            guardian_set_index: None,
+            timestamp: None,
+            nonce: None,
+            emitter_chain: None,
+            emitter_address: None,
+            sequence: None,
+            consistency_level: ConsistencyLevel::Finalized,
+            payload: None,
+        }
+    }
+    /// returns the chain which emitted the vaa, or `None` if `emitter_chain` is not a known
+    /// wormhole chain id
+    ///
+    /// the raw `emitter_chain` is kept so vaas from chains added after this release still parse
+    pub fn emitter_chain_id(&self) -> Option<Chain> {
+        match Chain::from_u16(self.emitter_chain)? {
+            // 0 addresses any destination chain, it never emits
+            Chain::Any => None,
+            chain => Some(chain),
+        }
+    }
+    /// parses a complete signed vaa, returning its body along with the guardian signatures
+    /// from its header, everything needed to verify and post the vaa
+    pub fn from_signed_vaa(bytes: &[u8]) -> Result<(Self, Vec<GuardianSignature>), VaaParseError> {
+        let vaa = SignedVaa::parse(bytes)?;
+        Ok((Self::from(&vaa), vaa.header.signatures))
+    }
+    /// serializes the complete signed vaa, the header with each `(guardian_index, signature)`
+    /// followed by the body, in the wire format read by `SignedVaa::parse`, failing if there
+    /// are more signatures than the u8 signature count can encode
+    pub fn to_full_vaa_bytes(
+        &self,
+        signatures: &[(u8, [u8; 65])],
+    ) -> Result<Vec<u8>, VaaSerializeError> {
+        let num_signatures = u8::try_from(signatures.len())
+            .map_err(|_| VaaSerializeError::TooManySignatures(signatures.len()))?;
+        let body = serialize_vaa(self);
+        let mut out =
+            Vec::with_capacity(VAA_HEADER_LEN + signatures.len() * VAA_SIGNATURE_LEN + body.len());
+        out.push(self.version);
+        out.extend_from_slice(&self.guardian_set_index.to_be_bytes());
+        out.push(num_signatures);
+        for (guardian_index, signature) in signatures {
+            out.push(*guardian_index);
+            out.extend_from_slice(signature);
+        }
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
     /// derives the guardian set account which stores information about the 
     /// guardians who signed teh vaa
     pub fn derive_guardian_set(&self) -> (Pubkey, u8) {
         crate::utils::derivations::derive_guardian_set(self.guardian_set_index)
     }
     /// given the vaa paylaod hash, return the account used for storing its information
+    ///
+    /// the core bridge seeds the posted vaa with the single keccak256 hash of the body, not
+    /// the double hash signed by the guardians (see `hash_vaa_double`)
     pub fn derive_posted_vaa_account(&self) -> (Pubkey, u8) {
         let payload_hash = hash_vaa(self).to_vec();
         crate::utils::derivations::derive_posted_vaa(&payload_hash)
@@ -54,6 +311,27 @@ impl PostVAADataIx {
     pub fn hash_vaa(&self) -> [u8; 32] {
         hash_vaa(self)
     }
+    /// returns a stable key identifying the vaa, formatted as
+    /// `{emitter_chain}/{emitter_address_hex}/{sequence}`, for use by caching layers
+    pub fn cache_key(&self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.emitter_chain,
+            hex::encode(self.emitter_address),
+            self.sequence
+        )
+    }
+    /// parses a key produced by `cache_key` into its (emitter_chain, emitter_address, sequence)
+    pub fn parse_cache_key(key: &str) -> Option<(u16, [u8; 32], u64)> {
+        let mut parts = key.split('/');
+        let emitter_chain = parts.next()?.parse().ok()?;
+        let emitter_address = hex::decode(parts.next()?).ok()?.try_into().ok()?;
+        let sequence = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some((emitter_chain, emitter_address, sequence))
+    }
 }
 
 // Convert a full VAA structure into the serialization of its unique components, this structure is
@@ -81,6 +359,30 @@ pub fn hash_vaa(vaa: &PostVAADataIx) -> [u8; 32] {
     h.finalize().into()
 }
 
+/// returns the keccak256 hash of `hash_vaa`, which is the message the guardians sign and the
+/// secp256k1 program recovers signatures against
+///
+/// this is only used for signature verification, the posted vaa account is derived from
+/// the single hash returned by `hash_vaa`
+pub fn hash_vaa_double(vaa: &PostVAADataIx) -> [u8; 32] {
+    solana_program::keccak::hash(&hash_vaa(vaa)).0
+}
+
+/// hashes a vaa like `hash_vaa`, but feeds each field to `keccak::hashv` directly instead of
+/// first serializing the body into a single buffer, avoiding an allocation of the payload size
+pub fn hash_vaa_hashv(vaa: &PostVAADataIx) -> [u8; 32] {
+    solana_program::keccak::hashv(&[
+        &vaa.timestamp.to_be_bytes(),
+        &vaa.nonce.to_be_bytes(),
+        &vaa.emitter_chain.to_be_bytes(),
+        &vaa.emitter_address,
+        &vaa.sequence.to_be_bytes(),
+        &[vaa.consistency_level],
+        &vaa.payload,
+    ])
+    .0
+}
+
 impl From<PostVAADataIx> for WormholeIx {
     fn from(value: PostVAADataIx) -> Self {
         Self::PostVAA {
@@ -128,3 +430,248 @@ pub fn create_post_vaa_ix(
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn vaa() -> PostVAADataIx {
+        PostVAADataIx {
+            version: 1,
+            guardian_set_index: 3,
+            timestamp: 1700000069,
+            nonce: 42,
+            emitter_chain: 1,
+            emitter_address: [7_u8; 32],
+            sequence: 69,
+            consistency_level: 32,
+            payload: b"Hello World".to_vec(),
+        }
+    }
+    fn builder() -> PostVAADataIxBuilder {
+        PostVAADataIx::builder()
+            .guardian_set_index(3)
+            .timestamp(1700000069)
+            .nonce(42)
+            .emitter_chain(1)
+            .emitter_address([7_u8; 32])
+            .sequence(69)
+            .payload(b"Hello World".to_vec())
+    }
+    #[test]
+    fn test_builder_consistency_level() {
+        assert_eq!(builder().build().unwrap(), vaa());
+        // the consistency level follows the sequence in the serialized body
+        let offset = 4 + 4 + 2 + 32 + 8;
+        for (level, byte) in [
+            (ConsistencyLevel::Confirmed, 1),
+            (ConsistencyLevel::Finalized, 32),
+            (ConsistencyLevel::Custom(15), 15),
+        ] {
+            let vaa = builder().consistency_level(level).build().unwrap();
+            assert_eq!(serialize_vaa(&vaa)[offset], byte);
+            assert_eq!(ConsistencyLevel::from(byte), level);
+        }
+        assert_eq!(
+            ConsistencyLevel::from(Finality::Confirmed),
+            ConsistencyLevel::Confirmed
+        );
+        assert_eq!(
+            ConsistencyLevel::from(Finality::Finalized),
+            ConsistencyLevel::Finalized
+        );
+    }
+    #[test]
+    fn test_builder() {
+        let built = builder()
+            .emitter_address_from_hex(&format!("0x{}", "07".repeat(32)))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(built, vaa());
+        assert_eq!(
+            builder().version(2).build().unwrap(),
+            PostVAADataIx {
+                version: 2,
+                ..vaa()
+            }
+        );
+        assert_eq!(
+            PostVAADataIx::builder()
+                .guardian_set_index(3)
+                .timestamp(1700000069)
+                .emitter_chain(1)
+                .emitter_address([7_u8; 32])
+                .payload(vec![])
+                .build(),
+            Err(PostVAADataIxBuilderError::MissingField("nonce"))
+        );
+        for emitter_address in ["07".repeat(31), "zz".repeat(32), String::new()] {
+            assert_eq!(
+                builder().emitter_address_from_hex(&emitter_address).err(),
+                Some(PostVAADataIxBuilderError::InvalidEmitterAddress(
+                    emitter_address
+                ))
+            );
+        }
+    }
+    #[test]
+    fn test_emitter_chain_id() {
+        for (emitter_chain, chain) in [
+            (1, Some(Chain::Solana)),
+            (2, Some(Chain::Ethereum)),
+            (23, Some(Chain::Arbitrum)),
+            (0, None),
+            (27, None),
+            (u16::MAX, None),
+        ] {
+            let vaa = PostVAADataIx {
+                emitter_chain,
+                ..vaa()
+            };
+            assert_eq!(vaa.emitter_chain_id(), chain);
+            if let Some(chain) = chain {
+                assert_eq!(chain.as_u16(), emitter_chain);
+            }
+        }
+    }
+    #[test]
+    fn test_json_roundtrip() {
+        let vaa = vaa();
+        let json = serde_json::to_value(&vaa).unwrap();
+        assert_eq!(json["emitter_address"], hex::encode([7_u8; 32]));
+        assert_eq!(json["payload"], hex::encode(b"Hello World"));
+        assert_eq!(
+            serde_json::from_value::<PostVAADataIx>(json.clone()).unwrap(),
+            vaa
+        );
+
+        let mut prefixed = json.clone();
+        prefixed["payload"] = format!("0x{}", hex::encode(b"Hello World")).into();
+        assert_eq!(
+            serde_json::from_value::<PostVAADataIx>(prefixed).unwrap(),
+            vaa
+        );
+        let mut short_address = json.clone();
+        short_address["emitter_address"] = hex::encode([7_u8; 31]).into();
+        assert!(serde_json::from_value::<PostVAADataIx>(short_address).is_err());
+        let mut bad_payload = json;
+        bad_payload["payload"] = "zz".into();
+        assert!(serde_json::from_value::<PostVAADataIx>(bad_payload).is_err());
+
+        // the vaa fixtures share the same layout, alongside the expected hash
+        let fixture: serde_json::Value = serde_json::from_slice(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/vaas/solana_hello_world.json"
+        )))
+        .unwrap();
+        let vaa = serde_json::from_value::<PostVAADataIx>(fixture.clone()).unwrap();
+        assert_eq!(hex::encode(vaa.hash_vaa()), fixture["hash"]);
+    }
+    #[test]
+    fn test_hash_vaa_hashv() {
+        for payload in [vec![], b"Hello World".to_vec(), vec![7_u8; 10 * 1024]] {
+            let vaa = PostVAADataIx { payload, ..vaa() };
+            assert_eq!(hash_vaa_hashv(&vaa), hash_vaa(&vaa));
+        }
+    }
+    #[test]
+    fn test_posted_vaa_account() {
+        let vaa = vaa();
+        assert_eq!(
+            hash_vaa_double(&vaa),
+            solana_program::keccak::hash(&hash_vaa(&vaa)).0
+        );
+        // guardians sign the double hash
+        let guardian = &crate::testing::mock_guardians(1)[0];
+        let signature = crate::testing::mock_guardian_sign(guardian, hash_vaa(&vaa));
+        let recovered = solana_program::secp256k1_recover::secp256k1_recover(
+            &hash_vaa_double(&vaa),
+            signature[64],
+            &signature[..64],
+        )
+        .unwrap();
+        assert_eq!(
+            solana_program::keccak::hash(&recovered.0).0[12..],
+            guardian.eth_address
+        );
+        // while the posted vaa account is seeded with the single hash
+        let (posted_vaa, _) = vaa.derive_posted_vaa_account();
+        assert_eq!(
+            posted_vaa,
+            crate::utils::derivations::derive_posted_vaa(&hash_vaa(&vaa)).0
+        );
+        assert_ne!(
+            posted_vaa,
+            crate::utils::derivations::derive_posted_vaa(&hash_vaa_double(&vaa)).0
+        );
+        // pinned so a change to the derivation is caught. this is computed from the synthetic
+        // fixture, the derivation is checked against an account posted on mainnet by
+        // `test_posted_vaa_address_mainnet` in `client::posted_vaa` (network-tests feature)
+        assert_eq!(
+            posted_vaa,
+            solana_program::pubkey!("tofhxXpj8Q2nXSTrjecNKttSrY3VVsiF9LLsnFU4enN")
+        );
+    }
+    #[test]
+    fn test_from_signed_vaa() {
+        let bytes = hex::decode(crate::testing::TOKEN_TRANSFER_VAA).unwrap();
+        let (vaa, signatures) = PostVAADataIx::from_signed_vaa(&bytes).unwrap();
+        assert_eq!(vaa.sequence, 140000);
+        assert_eq!(vaa.guardian_set_index, 3);
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(
+            hex::encode(vaa.hash_vaa()),
+            "885d2e9aa653e2ceb4f009d3e595eea7348a6e6af30a53f9cd419ad96404b056"
+        );
+        assert!(PostVAADataIx::from_signed_vaa(&bytes[..10]).is_err());
+    }
+    #[test]
+    fn test_to_full_vaa_bytes() {
+        let vaa = vaa();
+        let signatures = [(0_u8, [1_u8; 65]), (4, [2_u8; 65])];
+        let bytes = vaa.to_full_vaa_bytes(&signatures).unwrap();
+        let (parsed, parsed_signatures) = PostVAADataIx::from_signed_vaa(&bytes).unwrap();
+        assert_eq!(parsed, vaa);
+        assert_eq!(
+            parsed_signatures
+                .iter()
+                .map(|signature| (signature.guardian_set_index, signature.signature))
+                .collect::<Vec<_>>(),
+            signatures
+        );
+
+        let bytes = hex::decode(crate::testing::TOKEN_TRANSFER_VAA).unwrap();
+        let (vaa, signatures) = PostVAADataIx::from_signed_vaa(&bytes).unwrap();
+        let signatures = signatures
+            .iter()
+            .map(|signature| (signature.guardian_set_index, signature.signature))
+            .collect::<Vec<_>>();
+        assert_eq!(vaa.to_full_vaa_bytes(&signatures).unwrap(), bytes);
+
+        let signatures = vec![(0_u8, [1_u8; 65]); 256];
+        assert_eq!(
+            vaa.to_full_vaa_bytes(&signatures),
+            Err(VaaSerializeError::TooManySignatures(256))
+        );
+        assert_eq!(vaa.to_full_vaa_bytes(&signatures[..255]).unwrap()[5], 255);
+    }
+    #[test]
+    fn test_cache_key() {
+        let vaa = vaa();
+        let key = vaa.cache_key();
+        assert_eq!(key, format!("1/{}/69", hex::encode([7_u8; 32])));
+        assert_eq!(
+            PostVAADataIx::parse_cache_key(&key),
+            Some((1, [7_u8; 32], 69))
+        );
+        // the key only depends on the identity of the vaa
+        let other = PostVAADataIx {
+            timestamp: 0,
+            payload: vec![],
+            ..vaa
+        };
+        assert_eq!(other.cache_key(), key);
+        assert_eq!(PostVAADataIx::parse_cache_key("1/00/69"), None);
+        assert_eq!(PostVAADataIx::parse_cache_key(&format!("{key}/1")), None);
+    }
+}
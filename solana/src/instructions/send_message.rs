@@ -1,5 +1,14 @@
 use crate::message_payload::Payload;
-use crate::{state::emitter::Emitter, utils::derivations::derive_message_pda, WORMHOLE_PROGRAM_ID};
+use crate::{
+    error::WormholeLiteError,
+    instructions::post_vaa::{ConsistencyLevel, PostVAADataIx},
+    state::{emitter::Emitter, vaa::PostedMessageData},
+    utils::derivations::{
+        derive_core_bridge_config, derive_core_fee_collector, derive_indexed_emitter,
+        derive_indexed_message_pda, derive_message_pda, derive_send_message_pdas, derive_sequence,
+    },
+    WORMHOLE_PROGRAM_ID,
+};
 use borsh::ser::BorshSerialize;
 use solana_program::log::sol_log;
 use solana_program::{
@@ -7,11 +16,119 @@ use solana_program::{
     entrypoint::ProgramResult,
     instruction::{AccountMeta, Instruction},
     program::{invoke, invoke_signed},
+    program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
+    rent::Rent,
     system_instruction, system_program, sysvar,
 };
 use wormhole_anchor_sdk::wormhole::Finality;
+
+/// offset of the message fee within the core bridge config account
+pub(crate) const BRIDGE_CONFIG_FEE_OFFSET: usize = 16;
+
+/// parses the message fee out of the core bridge config account data
+///
+/// the config is laid out as guardian_set_index (u32), last_lamports (u64),
+/// guardian_set_expiration_time (u32) and fee (u64), all little endian
+pub fn parse_bridge_fee(data: &[u8]) -> Option<u64> {
+    let fee = data.get(BRIDGE_CONFIG_FEE_OFFSET..BRIDGE_CONFIG_FEE_OFFSET + 8)?;
+    let mut out = [0_u8; 8];
+    out.copy_from_slice(fee);
+    Some(u64::from_le_bytes(out))
+}
+
+/// size of the core bridge config fields read by `parse_bridge_config`
+pub const BRIDGE_CONFIG_LEN: usize = 24;
+
+/// the fields of the core bridge config account
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BridgeConfig {
+    /// index of the active guardian set
+    pub guardian_set_index: u32,
+    /// lamports held by the fee collector when fees were last collected
+    pub last_lamports: u64,
+    /// seconds a guardian set remains valid after being replaced
+    pub guardian_set_expiration_time: u32,
+    /// fee in lamports for posting a message
+    pub fee: u64,
+}
+
+/// parses the core bridge config account data, laid out as described by `parse_bridge_fee`,
+/// returning `None` if the data is too short to hold the config
+pub fn parse_bridge_config(data: &[u8]) -> Option<BridgeConfig> {
+    let data = data.get(..BRIDGE_CONFIG_LEN)?;
+    let (guardian_set_index, rest) = data.split_at(4);
+    let (last_lamports, rest) = rest.split_at(8);
+    let (guardian_set_expiration_time, fee) = rest.split_at(4);
+    Some(BridgeConfig {
+        guardian_set_index: u32::from_le_bytes(guardian_set_index.try_into().ok()?),
+        last_lamports: u64::from_le_bytes(last_lamports.try_into().ok()?),
+        guardian_set_expiration_time: u32::from_le_bytes(
+            guardian_set_expiration_time.try_into().ok()?,
+        ),
+        fee: u64::from_le_bytes(fee.try_into().ok()?),
+    })
+}
+
+/// converts a `Finality` into the consistency level carried by the vaa of a message
+/// published with it
+pub trait FinalityExt {
+    fn as_consistency_level(&self) -> u8;
+}
+
+impl FinalityExt for Finality {
+    /// see the `From<Finality>` implementation of `ConsistencyLevel`
+    fn as_consistency_level(&self) -> u8 {
+        ConsistencyLevel::from(*self).into()
+    }
+}
+
+/// a message to publish through wormhole, along with the consistency level its vaa will carry
+///
+/// the consistency level is derived from `finality` on construction so that tools
+/// reconstructing the vaa agree with what the core bridge records
+#[derive(Clone)]
+pub struct OutboundMessage {
+    pub batch_id: u32,
+    pub payload: Vec<u8>,
+    pub finality: Finality,
+    pub consistency_level: u8,
+}
+
+impl OutboundMessage {
+    pub fn new(batch_id: u32, payload: Vec<u8>, finality: Finality) -> Self {
+        Self {
+            batch_id,
+            payload,
+            consistency_level: finality.as_consistency_level(),
+            finality,
+        }
+    }
+    /// reconstructs the vaa data the guardians will sign once the message has been
+    /// observed, using the emitter, sequence and timestamp recorded by the core bridge
+    pub fn to_post_vaa_data(
+        &self,
+        guardian_set_index: u32,
+        timestamp: u32,
+        emitter_chain: u16,
+        emitter_address: [u8; 32],
+        sequence: u64,
+    ) -> PostVAADataIx {
+        PostVAADataIx {
+            version: 1,
+            guardian_set_index,
+            timestamp,
+            nonce: self.batch_id,
+            emitter_chain,
+            emitter_address,
+            sequence,
+            consistency_level: self.consistency_level,
+            payload: self.payload.clone(),
+        }
+    }
+}
+
 /// when invoking an instruction that publishes a message through wormhole, these are the accounts
 /// that must be used in the instruction
 pub struct TransactionAccountKeys {
@@ -27,7 +144,7 @@ pub struct TransactionAccountKeys {
     /// seed: [b"Sequence", PROGRAM_ID]
     pub core_emitter_sequence: Pubkey,
     /// core bridge program message contents account
-    /// may be a keypair or pda controlled by our program
+    /// pda controlled by our program, created by the core bridge when posting the message
     pub core_message_account: Pubkey,
     /// main wormhole program
     pub core_bridge_program: Pubkey,
@@ -42,6 +159,35 @@ pub struct TransactionAccountKeys {
 }
 
 impl TransactionAccountKeys {
+    /// derives every account needed to publish a message from `executing_program_id`'s emitter,
+    /// where `message_nonce` is the emitter's next publishable nonce
+    ///
+    /// ```
+    /// use solana_program::pubkey::Pubkey;
+    /// use wormhole_solana_lite::instructions::send_message::TransactionAccountKeys;
+    ///
+    /// let program_id = Pubkey::new_unique();
+    /// let payer = Pubkey::new_unique();
+    /// let keys = TransactionAccountKeys::for_program(program_id, payer, 0);
+    /// let account_metas = keys.to_account_metas();
+    /// assert_eq!(account_metas.len(), 10);
+    /// assert_eq!(account_metas[4].pubkey, payer);
+    /// ```
+    pub fn for_program(executing_program_id: Pubkey, payer: Pubkey, message_nonce: u64) -> Self {
+        let pdas = derive_send_message_pdas(executing_program_id, message_nonce);
+        Self {
+            payer,
+            emitter: pdas.emitter,
+            core_bridge_config: derive_core_bridge_config().0,
+            core_emitter_sequence: pdas.sequence,
+            core_message_account: pdas.message,
+            core_bridge_program: WORMHOLE_PROGRAM_ID,
+            core_fee_collector: derive_core_fee_collector().0,
+            system_program: system_program::id(),
+            clock: sysvar::clock::id(),
+            rent: sysvar::rent::id(),
+        }
+    }
     /// returns a vector of AccountMeta objects for sending a tx from an rpc client
     pub fn to_account_metas(&self) -> Vec<AccountMeta> {
         vec![
@@ -57,6 +203,18 @@ impl TransactionAccountKeys {
             AccountMeta::new_readonly(self.core_bridge_program, false), // 9
         ]
     }
+    /// returns the AccountMeta objects for invoking a program instruction which wraps
+    /// `send_message`, where `wrapper_accounts` are the wrapper program's own accounts
+    ///
+    /// ordering contract: the ten wormhole accounts always come first, in the order of
+    /// `to_account_metas`, followed by `wrapper_accounts` in the given order. this lets the
+    /// wrapper pass its full account slice to `send_message` (or `Accounts::try_from`), which only
+    /// reads the first ten accounts, and read its own accounts starting at index 10
+    pub fn to_wrapper_account_metas(&self, wrapper_accounts: &[AccountMeta]) -> Vec<AccountMeta> {
+        let mut account_metas = self.to_account_metas();
+        account_metas.extend_from_slice(wrapper_accounts);
+        account_metas
+    }
     /// returns a vector of AccountMeta objects poublishing a message via cpi
     pub fn to_cpi_account_metas(&self) -> Vec<AccountMeta> {
         vec![
@@ -88,7 +246,7 @@ pub struct Accounts<'info> {
     /// seed: [b"Sequence", PROGRAM_ID]
     pub core_emitter_sequence: AccountInfo<'info>,
     /// core bridge program message contents account
-    /// may be a keypair or pda controlled by our program
+    /// pda controlled by our program, created by the core bridge when posting the message
     pub core_message_account: AccountInfo<'info>,
     /// main wormhole program
     pub core_bridge_program: AccountInfo<'info>,
@@ -102,20 +260,28 @@ pub struct Accounts<'info> {
     pub rent: AccountInfo<'info>,
 }
 
-impl<'info> From<&[AccountInfo<'info>]> for Accounts<'info> {
-    fn from(value: &[AccountInfo<'info>]) -> Self {
-        Self {
-            core_bridge_config: value.get(0).unwrap().clone(),
-            core_message_account: value.get(1).unwrap().clone(),
-            emitter: value.get(2).unwrap().clone(),
-            core_emitter_sequence: value.get(3).unwrap().clone(),
-            payer: value.get(4).unwrap().clone(),
-            core_fee_collector: value.get(5).unwrap().clone(),
-            clock: value.get(6).unwrap().clone(),
-            system_program: value.get(7).unwrap().clone(),
-            rent: value.get(8).unwrap().clone(),
-            core_bridge_program: value.get(9).unwrap().clone(), // last account in the slice
-        }
+impl<'info> TryFrom<&[AccountInfo<'info>]> for Accounts<'info> {
+    type Error = ProgramError;
+    /// returns `ProgramError::NotEnoughAccountKeys` if fewer than `Accounts::LEN` accounts are given
+    fn try_from(value: &[AccountInfo<'info>]) -> Result<Self, Self::Error> {
+        let account = |index: usize| {
+            value
+                .get(index)
+                .cloned()
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        Ok(Self {
+            core_bridge_config: account(0)?,
+            core_message_account: account(1)?,
+            emitter: account(2)?,
+            core_emitter_sequence: account(3)?,
+            payer: account(4)?,
+            core_fee_collector: account(5)?,
+            clock: account(6)?,
+            system_program: account(7)?,
+            rent: account(8)?,
+            core_bridge_program: account(9)?, // last account in the slice
+        })
     }
 }
 
@@ -137,7 +303,32 @@ impl<'info> From<&Accounts<'info>> for TransactionAccountKeys {
 }
 
 impl<'info> Accounts<'info> {
-    /// converts the Accounts object into a vector of AccountInfos, used for cpi
+    /// number of accounts expected at the start of the account slice
+    pub const LEN: usize = 10;
+    /// constructs the accounts from the start of `value`, panicking if the slice is too short
+    #[deprecated(note = "use `Accounts::try_from` instead")]
+    pub fn from(value: &[AccountInfo<'info>]) -> Self {
+        Self::try_from(value).unwrap()
+    }
+    /// constructs the accounts from the start of `accounts` like `Accounts::try_from` and validates
+    /// them against the pdas derived from the emitter account, so a misordered slice is
+    /// rejected instead of producing a misparsed struct
+    ///
+    /// returns `ProgramError::NotEnoughAccountKeys` if fewer than `Accounts::LEN` accounts are given
+    pub fn try_from_slice_validated(
+        accounts: &[AccountInfo<'info>],
+        executing_program_id: Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let account_infos = Self::try_from(accounts)?;
+        let emitter = Emitter::unpack(&account_infos.emitter.data.borrow())?;
+        let (emitter_pda, _) = emitter.derive();
+        let (sequence_pda, _) = emitter.derive_sequence();
+        let (message_pda, _) = emitter.next_message_pda(executing_program_id);
+        account_infos.try_validate(emitter_pda, message_pda, sequence_pda, executing_program_id)?;
+        Ok(account_infos)
+    }
+    /// converts the Accounts object into a vector of AccountInfos, used for cpi, holding an
+    /// AccountInfo for every account of `TransactionAccountKeys::to_cpi_account_metas`
     pub fn to_vec(&self) -> Vec<AccountInfo<'info>> {
         vec![
             self.core_bridge_config.clone(),
@@ -149,32 +340,53 @@ impl<'info> Accounts<'info> {
             self.clock.clone(),
             self.system_program.clone(),
             self.rent.clone(),
+            self.core_bridge_program.clone(),
         ]
     }
-    /// creates an instruction which is used to seed the fee collector with fees
+    /// reads the current message fee out of the core bridge config account, see `parse_bridge_fee`
+    pub fn read_bridge_fee(&self) -> Result<u64, ProgramError> {
+        parse_bridge_fee(&self.core_bridge_config.data.borrow())
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+    /// creates an instruction which is used to seed the fee collector with fees, transferring
+    /// the fee currently configured in the core bridge config account
     ///
     /// must be invoked first
-    pub fn fee_collector_ix(&self) -> Instruction {
-        system_instruction::transfer(self.payer.key, self.core_fee_collector.key, 100)
+    pub fn fee_collector_ix(&self) -> Result<Instruction, ProgramError> {
+        Ok(self.fee_collector_ix_with_amount(self.read_bridge_fee()?))
     }
-    /// creates an instruction which is used to post a message to wormhole
+    /// creates an instruction which is used to seed the fee collector with `lamports`
+    ///
+    /// must be invoked first
+    pub fn fee_collector_ix_with_amount(&self, lamports: u64) -> Instruction {
+        system_instruction::transfer(self.payer.key, self.core_fee_collector.key, lamports)
+    }
+    /// creates an instruction which is used to post a message to wormhole, see `build_post_message_ix`
     pub fn post_message_ix(
         &self,
         batch_id: u32,
         payload: Vec<u8>,
         finality: Finality,
     ) -> Instruction {
-        Instruction {
-            program_id: *self.core_bridge_program.key,
-            accounts: TransactionAccountKeys::from(self).to_cpi_account_metas(),
-            data: wormhole_anchor_sdk::wormhole::Instruction::PostMessage {
-                batch_id,
-                payload,
-                finality,
-            }
-            .try_to_vec()
-            .unwrap(),
+        build_post_message_ix(
+            &TransactionAccountKeys::from(self),
+            batch_id,
+            payload,
+            finality,
+        )
+    }
+    /// creates an instruction which is used to post `message` to wormhole
+    pub fn post_outbound_message_ix(&self, message: &OutboundMessage) -> Instruction {
+        self.post_message_ix(message.batch_id, message.payload.clone(), message.finality)
+    }
+    /// returns true if the core bridge config account data parses as a bridge config,
+    /// logging the failure otherwise
+    pub fn validate_bridge_config(&self) -> bool {
+        if parse_bridge_config(&self.core_bridge_config.data.borrow()).is_none() {
+            WormholeLiteError::InvalidBridgeConfig.log();
+            return false;
         }
+        true
     }
     /// validates the account information, returning true if verification passes
     pub fn validate(
@@ -184,118 +396,293 @@ impl<'info> Accounts<'info> {
         sequence_pda: Pubkey,
         executing_program_id: Pubkey,
     ) -> bool {
+        self.try_validate(emitter_pda, message_pda, sequence_pda, executing_program_id)
+            .is_ok()
+    }
+    /// validates the account information like `validate`, additionally checking that the payer
+    /// holds enough lamports to cover the message fee and the rent of the message account,
+    /// see `PostedMessageData::space` for computing the size of the message account
+    pub fn validate_with_balances(
+        &self,
+        emitter_pda: Pubkey,
+        message_pda: Pubkey,
+        sequence_pda: Pubkey,
+        executing_program_id: Pubkey,
+        fee: u64,
+        message_rent: u64,
+    ) -> ProgramResult {
+        self.try_validate(emitter_pda, message_pda, sequence_pda, executing_program_id)?;
+        if self.payer.lamports() < fee.saturating_add(message_rent) {
+            sol_log("payer has insufficient lamports for message fee and rent");
+            return Err(ProgramError::InsufficientFunds);
+        }
+        Ok(())
+    }
+    /// validates the account information, returning the first check that failed
+    pub fn try_validate(
+        &self,
+        emitter_pda: Pubkey,
+        message_pda: Pubkey,
+        sequence_pda: Pubkey,
+        executing_program_id: Pubkey,
+    ) -> Result<(), WormholeLiteError> {
         // validate account keys
         if self.clock.key.ne(&sysvar::clock::id()) {
-            sol_log("invalid clock");
-            return false;
+            return Err(WormholeLiteError::InvalidClock.log());
         }
         if self.rent.key.ne(&sysvar::rent::id()) {
-            sol_log("invalid rent");
-            return false;
+            return Err(WormholeLiteError::InvalidRent.log());
         }
         if self.system_program.key.ne(&system_program::id()) {
-            sol_log("invalid system program");
-            return false;
+            return Err(WormholeLiteError::InvalidSystemProgram.log());
         }
         if self.core_bridge_program.key.ne(&WORMHOLE_PROGRAM_ID) {
-            sol_log("invalid core bridge program");
-            return false;
+            return Err(WormholeLiteError::InvalidCoreBridgeProgram.log());
+        }
+        // any core bridge account long enough parses as a config, so the key is checked too
+        if self
+            .core_bridge_config
+            .key
+            .ne(&derive_core_bridge_config().0)
+        {
+            return Err(WormholeLiteError::InvalidBridgeConfig.log());
         }
         if self.emitter.key.ne(&emitter_pda) {
-            sol_log("invalid emitter");
-            return false;
+            return Err(WormholeLiteError::InvalidEmitter.log());
         }
         if self.core_message_account.key.ne(&message_pda) {
-            sol_log("invalid message");
-            return false;
+            return Err(WormholeLiteError::InvalidMessage.log());
         }
         if self.core_emitter_sequence.key.ne(&sequence_pda) {
-            sol_log("invalid sequence");
-            return false;
+            return Err(WormholeLiteError::InvalidSequence.log());
+        }
+        // the core bridge creates the message account, so a reused nonce would otherwise
+        // fail deep within the core bridge
+        if !self.core_message_account.data_is_empty() {
+            return Err(WormholeLiteError::MessageAccountInUse.log());
         }
         // validate account owners
         if executing_program_id.ne(self.emitter.owner) {
-            sol_log("invalid emitter account owner");
-            return false;
+            return Err(WormholeLiteError::InvalidEmitterOwner.log());
         }
         if self
             .core_bridge_config
             .owner
             .ne(self.core_bridge_program.key)
         {
-            sol_log("invalid bridge config owner");
-            return false;
+            return Err(WormholeLiteError::InvalidBridgeConfigOwner.log());
         }
-        if self.emitter.owner.ne(&executing_program_id) {
-            sol_log("invalid emitter owner");
-            return false;
+        if !self.validate_bridge_config() {
+            return Err(WormholeLiteError::InvalidBridgeConfig);
         }
         // sequence account may not be initialized yet
         // other ownership doesnt need to be verified since that is handle by wormhole program
-        true
+        Ok(())
     }
-    pub fn try_validate(
-        &self,
-        emitter_pda: Pubkey,
-        message_pda: Pubkey,
-        sequence_pda: Pubkey,
-        executing_program_id: Pubkey,
-    ) {
-        if !self.validate(emitter_pda, message_pda, sequence_pda, executing_program_id) {
-            panic!("invalid accounts");
+}
+/// creates an instruction which is used to post a message to wormhole, for off-chain clients
+/// which only have the account keys
+pub fn build_post_message_ix(
+    keys: &TransactionAccountKeys,
+    batch_id: u32,
+    payload: Vec<u8>,
+    finality: Finality,
+) -> Instruction {
+    Instruction {
+        program_id: keys.core_bridge_program,
+        accounts: keys.to_cpi_account_metas(),
+        data: wormhole_anchor_sdk::wormhole::Instruction::PostMessage {
+            batch_id,
+            payload,
+            finality,
         }
+        .try_to_vec()
+        .unwrap(),
     }
 }
-/// sends a message via wormhole using CPI
+
+/// creates an instruction which creates the message pda of `executing_program_id` for `nonce`,
+/// sized to hold a message with a `payload_len` byte payload and owned by the core bridge
+///
+/// the message pda must sign, so the executing program invokes this with the
+/// `[b"message", nonce]` seeds. `send_message` doesn't use it: the core bridge creates the
+/// message account itself during post_message, and requires the account to be empty
+pub fn create_message_account_ix(
+    payer: Pubkey,
+    executing_program_id: Pubkey,
+    nonce: u64,
+    payload_len: usize,
+    rent: &Rent,
+) -> Instruction {
+    let (message_pda, _) = derive_message_pda(executing_program_id, nonce);
+    let space = PostedMessageData::space(payload_len);
+    system_instruction::create_account(
+        &payer,
+        &message_pda,
+        rent.minimum_balance(space),
+        space as u64,
+        &WORMHOLE_PROGRAM_ID,
+    )
+}
+
+/// sends a message via wormhole using CPI, paying `fee` lamports to the fee collector
 /// https://docs.rs/wormhole-core-bridge-solana/0.0.0-alpha.6/wormhole_core_bridge_solana/
 ///
-/// this is not tested within this actual crate
+/// the fee must cover the one configured by the core bridge, see `Accounts::read_bridge_fee`,
+/// otherwise `WormholeLiteError::InsufficientFee` is returned before any cpi
+///
+/// the message is published with `Finality::Finalized`, see `send_message_with_finality`
+///
+/// every call reads the emitter nonce from the emitter account and writes the incremented
+/// nonce back once the message is posted, so a program may send several messages from the
+/// same emitter in one transaction as long as it doesn't write back an emitter it unpacked
+/// before an earlier call
 pub fn send_message<'info>(
     program_id: Pubkey,
     accounts: &[AccountInfo<'info>],
     batch_id: u32,
     payload: Payload,
+    fee: u64,
 ) -> ProgramResult {
-    let account_infos = Accounts::from(accounts);
-    let (sequence_pda, _, emitter_pda, emitter_nonce) = {
-        let emitter = Emitter::unpack(&account_infos.emitter.data.borrow())?;
-        let (sequence_pda, sequence_nonce) = emitter.derive_sequence();
-        let (emitter_pda, emitter_nonce) = emitter.derive();
-        (sequence_pda, sequence_nonce, emitter_pda, emitter_nonce)
-    };
-    let next_publishable_nonce =
-        Emitter::slice_next_publishable_nonce(&account_infos.emitter.data.borrow());
+    send_message_with_finality(
+        program_id,
+        accounts,
+        batch_id,
+        payload,
+        fee,
+        Finality::Finalized,
+    )
+}
+
+/// sends a message via wormhole like `send_message`, publishing it with `finality`
+///
+/// `Finality::Confirmed` lets the guardians sign the message sooner, at the risk of the
+/// message being observed on a fork which is later abandoned
+pub fn send_message_with_finality<'info>(
+    program_id: Pubkey,
+    accounts: &[AccountInfo<'info>],
+    batch_id: u32,
+    payload: Payload,
+    fee: u64,
+    finality: Finality,
+) -> ProgramResult {
+    let account_infos = Accounts::try_from(accounts)?;
+    let mut emitter = Emitter::unpack(&account_infos.emitter.data.borrow())?;
+    if !emitter.validate_padding() {
+        return Err(WormholeLiteError::InvalidEmitterPadding.log().into());
+    }
+    let (sequence_pda, _) = emitter.derive_sequence();
+    let (emitter_pda, emitter_nonce) = emitter.derive();
+    let next_publishable_nonce = emitter.reserve_next_nonce()?;
     let (message_pda, message_nonce) = derive_message_pda(program_id, next_publishable_nonce);
 
     // validate all accounts to be used in the instruction
-    account_infos.try_validate(emitter_pda, message_pda, sequence_pda, program_id);
+    account_infos.try_validate(emitter_pda, message_pda, sequence_pda, program_id)?;
 
-    let ix = account_infos.fee_collector_ix();
-    invoke(
-        &ix,
+    publish_message(
+        &account_infos,
+        emitter,
+        OutboundMessage::new(batch_id, payload.try_to_vec()?, finality),
+        fee,
         &[
-            account_infos.payer.clone(),
-            account_infos.core_fee_collector.clone(),
+            &[Emitter::seed(), &[emitter_nonce]],
+            &[
+                b"message",
+                &next_publishable_nonce.to_le_bytes()[..],
+                &[message_nonce],
+            ],
         ],
-    )?;
+    )
+}
 
-    let ix = account_infos.post_message_ix(batch_id, payload.try_to_vec()?, Finality::Finalized);
-    invoke_signed(
-        &ix,
-        &account_infos.to_vec(),
+/// sends a message via wormhole using CPI, publishing through one of several indexed
+/// emitters owned by the program (see `initialize_indexed_emitter`)
+///
+/// every emitter has its own wormhole sequence, so publishing round-robin across emitters
+/// lets a program avoid serializing all messages through a single emitter nonce. the trade
+/// off is that ordering is only guaranteed per emitter: consumers must track sequences per
+/// (emitter, sequence) pair and must not assume any ordering between messages published by
+/// different emitters
+pub fn send_message_via_emitter<'info>(
+    program_id: Pubkey,
+    accounts: &[AccountInfo<'info>],
+    emitter_index: u16,
+    batch_id: u32,
+    payload: Payload,
+    fee: u64,
+) -> ProgramResult {
+    let account_infos = Accounts::try_from(accounts)?;
+    let (emitter_pda, emitter_nonce) = derive_indexed_emitter(program_id, emitter_index);
+    let (sequence_pda, _) = derive_sequence(emitter_pda);
+    let mut emitter = Emitter::unpack(&account_infos.emitter.data.borrow())?;
+    if !emitter.validate_padding() {
+        return Err(WormholeLiteError::InvalidEmitterPadding.log().into());
+    }
+    let next_publishable_nonce = emitter.reserve_next_nonce()?;
+    let (message_pda, message_nonce) =
+        derive_indexed_message_pda(program_id, emitter_index, next_publishable_nonce);
+
+    // validate all accounts to be used in the instruction
+    account_infos.try_validate(emitter_pda, message_pda, sequence_pda, program_id)?;
+
+    publish_message(
+        &account_infos,
+        emitter,
+        OutboundMessage::new(batch_id, payload.try_to_vec()?, Finality::Finalized),
+        fee,
         &[
-            &[Emitter::seed(), &[emitter_nonce]],
+            &[
+                Emitter::seed(),
+                &emitter_index.to_le_bytes()[..],
+                &[emitter_nonce],
+            ],
             &[
                 b"message",
+                &emitter_index.to_le_bytes()[..],
                 &next_publishable_nonce.to_le_bytes()[..],
                 &[message_nonce],
             ],
         ],
+    )
+}
+
+/// pays the message fee, posts the message through the core bridge signing with the
+/// emitter and message seeds, then packs `emitter` into the emitter account
+///
+/// `emitter` must be the state unpacked from the emitter account with the message nonce
+/// already taken through `Emitter::reserve_next_nonce`, so the account is written exactly
+/// once, after the cpi. this assumes the caller doesn't hold another copy of the emitter
+/// which it writes back later in the same instruction, as that copy would clobber the
+/// reserved nonce and the next message would reuse the message pda
+///
+/// accounts must be validated before calling
+fn publish_message(
+    account_infos: &Accounts<'_>,
+    emitter: Emitter,
+    message: OutboundMessage,
+    fee: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    // the core bridge would otherwise reject the underpaid message with an opaque error
+    let bridge_fee = account_infos.read_bridge_fee()?;
+    if fee < bridge_fee {
+        sol_log(&format!(
+            "fee {fee} is less than the bridge fee {bridge_fee}"
+        ));
+        return Err(WormholeLiteError::InsufficientFee.log().into());
+    }
+    let ix = account_infos.fee_collector_ix_with_amount(fee);
+    invoke(
+        &ix,
+        &[
+            account_infos.payer.clone(),
+            account_infos.core_fee_collector.clone(),
+        ],
     )?;
 
-    // increment the nonce used for message account derivation
-    let mut emitter = Emitter::unpack(&account_infos.emitter.data.borrow())?;
-    emitter.next_publishable_nonce = emitter.next_publishable_nonce.checked_add(1).unwrap();
+    let ix = account_infos.post_outbound_message_ix(&message);
+    invoke_signed(&ix, &account_infos.to_vec(), signer_seeds)?;
+
     Emitter::pack(emitter, &mut account_infos.emitter.data.borrow_mut())?;
     Ok(())
 }
@@ -303,12 +690,7 @@ pub fn send_message<'info>(
 mod test {
     use solana_program::system_instruction::SystemInstruction;
 
-    use crate::{
-        utils::derivations::{
-            derive_core_bridge_config, derive_core_fee_collector, derive_emitter, derive_sequence,
-        },
-        WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID,
-    };
+    use crate::{utils::derivations::derive_emitter, WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID};
 
     use super::*;
     fn core_bridge_config() -> Pubkey {
@@ -361,13 +743,131 @@ mod test {
         ];
         let got_metas = accts.to_account_metas();
         assert_eq!(got_metas, expected_metas);
+        assert_eq!(
+            TransactionAccountKeys::for_program(pid, payer(), 69).to_account_metas(),
+            expected_metas
+        );
+    }
+    #[test]
+    fn test_wrapper_account_metas() {
+        let pid = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let accts = TransactionAccountKeys::for_program(pid, payer(), 69);
+        let wrapper_accounts = vec![
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(Pubkey::new_unique(), true),
+        ];
+        let account_metas = accts.to_wrapper_account_metas(&wrapper_accounts);
+        assert_eq!(account_metas.len(), 12);
+        assert_eq!(account_metas[..10], accts.to_account_metas()[..]);
+        assert_eq!(account_metas[10..], wrapper_accounts[..]);
+
+        let mut lamports = vec![0_u64; account_metas.len()];
+        let mut data = vec![vec![0_u8; 0]; account_metas.len()];
+        let owner = system_program::id();
+        let account_infos = account_metas
+            .iter()
+            .zip(lamports.iter_mut().zip(data.iter_mut()))
+            .map(|(meta, (lamports, data))| {
+                AccountInfo::new(
+                    &meta.pubkey,
+                    meta.is_signer,
+                    meta.is_writable,
+                    lamports,
+                    data,
+                    &owner,
+                    false,
+                    0,
+                )
+            })
+            .collect::<Vec<_>>();
+        let accounts = Accounts::try_from(&account_infos[..]).unwrap();
+        assert_eq!(
+            TransactionAccountKeys::from(&accounts).to_account_metas(),
+            accts.to_account_metas()
+        );
+        assert_eq!(*account_infos[10].key, wrapper_accounts[0].pubkey);
+    }
+    #[test]
+    fn test_parse_bridge_config() {
+        let mut data = vec![0_u8; BRIDGE_CONFIG_LEN];
+        data[..4].copy_from_slice(&4_u32.to_le_bytes());
+        data[4..12].copy_from_slice(&1_000_u64.to_le_bytes());
+        data[12..16].copy_from_slice(&86400_u32.to_le_bytes());
+        data[16..24].copy_from_slice(&2500_u64.to_le_bytes());
+        let config = parse_bridge_config(&data).unwrap();
+        assert_eq!(
+            config,
+            BridgeConfig {
+                guardian_set_index: 4,
+                last_lamports: 1_000,
+                guardian_set_expiration_time: 86400,
+                fee: 2500,
+            }
+        );
+        assert_eq!(parse_bridge_fee(&data), Some(config.fee));
+        assert_eq!(parse_bridge_config(&data[..BRIDGE_CONFIG_LEN - 1]), None);
+    }
+    #[test]
+    fn test_create_message_account_ix() {
+        let pid = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let rent = Rent::default();
+        let ix = create_message_account_ix(payer(), pid, 69, 11, &rent);
+        let message_pda = core_message_account(pid, 69);
+        assert_eq!(ix.program_id, system_program::id());
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new(payer(), true),
+                AccountMeta::new(message_pda, true),
+            ]
+        );
+        assert_eq!(
+            bincode::deserialize::<SystemInstruction>(&ix.data).unwrap(),
+            SystemInstruction::CreateAccount {
+                lamports: rent.minimum_balance(PostedMessageData::FIXED_LEN + 11),
+                space: (PostedMessageData::FIXED_LEN + 11) as u64,
+                owner: WORMHOLE_PROGRAM_ID,
+            }
+        );
+    }
+    #[test]
+    fn test_try_from_slice_validated() {
+        let pid = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let keys = TransactionAccountKeys::for_program(pid, payer(), 0);
+        let account_metas = keys.to_account_metas();
+        let mut fixture = crate::testing::SendMessageFixture::new(pid, keys);
+        let account_infos = fixture.account_infos();
+        let accounts = Accounts::try_from_slice_validated(&account_infos, pid).unwrap();
+        assert_eq!(
+            TransactionAccountKeys::from(&accounts).to_account_metas(),
+            account_metas
+        );
+        assert_eq!(
+            Accounts::try_from_slice_validated(&account_infos[..9], pid).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+        for len in 0..Accounts::LEN {
+            assert_eq!(
+                Accounts::try_from(&account_infos[..len]).err(),
+                Some(ProgramError::NotEnoughAccountKeys)
+            );
+        }
+        // swapping the message and sequence accounts is caught by validation
+        let mut misordered = account_infos.clone();
+        misordered.swap(1, 3);
+        assert_eq!(
+            Accounts::try_from_slice_validated(&misordered, pid).err(),
+            Some(WormholeLiteError::InvalidMessage.into())
+        );
     }
     #[test]
     fn test_account_infos() {
         let key = Pubkey::new_unique();
+        // core bridge config with a fee of 2500 lamports
         let mut data = vec![5; 80];
+        data[16..24].copy_from_slice(&2500_u64.to_le_bytes());
         let mut lamports = 42;
-        let mut data2 = vec![5; 80];
+        let mut data2 = vec![];
         let mut lamports2 = 42;
         let mut data3 = vec![5; 80];
         let mut lamports3 = 42;
@@ -513,7 +1013,7 @@ mod test {
             core_bridge_program.clone(),
         ];
 
-        let accounts: Accounts<'_> = Accounts::from(&account_infos_vec[..]);
+        let accounts: Accounts<'_> = Accounts::try_from(&account_infos_vec[..]).unwrap();
 
         assert_eq!(*accounts.core_bridge_config.key, accts.core_bridge_config);
         assert_eq!(
@@ -532,6 +1032,7 @@ mod test {
         assert_eq!(*accounts.rent.key, accts.rent);
         assert_eq!(*accounts.core_bridge_program.key, accts.core_bridge_program);
 
+        assert_eq!(accounts.to_vec().len(), account_infos_vec.len());
         for (a1, a2) in accounts.to_vec().iter().zip(account_infos_vec.iter()) {
             assert_eq!(a1.key, a2.key);
         }
@@ -547,12 +1048,147 @@ mod test {
             accts.core_emitter_sequence,
             Pubkey::new_unique(),
         ));
-        let fee_collector_ix = accounts.fee_collector_ix();
+        assert_eq!(
+            accounts.try_validate(
+                accts.emitter,
+                accts.core_message_account,
+                accts.core_emitter_sequence,
+                Pubkey::new_unique(),
+            ),
+            Err(WormholeLiteError::InvalidEmitterOwner)
+        );
+        assert_eq!(
+            accounts.try_validate(
+                accts.emitter,
+                accts.core_emitter_sequence,
+                accts.core_emitter_sequence,
+                pid,
+            ),
+            Err(WormholeLiteError::InvalidMessage)
+        );
+        // the payer holds 42 lamports
+        assert!(accounts
+            .validate_with_balances(
+                accts.emitter,
+                accts.core_message_account,
+                accts.core_emitter_sequence,
+                pid,
+                40,
+                2,
+            )
+            .is_ok());
+        assert_eq!(
+            accounts.validate_with_balances(
+                accts.emitter,
+                accts.core_message_account,
+                accts.core_emitter_sequence,
+                pid,
+                40,
+                3,
+            ),
+            Err(ProgramError::InsufficientFunds)
+        );
+        assert_eq!(accounts.read_bridge_fee().unwrap(), 2500);
+        let mut used_message_lamports = 42;
+        let mut used_message_data = vec![5; 80];
+        let used_accounts = Accounts {
+            core_message_account: AccountInfo::new(
+                &accts.core_message_account,
+                false,
+                false,
+                &mut used_message_lamports,
+                &mut used_message_data,
+                &WORMHOLE_PROGRAM_ID,
+                false,
+                0,
+            ),
+            ..Accounts::try_from(&account_infos_vec[..]).unwrap()
+        };
+        assert!(!used_accounts.validate(
+            accts.emitter,
+            accts.core_message_account,
+            accts.core_emitter_sequence,
+            pid,
+        ));
+        assert_eq!(
+            used_accounts.try_validate(
+                accts.emitter,
+                accts.core_message_account,
+                accts.core_emitter_sequence,
+                pid,
+            ),
+            Err(WormholeLiteError::MessageAccountInUse)
+        );
+        assert!(accounts.validate_bridge_config());
+        // owned by the core bridge, but too short to be the config
+        let mut garbage_config_lamports = 42;
+        let mut garbage_config_data = vec![5; BRIDGE_CONFIG_LEN - 1];
+        let garbage_config_accounts = Accounts {
+            core_bridge_config: AccountInfo::new(
+                &accts.core_bridge_config,
+                false,
+                false,
+                &mut garbage_config_lamports,
+                &mut garbage_config_data,
+                &WORMHOLE_PROGRAM_ID,
+                false,
+                0,
+            ),
+            ..Accounts::try_from(&account_infos_vec[..]).unwrap()
+        };
+        assert!(!garbage_config_accounts.validate_bridge_config());
+        assert!(!garbage_config_accounts.validate(
+            accts.emitter,
+            accts.core_message_account,
+            accts.core_emitter_sequence,
+            pid,
+        ));
+        assert_eq!(
+            garbage_config_accounts.try_validate(
+                accts.emitter,
+                accts.core_message_account,
+                accts.core_emitter_sequence,
+                pid,
+            ),
+            Err(WormholeLiteError::InvalidBridgeConfig)
+        );
+        // a core bridge account which parses as a config, but isn't the config
+        let guardian_set = crate::utils::derivations::derive_guardian_set(3).0;
+        let mut guardian_set_lamports = 42;
+        let mut guardian_set_data = vec![5; 1024];
+        let wrong_config_accounts = Accounts {
+            core_bridge_config: AccountInfo::new(
+                &guardian_set,
+                false,
+                false,
+                &mut guardian_set_lamports,
+                &mut guardian_set_data,
+                &WORMHOLE_PROGRAM_ID,
+                false,
+                0,
+            ),
+            ..Accounts::try_from(&account_infos_vec[..]).unwrap()
+        };
+        assert!(wrong_config_accounts.validate_bridge_config());
+        assert_eq!(
+            wrong_config_accounts.try_validate(
+                accts.emitter,
+                accts.core_message_account,
+                accts.core_emitter_sequence,
+                pid,
+            ),
+            Err(WormholeLiteError::InvalidBridgeConfig)
+        );
+        let fee_collector_ix = accounts.fee_collector_ix().unwrap();
+        assert_eq!(
+            fee_collector_ix,
+            accounts.fee_collector_ix_with_amount(2500)
+        );
         assert_eq!(
             fee_collector_ix,
             Instruction::new_with_bincode(
                 system_program::id(),
-                &SystemInstruction::Transfer { lamports: 100 },
+                &SystemInstruction::Transfer { lamports: 2500 },
                 vec![
                     AccountMeta::new(*accounts.payer.key, true),
                     AccountMeta::new(*accounts.core_fee_collector.key, false)
@@ -574,6 +1210,290 @@ mod test {
                 .try_to_vec()
                 .unwrap()
             }
+        );
+        assert_eq!(
+            post_msg_ix,
+            build_post_message_ix(&accts, 69, b"Hello World".to_vec(), Finality::Finalized)
+        );
+    }
+    /// records every instruction invoked through cpi instead of executing it
+    struct RecordingSyscallStubs(std::sync::Arc<std::sync::Mutex<Vec<Instruction>>>);
+    impl solana_program::program_stubs::SyscallStubs for RecordingSyscallStubs {
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            _account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            self.0.lock().unwrap().push(instruction.clone());
+            Ok(())
+        }
+    }
+    /// serializes the tests installing syscall stubs, which are process wide
+    static SYSCALL_STUBS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    /// installs `RecordingSyscallStubs` until dropped, then restores the previous stubs even
+    /// if the test panicked
+    struct RecordingSyscallStubsGuard {
+        invoked: std::sync::Arc<std::sync::Mutex<Vec<Instruction>>>,
+        previous_stubs: Option<Box<dyn solana_program::program_stubs::SyscallStubs>>,
+        // released after the previous stubs are restored
+        _lock: std::sync::MutexGuard<'static, ()>,
+    }
+    impl RecordingSyscallStubsGuard {
+        fn install() -> Self {
+            let lock = SYSCALL_STUBS_LOCK
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let invoked = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let previous_stubs = solana_program::program_stubs::set_syscall_stubs(Box::new(
+                RecordingSyscallStubs(invoked.clone()),
+            ));
+            Self {
+                invoked,
+                previous_stubs: Some(previous_stubs),
+                _lock: lock,
+            }
+        }
+        /// returns every instruction invoked so far
+        fn invoked(&self) -> Vec<Instruction> {
+            self.invoked.lock().unwrap().clone()
+        }
+    }
+    impl Drop for RecordingSyscallStubsGuard {
+        fn drop(&mut self) {
+            if let Some(previous_stubs) = self.previous_stubs.take() {
+                solana_program::program_stubs::set_syscall_stubs(previous_stubs);
+            }
+        }
+    }
+    #[test]
+    fn test_send_message_nonce_progression() {
+        // the emitter starts at nonce 0, and every send publishes to the message pda derived
+        // from the current nonce before incrementing it, so three sends use the message pdas
+        // for nonces 0, 1 and 2 and leave the emitter at nonce 3
+        let stubs = RecordingSyscallStubsGuard::install();
+
+        let pid = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let mut fixture = crate::testing::SendMessageFixture::new(
+            pid,
+            TransactionAccountKeys::for_program(pid, payer(), 0),
+        );
+        for nonce in 0..3 {
+            fixture.set_keys(TransactionAccountKeys::for_program(pid, payer(), nonce));
+            let account_infos = fixture.account_infos();
+            let payload = Payload::new(1, b"Hello World".to_vec()).unwrap();
+            // the second message is published with confirmed finality
+            if nonce == 1 {
+                send_message_with_finality(
+                    pid,
+                    &account_infos,
+                    nonce as u32,
+                    payload,
+                    2500,
+                    Finality::Confirmed,
+                )
+            } else {
+                send_message(pid, &account_infos, nonce as u32, payload, 2500)
+            }
+            .unwrap();
+        }
+        let invoked = stubs.invoked();
+        drop(stubs);
+
+        assert_eq!(fixture.emitter().next_publishable_nonce, 3);
+        // each send pays the fee, then posts a message
+        assert_eq!(invoked.len(), 6);
+        let post_message_ixs = invoked
+            .iter()
+            .filter(|ix| ix.program_id == WORMHOLE_PROGRAM_ID)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            post_message_ixs
+                .iter()
+                .map(|ix| ix.accounts[1].pubkey)
+                .collect::<Vec<_>>(),
+            (0..3)
+                .map(|nonce| core_message_account(pid, nonce))
+                .collect::<Vec<_>>()
+        );
+        for (nonce, (ix, finality)) in post_message_ixs
+            .iter()
+            .zip([
+                Finality::Finalized,
+                Finality::Confirmed,
+                Finality::Finalized,
+            ])
+            .enumerate()
+        {
+            assert_eq!(
+                ix.data,
+                wormhole_anchor_sdk::wormhole::Instruction::PostMessage {
+                    batch_id: nonce as u32,
+                    payload: Payload::new(1, b"Hello World".to_vec())
+                        .unwrap()
+                        .try_to_vec()
+                        .unwrap(),
+                    finality,
+                }
+                .try_to_vec()
+                .unwrap()
+            );
+        }
+    }
+    #[test]
+    fn test_send_message_insufficient_fee() {
+        let stubs = RecordingSyscallStubsGuard::install();
+
+        let pid = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        // the core bridge charges 5000 lamports per message
+        let mut fixture = crate::testing::SendMessageFixture::new(
+            pid,
+            TransactionAccountKeys::for_program(pid, payer(), 0),
         )
+        .with_fee(5000);
+        let account_infos = fixture.account_infos();
+        let payload = Payload::new(1, b"Hello World".to_vec()).unwrap();
+        assert_eq!(
+            send_message(pid, &account_infos, 0, payload.clone(), 2500),
+            Err(WormholeLiteError::InsufficientFee.into())
+        );
+        // nothing was invoked, and the nonce wasn't consumed
+        assert!(stubs.invoked().is_empty());
+        assert_eq!(
+            Emitter::unpack(&account_infos[2].data.borrow())
+                .unwrap()
+                .next_publishable_nonce,
+            0
+        );
+        send_message(pid, &account_infos, 0, payload, 5000).unwrap();
+        assert_eq!(stubs.invoked().len(), 2);
+    }
+    #[test]
+    fn test_outbound_message() {
+        assert_eq!(Finality::Finalized.as_consistency_level(), 32);
+        assert_eq!(Finality::Confirmed.as_consistency_level(), 1);
+
+        let message = OutboundMessage::new(69, b"Hello World".to_vec(), Finality::Finalized);
+        assert_eq!(message.consistency_level, 32);
+        let emitter = derive_emitter(WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID)
+            .0
+            .to_bytes();
+        let vaa = message.to_post_vaa_data(3, 1700000069, 1, emitter, 420);
+        assert_eq!(vaa.consistency_level, 32);
+        assert_eq!(vaa.nonce, 69);
+        // reconstructing the vaa from the same message always yields the same digest
+        assert_eq!(
+            vaa.hash_vaa(),
+            message
+                .to_post_vaa_data(3, 1700000069, 1, emitter, 420)
+                .hash_vaa()
+        );
+        assert_ne!(
+            vaa.hash_vaa(),
+            OutboundMessage::new(69, b"Hello World".to_vec(), Finality::Confirmed)
+                .to_post_vaa_data(3, 1700000069, 1, emitter, 420)
+                .hash_vaa()
+        );
+    }
+    #[test]
+    fn test_indexed_emitters() {
+        let pid = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let (emitter_0, _) = derive_indexed_emitter(pid, 0);
+        let (emitter_1, _) = derive_indexed_emitter(pid, 1);
+        assert_ne!(emitter_0, emitter_1);
+        assert_ne!(emitter_0, emitter(pid));
+        assert_ne!(derive_sequence(emitter_0).0, derive_sequence(emitter_1).0);
+        assert_ne!(
+            derive_indexed_message_pda(pid, 0, 69).0,
+            derive_indexed_message_pda(pid, 1, 69).0
+        );
+
+        let mut et_0 = Emitter {
+            owner: pid,
+            nonce: derive_indexed_emitter(pid, 0).1,
+            next_publishable_nonce: 0,
+            padding: [0_u8; 32],
+        };
+        let et_1 = Emitter {
+            nonce: derive_indexed_emitter(pid, 1).1,
+            ..et_0
+        };
+        assert_eq!(et_0.derive_indexed(0).0, emitter_0);
+        assert_eq!(et_1.derive_indexed(1).0, emitter_1);
+        et_0.increment_publishable_nonce();
+        assert_eq!(et_0.next_publishable_nonce, 1);
+        assert_eq!(et_1.next_publishable_nonce, 0);
+    }
+    #[test]
+    fn test_send_message_via_indexed_emitters() {
+        // every indexed emitter tracks its own nonce, so sending through emitters 0, 1 and 0
+        // publishes to the message pdas for (0, 0), (1, 0) and (0, 1), leaving emitter 0 at
+        // nonce 2 and emitter 1 at nonce 1
+        let stubs = RecordingSyscallStubsGuard::install();
+
+        let pid = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let indexed_keys = |emitter_index: u16, nonce: u64| {
+            let emitter_pda = derive_indexed_emitter(pid, emitter_index).0;
+            TransactionAccountKeys {
+                emitter: emitter_pda,
+                core_emitter_sequence: derive_sequence(emitter_pda).0,
+                core_message_account: derive_indexed_message_pda(pid, emitter_index, nonce).0,
+                ..TransactionAccountKeys::for_program(pid, payer(), nonce)
+            }
+        };
+        let mut fixtures = [0_u16, 1].map(|emitter_index| {
+            crate::testing::SendMessageFixture::new(pid, indexed_keys(emitter_index, 0))
+                .with_emitter(Emitter {
+                    owner: pid,
+                    nonce: derive_indexed_emitter(pid, emitter_index).1,
+                    next_publishable_nonce: 0,
+                    padding: [0_u8; 32],
+                })
+        });
+        for (batch_id, emitter_index) in [0_u16, 1, 0].into_iter().enumerate() {
+            // the state of both emitters persists across sends
+            let fixture = &mut fixtures[emitter_index as usize];
+            let nonce = fixture.emitter().next_publishable_nonce;
+            fixture.set_keys(indexed_keys(emitter_index, nonce));
+            send_message_via_emitter(
+                pid,
+                &fixture.account_infos(),
+                emitter_index,
+                batch_id as u32,
+                Payload::new(1, b"Hello World".to_vec()).unwrap(),
+                2500,
+            )
+            .unwrap();
+        }
+        let invoked = stubs.invoked();
+        drop(stubs);
+
+        assert_eq!(fixtures[0].emitter().next_publishable_nonce, 2);
+        assert_eq!(fixtures[1].emitter().next_publishable_nonce, 1);
+        assert_eq!(invoked.len(), 6);
+        let post_message_ixs = invoked
+            .iter()
+            .filter(|ix| ix.program_id == WORMHOLE_PROGRAM_ID)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            post_message_ixs
+                .iter()
+                .map(|ix| (ix.accounts[1].pubkey, ix.accounts[2].pubkey))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    derive_indexed_message_pda(pid, 0, 0).0,
+                    derive_indexed_emitter(pid, 0).0
+                ),
+                (
+                    derive_indexed_message_pda(pid, 1, 0).0,
+                    derive_indexed_emitter(pid, 1).0
+                ),
+                (
+                    derive_indexed_message_pda(pid, 0, 1).0,
+                    derive_indexed_emitter(pid, 0).0
+                ),
+            ]
+        );
     }
 }
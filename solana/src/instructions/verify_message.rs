@@ -0,0 +1,192 @@
+use solana_program::{
+    account_info::AccountInfo, program_error::ProgramError, system_program, sysvar,
+};
+
+use crate::{
+    error::WormholeLiteError, instructions::post_vaa::PostVAADataIx,
+    utils::derivations::derive_core_bridge_config, WORMHOLE_PROGRAM_ID,
+};
+
+/// on-chain object pointing to the accounts used by the core bridge post_vaa instruction,
+/// in the order used by `create_post_vaa_ix`
+pub struct PostVAAAccounts<'info> {
+    /// core bridge guardian set which signed the vaa
+    /// seed: [b"GuardianSet", guardian_set_index]
+    pub guardian_set: AccountInfo<'info>,
+    /// core bridge program account
+    /// seed: [b"Bridge"]
+    pub core_bridge_config: AccountInfo<'info>,
+    /// signature set populated by the verify_signature instructions
+    pub signature_set: AccountInfo<'info>,
+    /// account storing the posted vaa
+    /// seed: [b"PostedVAA", vaa_hash]
+    pub posted_vaa: AccountInfo<'info>,
+    /// account used to pay for fees
+    pub payer: AccountInfo<'info>,
+    /// clock sysvar account
+    pub clock: AccountInfo<'info>,
+    /// rent sysvar account
+    pub rent: AccountInfo<'info>,
+    /// system program
+    pub system_program: AccountInfo<'info>,
+}
+
+impl<'info> TryFrom<&[AccountInfo<'info>]> for PostVAAAccounts<'info> {
+    type Error = ProgramError;
+    /// returns `ProgramError::NotEnoughAccountKeys` if fewer than 8 accounts are given
+    fn try_from(value: &[AccountInfo<'info>]) -> Result<Self, Self::Error> {
+        let account = |index: usize| {
+            value
+                .get(index)
+                .cloned()
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        Ok(Self {
+            guardian_set: account(0)?,
+            core_bridge_config: account(1)?,
+            signature_set: account(2)?,
+            posted_vaa: account(3)?,
+            payer: account(4)?,
+            clock: account(5)?,
+            rent: account(6)?,
+            system_program: account(7)?,
+        })
+    }
+}
+
+impl<'info> PostVAAAccounts<'info> {
+    /// returns the accounts in the order expected by the post_vaa instruction
+    pub fn to_vec(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.guardian_set.clone(),
+            self.core_bridge_config.clone(),
+            self.signature_set.clone(),
+            self.posted_vaa.clone(),
+            self.payer.clone(),
+            self.clock.clone(),
+            self.rent.clone(),
+            self.system_program.clone(),
+        ]
+    }
+    /// validates the account information against `vaa`, returning true if verification passes
+    pub fn validate(&self, vaa: &PostVAADataIx) -> bool {
+        self.try_validate(vaa).is_ok()
+    }
+    /// validates the account information against `vaa`, returning the first check that failed
+    pub fn try_validate(&self, vaa: &PostVAADataIx) -> Result<(), WormholeLiteError> {
+        // validate account keys
+        if self.clock.key.ne(&sysvar::clock::id()) {
+            return Err(WormholeLiteError::InvalidClock.log());
+        }
+        if self.rent.key.ne(&sysvar::rent::id()) {
+            return Err(WormholeLiteError::InvalidRent.log());
+        }
+        if self.system_program.key.ne(&system_program::id()) {
+            return Err(WormholeLiteError::InvalidSystemProgram.log());
+        }
+        if self
+            .core_bridge_config
+            .key
+            .ne(&derive_core_bridge_config().0)
+        {
+            return Err(WormholeLiteError::InvalidBridgeConfig.log());
+        }
+        if self.guardian_set.key.ne(&vaa.derive_guardian_set().0) {
+            return Err(WormholeLiteError::InvalidGuardianSet.log());
+        }
+        if self.posted_vaa.key.ne(&vaa.derive_posted_vaa_account().0) {
+            return Err(WormholeLiteError::InvalidPostedVaa.log());
+        }
+        // validate account owners
+        if self.core_bridge_config.owner.ne(&WORMHOLE_PROGRAM_ID) {
+            return Err(WormholeLiteError::InvalidBridgeConfigOwner.log());
+        }
+        if self.guardian_set.owner.ne(&WORMHOLE_PROGRAM_ID) {
+            return Err(WormholeLiteError::InvalidGuardianSetOwner.log());
+        }
+        // the signature set and posted vaa are checked by the wormhole program
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use solana_program::pubkey::Pubkey;
+
+    use super::*;
+    fn vaa() -> PostVAADataIx {
+        PostVAADataIx {
+            version: 1,
+            guardian_set_index: 3,
+            timestamp: 1700000069,
+            nonce: 42,
+            emitter_chain: 1,
+            emitter_address: [7_u8; 32],
+            sequence: 69,
+            consistency_level: 32,
+            payload: b"Hello World".to_vec(),
+        }
+    }
+    #[test]
+    fn test_post_vaa_accounts() {
+        let vaa = vaa();
+        let mut keys = [
+            vaa.derive_guardian_set().0,
+            derive_core_bridge_config().0,
+            Pubkey::new_unique(),
+            vaa.derive_posted_vaa_account().0,
+            Pubkey::new_unique(),
+            sysvar::clock::id(),
+            sysvar::rent::id(),
+            system_program::id(),
+        ];
+        let owners = [
+            WORMHOLE_PROGRAM_ID,
+            WORMHOLE_PROGRAM_ID,
+            WORMHOLE_PROGRAM_ID,
+            system_program::id(),
+            system_program::id(),
+            sysvar::id(),
+            sysvar::id(),
+            crate::testing::NATIVE_LOADER_ID,
+        ];
+        let check = |keys: &[Pubkey; 8], owners: &[Pubkey; 8]| {
+            let mut lamports = [1_u64; 8];
+            let mut data: [Vec<u8>; 8] = Default::default();
+            let account_infos = keys
+                .iter()
+                .zip(owners.iter())
+                .zip(lamports.iter_mut())
+                .zip(data.iter_mut())
+                .map(|(((key, owner), lamports), data)| {
+                    AccountInfo::new(key, false, false, lamports, data, owner, false, 0)
+                })
+                .collect::<Vec<_>>();
+            let accounts = PostVAAAccounts::try_from(&account_infos[..]).unwrap();
+            assert_eq!(accounts.to_vec().len(), 8);
+            assert_eq!(
+                PostVAAAccounts::try_from(&account_infos[..7]).err(),
+                Some(ProgramError::NotEnoughAccountKeys)
+            );
+            accounts.try_validate(&vaa)
+        };
+        assert_eq!(check(&keys, &owners), Ok(()));
+
+        let mut bad_owners = owners;
+        bad_owners[0] = system_program::id();
+        assert_eq!(
+            check(&keys, &bad_owners),
+            Err(WormholeLiteError::InvalidGuardianSetOwner)
+        );
+        keys[3] = Pubkey::new_unique();
+        assert_eq!(
+            check(&keys, &owners),
+            Err(WormholeLiteError::InvalidPostedVaa)
+        );
+        keys[0] = Pubkey::new_unique();
+        assert_eq!(
+            check(&keys, &owners),
+            Err(WormholeLiteError::InvalidGuardianSet)
+        );
+    }
+}
@@ -7,6 +7,7 @@ use solana_program::{
 use wormhole_anchor_sdk::wormhole::Instruction as WormholeIx;
 
 use crate::WORMHOLE_PROGRAM_ID;
+use thiserror::Error;
 
 /// the maximum amount of guardian keys in a single instruction
 pub const MAX_LEN_GUARDIAN_KEYS: usize = 19;
@@ -17,6 +18,14 @@ pub struct VerifySignaturesData {
     pub signers: [i8; MAX_LEN_GUARDIAN_KEYS],
 }
 
+/// returned when a signer is marked at a guardian index which does not exist in the guardian set
+#[derive(Debug, Error, PartialEq)]
+#[error("signer index {index} is beyond the guardian set size {guardian_count}")]
+pub struct IndexBeyondSet {
+    pub index: usize,
+    pub guardian_count: usize,
+}
+
 /// represents a guardian which participated in signing some data, whereby `index` is
 /// the guardian's element index from the overall guardian set
 #[derive(Clone, Copy, PartialEq, Debug, BorshSerialize, BorshDeserialize)]
@@ -40,6 +49,18 @@ impl VerifySignaturesData {
         }
         Some(verify_signatures)
     }
+    /// validates that every marked signer references a guardian within a set of `guardian_count` keys
+    pub fn validate_against_set(&self, guardian_count: usize) -> Result<(), IndexBeyondSet> {
+        for (index, signer) in self.signers.iter().enumerate() {
+            if *signer != -1 && index >= guardian_count {
+                return Err(IndexBeyondSet {
+                    index,
+                    guardian_count,
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
 /// initializes a default signatures data set defaulting to -1 for all members
@@ -110,4 +131,39 @@ mod test {
             assert_eq!(verify_sig_data.signers[want as usize], 0_i8);
         }
     }
+    #[test]
+    fn test_validate_against_set() {
+        let verify_sig_data =
+            VerifySignaturesData::parse_signature_set(&[GuardianSignatureMember::new(18)]).unwrap();
+        assert!(verify_sig_data.validate_against_set(19).is_ok());
+        assert_eq!(
+            verify_sig_data.validate_against_set(13),
+            Err(IndexBeyondSet {
+                index: 18,
+                guardian_count: 13
+            })
+        );
+        assert!(VerifySignaturesData::default()
+            .validate_against_set(0)
+            .is_ok());
+    }
+    #[test]
+    fn test_verify_signature_account_infos() {
+        let payer = Pubkey::new_unique();
+        let signature_set = Pubkey::new_unique();
+        let mut fixture = crate::testing::VerifySignatureFixture::new(payer, 3, signature_set);
+        let ix =
+            create_verify_signature_ix(payer, 3, signature_set, VerifySignaturesData::default())
+                .unwrap();
+        let account_infos = fixture.account_infos();
+        assert_eq!(account_infos.len(), ix.accounts.len());
+        for (account_info, meta) in account_infos.iter().zip(ix.accounts.iter()) {
+            assert_eq!(*account_info.key, meta.pubkey);
+            assert_eq!(account_info.is_signer, meta.is_signer);
+            assert_eq!(account_info.is_writable, meta.is_writable);
+        }
+        assert_eq!(*account_infos[1].owner, WORMHOLE_PROGRAM_ID);
+        assert_eq!(*account_infos[3].owner, sysvar::id());
+        assert!(account_infos[5].executable);
+    }
 }
@@ -1,3 +1,19 @@
+//! helpers for publishing and consuming wormhole messages from solana programs
+//!
+//! # features
+//!
+//! - `client` (default): the off-chain rpc client in [`client`], pulling in `solana-client`,
+//!   `solana-sdk`, `tokio`, `anyhow` and the wormhole explorer client. the module and its
+//!   dependencies are never compiled for `target_os = "solana"` or `target_arch = "wasm32"`,
+//!   but programs should still depend on this crate with `default-features = false` to keep
+//!   host builds lean
+//! - `testing`: exposes the fixtures in `testing` to downstream test suites
+//! - `wasm`: wasm-bindgen wrappers in `wasm` for computing pdas and encoding payloads from
+//!   the browser, checked with `make check-wasm`
+//!
+//! `instructions`, `state`, `utils` and `message_payload` must compile without the `client`
+//! feature, which is checked with `make check-no-client`
+
 use solana_program::pubkey::Pubkey;
 
 /// state objects for solana programs
@@ -9,13 +25,28 @@ pub mod utils;
 /// instructions for invoking the wormhole bridge program through cpi
 pub mod instructions;
 
+/// typed errors returned by the on-chain instructions
+pub mod error;
+
 /// structured payloads for handling arbitrary messages
 pub mod message_payload;
 
 /// provides an offchain client client that can be used to interact with the wormhole bridge through rpc
-#[cfg(feature = "client")]
+#[cfg(all(
+    feature = "client",
+    not(target_os = "solana"),
+    not(target_arch = "wasm32")
+))]
 pub mod client;
 
+/// wasm-bindgen wrappers of the derivations and payload encoding for use from javascript
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// deterministic fixtures shared by tests across modules
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 /// id of the core wormhole program
 pub const WORMHOLE_PROGRAM_ID: Pubkey =
     solana_program::pubkey!("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth");
@@ -1,4 +1,46 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use thiserror::Error;
+
+/// maximum number of bytes of data a payload may hold
+pub const MAX_PAYLOAD_LEN: usize = 1024;
+
+/// errors returned when constructing a payload
+#[derive(Debug, Error, PartialEq)]
+pub enum PayloadError {
+    #[error("payload data is {0} bytes, exceeding the {MAX_PAYLOAD_LEN} byte limit")]
+    TooLarge(usize),
+    #[error("payload id {0} is reserved for identifying payload formats")]
+    ReservedPayloadId(u8),
+}
+
+/// bytes at the start of each fragment's data holding the big-endian u16 fragment index
+/// and total number of fragments, see `Payload::fragment`
+pub const FRAGMENT_HEADER_LEN: usize = 4;
+
+/// errors returned when reassembling fragmented payloads
+#[derive(Debug, Error, PartialEq)]
+pub enum FragmentError {
+    #[error("no fragments to reassemble")]
+    Empty,
+    #[error("fragment {0} is too short to hold a fragment header")]
+    Malformed(usize),
+    #[error("fragment {position} has payload id {actual}, expected {expected}")]
+    PayloadIdMismatch {
+        position: usize,
+        expected: u8,
+        actual: u8,
+    },
+    #[error("fragment {position} declares {actual} total fragments, expected {expected}")]
+    TotalMismatch {
+        position: usize,
+        expected: u16,
+        actual: u16,
+    },
+    #[error("fragment {actual} is out of order, expected fragment {expected}")]
+    OutOfOrder { expected: u16, actual: u16 },
+    #[error("missing fragments, received {received} of {total}")]
+    Missing { received: usize, total: u16 },
+}
 
 /// an object representing an arbitrary payload to relay through wormhole, whereby the
 /// `payload_id` is used to identify the specific instruction/function to execute and
@@ -8,12 +50,32 @@ use borsh::{BorshDeserialize, BorshSerialize};
 pub struct Payload {
     /// payload_id is used to identify the type of payload being sent, and is application specific
     pub payload_id: u8,
-    /// the actual data contained by the payload, limited to 1024 bytes due to solana based constraints
+    /// the actual data contained by the payload, limited to `MAX_PAYLOAD_LEN` bytes due to solana based constraints
     pub data: Vec<u8>,
 }
 
+/// returned when a payload carries a different payload_id than the one expected
+#[derive(Debug, Error, PartialEq)]
+#[error("unexpected payload id {actual}, expected {expected}")]
+pub struct UnexpectedPayloadId {
+    pub expected: u8,
+    pub actual: u8,
+}
+
 impl BorshSerialize for Payload {
     fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        if self.data.len() > MAX_PAYLOAD_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                PayloadError::TooLarge(self.data.len()),
+            ));
+        }
+        if RESERVED_FORMAT_BYTES.contains(&self.payload_id) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                PayloadError::ReservedPayloadId(self.payload_id),
+            ));
+        }
         self.payload_id.serialize(writer)?;
         // serialize the length of the data first
         (self.data.len() as u16).to_be_bytes().serialize(writer)?;
@@ -26,18 +88,479 @@ impl BorshSerialize for Payload {
 }
 
 impl BorshDeserialize for Payload {
+    /// reads exactly the payload id, the big-endian u16 length and `length` bytes of data,
+    /// leaving the rest of the reader intact so the payload can be embedded in other messages
     fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let mut data = Vec::with_capacity(1024);
-        reader.read_to_end(&mut data)?;
-        let payload_id = data[0];
-        let length = {
-            let mut out = [0u8; 2];
-            out.copy_from_slice(&data[1..3]);
-            u16::from_be_bytes(out) as usize
-        };
-        let data = data[3..(3 + length)].to_vec();
+        // untrusted input must error instead of panicking when truncated
+        let truncated = |err: std::io::Error| match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => std::io::ErrorKind::InvalidData.into(),
+            _ => err,
+        };
+        let mut header = [0_u8; 3];
+        reader.read_exact(&mut header).map_err(truncated)?;
+        let payload_id = header[0];
+        if RESERVED_FORMAT_BYTES.contains(&payload_id) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                PayloadError::ReservedPayloadId(payload_id),
+            ));
+        }
+        let length = u16::from_be_bytes([header[1], header[2]]) as usize;
+        let mut data = vec![0_u8; length];
+        reader.read_exact(&mut data).map_err(truncated)?;
+        Ok(Self { payload_id, data })
+    }
+}
+
+impl Payload {
+    /// creates a new payload, returning an error if `data` exceeds `MAX_PAYLOAD_LEN` bytes
+    /// or `payload_id` is within `RESERVED_FORMAT_BYTES`
+    pub fn new(payload_id: u8, data: Vec<u8>) -> Result<Self, PayloadError> {
+        if data.len() > MAX_PAYLOAD_LEN {
+            return Err(PayloadError::TooLarge(data.len()));
+        }
+        if RESERVED_FORMAT_BYTES.contains(&payload_id) {
+            return Err(PayloadError::ReservedPayloadId(payload_id));
+        }
+        Ok(Self { payload_id, data })
+    }
+    /// returns the payload if its payload_id is `expected`, for rejecting unexpected message
+    /// types before consuming them, ie `vaa.decode_payload()?.expect_id(3)?`
+    pub fn expect_id(&self, expected: u8) -> Result<&Self, UnexpectedPayloadId> {
+        if self.payload_id != expected {
+            return Err(UnexpectedPayloadId {
+                expected,
+                actual: self.payload_id,
+            });
+        }
+        Ok(self)
+    }
+    /// hex encodes the serialized payload, as displayed by explorers, returning an error if
+    /// the payload can't be serialized (ie: the data exceeds `MAX_PAYLOAD_LEN` bytes)
+    pub fn to_hex(&self) -> std::io::Result<String> {
+        Ok(hex::encode(self.try_to_vec()?))
+    }
+    /// decodes a payload from its hex encoded serialized form, optionally prefixed with `0x`
+    pub fn from_hex(s: &str) -> std::io::Result<Self> {
+        let raw = hex::decode(s.trim_start_matches("0x"))
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        Self::try_from_slice(&raw)
+    }
+    /// splits `data`, which may exceed `MAX_PAYLOAD_LEN`, into payloads with `payload_id`
+    /// which can be published as separate messages, see `reassemble`
+    ///
+    /// each fragment's data starts with a `FRAGMENT_HEADER_LEN` byte header holding the
+    /// fragment index and the total number of fragments, followed by up to
+    /// `MAX_PAYLOAD_LEN - FRAGMENT_HEADER_LEN` bytes of `data`. empty data results in a
+    /// single fragment
+    ///
+    /// panics if `data` requires more than `u16::MAX` fragments
+    pub fn fragment(payload_id: u8, data: &[u8]) -> Vec<Payload> {
+        let chunks = data
+            .chunks(MAX_PAYLOAD_LEN - FRAGMENT_HEADER_LEN)
+            .collect::<Vec<_>>();
+        let chunks = if chunks.is_empty() {
+            vec![data]
+        } else {
+            chunks
+        };
+        let total = u16::try_from(chunks.len()).expect("too many fragments");
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut fragment = Vec::with_capacity(FRAGMENT_HEADER_LEN + chunk.len());
+                fragment.extend_from_slice(&(index as u16).to_be_bytes());
+                fragment.extend_from_slice(&total.to_be_bytes());
+                fragment.extend_from_slice(chunk);
+                Payload {
+                    payload_id,
+                    data: fragment,
+                }
+            })
+            .collect()
+    }
+    /// concatenates the data of payloads created by `fragment`, which must all be present,
+    /// in order, and share the same payload id
+    pub fn reassemble(fragments: &[Payload]) -> Result<Vec<u8>, FragmentError> {
+        let header = |position: usize, fragment: &Payload| {
+            let data = &fragment.data;
+            if data.len() < FRAGMENT_HEADER_LEN {
+                return Err(FragmentError::Malformed(position));
+            }
+            Ok((
+                u16::from_be_bytes([data[0], data[1]]),
+                u16::from_be_bytes([data[2], data[3]]),
+            ))
+        };
+        let first = fragments.first().ok_or(FragmentError::Empty)?;
+        let (_, total) = header(0, first)?;
+        let mut data = Vec::new();
+        for (position, fragment) in fragments.iter().enumerate() {
+            let (index, fragment_total) = header(position, fragment)?;
+            if fragment.payload_id != first.payload_id {
+                return Err(FragmentError::PayloadIdMismatch {
+                    position,
+                    expected: first.payload_id,
+                    actual: fragment.payload_id,
+                });
+            }
+            if fragment_total != total {
+                return Err(FragmentError::TotalMismatch {
+                    position,
+                    expected: total,
+                    actual: fragment_total,
+                });
+            }
+            if index as usize != position {
+                if fragments.len() < total as usize {
+                    return Err(FragmentError::Missing {
+                        received: fragments.len(),
+                        total,
+                    });
+                }
+                return Err(FragmentError::OutOfOrder {
+                    expected: position as u16,
+                    actual: index,
+                });
+            }
+            data.extend_from_slice(&fragment.data[FRAGMENT_HEADER_LEN..]);
+        }
+        if fragments.len() != total as usize {
+            return Err(FragmentError::Missing {
+                received: fragments.len(),
+                total,
+            });
+        }
+        Ok(data)
+    }
+    /// decodes a payload serialized in either the v1 or v2 format, detected by the first byte:
+    /// bytes within `RESERVED_FORMAT_BYTES` identify later formats, anything else is the
+    /// payload id of a v1 payload. v1 payloads can't use reserved payload ids, so the two
+    /// never overlap
+    pub fn decode_any(bytes: &[u8]) -> std::io::Result<AnyPayload> {
+        match bytes.first() {
+            Some(&PAYLOAD_V2_VERSION) => Ok(AnyPayload::V2(PayloadV2::try_from_slice(bytes)?)),
+            Some(version) if RESERVED_FORMAT_BYTES.contains(version) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported payload version {version}"),
+            )),
+            Some(_) => Ok(AnyPayload::V1(Payload::try_from_slice(bytes)?)),
+            None => Err(std::io::ErrorKind::InvalidData.into()),
+        }
+    }
+}
+
+/// a payload tagged with an application defined format version, distinct from the
+/// `payload_id`, allowing consumers to handle payload format migrations
+///
+/// serialized as the version byte followed by the serialized payload
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionedPayload {
+    pub version: u8,
+    pub payload: Payload,
+}
+
+impl BorshSerialize for VersionedPayload {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.version.serialize(writer)?;
+        self.payload.serialize(writer)
+    }
+}
+
+impl VersionedPayload {
+    /// deserializes a versioned payload, returning an error if its version is not
+    /// within `supported`
+    pub fn try_from_slice(
+        data: &[u8],
+        supported: impl std::ops::RangeBounds<u8>,
+    ) -> std::io::Result<Self> {
+        let (version, payload) = data.split_first().ok_or(std::io::ErrorKind::InvalidData)?;
+        if !supported.contains(version) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported payload version {version}"),
+            ));
+        }
+        Ok(Self {
+            version: *version,
+            payload: Payload::try_from_slice(payload)?,
+        })
+    }
+}
+
+/// first bytes reserved for identifying payload formats after v1, see `Payload::decode_any`
+///
+/// v1 payloads whose payload_id falls within this range couldn't be told apart from later
+/// formats, so `Payload` rejects these payload ids when constructed, serialized or
+/// deserialized
+pub const RESERVED_FORMAT_BYTES: std::ops::RangeInclusive<u8> = 0xf0..=0xff;
+/// first byte of a serialized `PayloadV2`
+pub const PAYLOAD_V2_VERSION: u8 = 0xf2;
+
+/// a payload in the v2 wire format, which adds a leading version byte and a crc32 checksum
+/// of the data to the v1 `Payload` layout
+///
+/// serialized as `PAYLOAD_V2_VERSION`, the payload id, the big-endian u16 length, the data,
+/// and the big-endian crc32 of the data
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayloadV2 {
+    pub payload_id: u8,
+    /// the actual data contained by the payload, limited to `MAX_PAYLOAD_LEN` bytes
+    pub data: Vec<u8>,
+}
+
+impl PayloadV2 {
+    /// creates a new payload, returning an error if `data` exceeds `MAX_PAYLOAD_LEN` bytes
+    pub fn new(payload_id: u8, data: Vec<u8>) -> Result<Self, PayloadError> {
+        if data.len() > MAX_PAYLOAD_LEN {
+            return Err(PayloadError::TooLarge(data.len()));
+        }
         Ok(Self { payload_id, data })
     }
+    /// returns the crc32 (ieee) checksum of the data
+    pub fn checksum(&self) -> u32 {
+        crc32(&self.data)
+    }
+}
+
+impl BorshSerialize for PayloadV2 {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        if self.data.len() > MAX_PAYLOAD_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                PayloadError::TooLarge(self.data.len()),
+            ));
+        }
+        PAYLOAD_V2_VERSION.serialize(writer)?;
+        self.payload_id.serialize(writer)?;
+        (self.data.len() as u16).to_be_bytes().serialize(writer)?;
+        writer.write_all(&self.data)?;
+        self.checksum().to_be_bytes().serialize(writer)
+    }
+}
+
+impl BorshDeserialize for PayloadV2 {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let truncated = |err: std::io::Error| match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => std::io::ErrorKind::InvalidData.into(),
+            _ => err,
+        };
+        let mut header = [0_u8; 4];
+        reader.read_exact(&mut header).map_err(truncated)?;
+        if header[0] != PAYLOAD_V2_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unexpected payload version {}", header[0]),
+            ));
+        }
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        // matches the limit enforced when serializing
+        if length > MAX_PAYLOAD_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                PayloadError::TooLarge(length),
+            ));
+        }
+        let mut data = vec![0_u8; length];
+        reader.read_exact(&mut data).map_err(truncated)?;
+        let mut checksum = [0_u8; 4];
+        reader.read_exact(&mut checksum).map_err(truncated)?;
+        let payload = Self {
+            payload_id: header[1],
+            data,
+        };
+        if payload.checksum() != u32::from_be_bytes(checksum) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "payload checksum mismatch",
+            ));
+        }
+        Ok(payload)
+    }
+}
+
+/// a payload decoded from any supported wire format, see `Payload::decode_any`
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnyPayload {
+    V1(Payload),
+    V2(PayloadV2),
+}
+
+impl AnyPayload {
+    pub fn payload_id(&self) -> u8 {
+        match self {
+            Self::V1(payload) => payload.payload_id,
+            Self::V2(payload) => payload.payload_id,
+        }
+    }
+    pub fn data(&self) -> &[u8] {
+        match self {
+            Self::V1(payload) => &payload.data,
+            Self::V2(payload) => &payload.data,
+        }
+    }
+}
+
+/// computes the crc32 (ieee 802.3) checksum of `data`
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// payload id used by the wormhole relayer for delivery instructions
+pub const RELAYER_PAYLOAD_ID_DELIVERY_INSTRUCTION: u8 = 1;
+/// payload id used by the wormhole relayer for redelivery instructions
+pub const RELAYER_PAYLOAD_ID_REDELIVERY_INSTRUCTION: u8 = 2;
+/// key type used by the wormhole relayer to reference another vaa
+pub const RELAYER_VAA_KEY_TYPE: u8 = 1;
+
+/// the payload emitted by the wormhole relayer (automatic relaying) contract when a delivery
+/// is requested, which wraps the application payload alongside delivery and refund information
+///
+/// all integers are big-endian, uint256 values are kept as raw 32 byte words, and
+/// variable length fields are prefixed with a u32 length
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeliveryInstruction {
+    /// wormhole chain id of the chain the payload is being delivered to
+    pub target_chain: u16,
+    /// wormhole formatted address of the contract receiving the payload
+    pub target_address: [u8; 32],
+    /// the application payload being delivered
+    pub payload: Vec<u8>,
+    /// uint256 amount of receiver value requested on the target chain
+    pub requested_receiver_value: [u8; 32],
+    /// uint256 amount of extra receiver value paid for on the target chain
+    pub extra_receiver_value: [u8; 32],
+    /// delivery provider specific execution parameters (ie: gas limit)
+    pub encoded_execution_info: Vec<u8>,
+    /// wormhole chain id of the chain refunds are sent to
+    pub refund_chain: u16,
+    /// wormhole formatted address which receives refunds
+    pub refund_address: [u8; 32],
+    /// delivery provider on the refund chain
+    pub refund_delivery_provider: [u8; 32],
+    /// delivery provider on the source chain
+    pub source_delivery_provider: [u8; 32],
+    /// wormhole formatted address of the contract which requested the delivery
+    pub sender_address: [u8; 32],
+    /// additional messages that must be delivered alongside the payload
+    pub message_keys: Vec<MessageKey>,
+}
+
+/// references an additional message that is delivered alongside a delivery instruction
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessageKey {
+    /// a vaa identified by its emitter and sequence
+    Vaa {
+        chain_id: u16,
+        emitter_address: [u8; 32],
+        sequence: u64,
+    },
+    /// any other key type, left encoded
+    Other { key_type: u8, encoded: Vec<u8> },
+}
+
+impl DeliveryInstruction {
+    /// decodes a wormhole relayer payload, returning an error if the payload is not
+    /// a delivery instruction or is malformed
+    pub fn try_from_payload(payload: &[u8]) -> std::io::Result<Self> {
+        let mut reader = RelayerReader { data: payload };
+        match reader.u8()? {
+            RELAYER_PAYLOAD_ID_DELIVERY_INSTRUCTION => {}
+            RELAYER_PAYLOAD_ID_REDELIVERY_INSTRUCTION => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "redelivery instructions are not supported",
+                ))
+            }
+            id => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unknown relayer payload id {id}"),
+                ))
+            }
+        }
+        let instruction = Self {
+            target_chain: reader.u16()?,
+            target_address: reader.bytes32()?,
+            payload: reader.bytes()?,
+            requested_receiver_value: reader.bytes32()?,
+            extra_receiver_value: reader.bytes32()?,
+            encoded_execution_info: reader.bytes()?,
+            refund_chain: reader.u16()?,
+            refund_address: reader.bytes32()?,
+            refund_delivery_provider: reader.bytes32()?,
+            source_delivery_provider: reader.bytes32()?,
+            sender_address: reader.bytes32()?,
+            message_keys: {
+                let count = reader.u8()?;
+                let mut keys = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    keys.push(match reader.u8()? {
+                        RELAYER_VAA_KEY_TYPE => MessageKey::Vaa {
+                            chain_id: reader.u16()?,
+                            emitter_address: reader.bytes32()?,
+                            sequence: u64::from_be_bytes(reader.take::<8>()?),
+                        },
+                        key_type => MessageKey::Other {
+                            key_type,
+                            encoded: reader.bytes()?,
+                        },
+                    });
+                }
+                keys
+            },
+        };
+        if !reader.data.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "trailing bytes after delivery instruction",
+            ));
+        }
+        Ok(instruction)
+    }
+}
+
+/// reads big-endian fields out of a relayer payload
+struct RelayerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RelayerReader<'a> {
+    fn take<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        if self.data.len() < N {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut out = [0_u8; N];
+        out.copy_from_slice(&self.data[..N]);
+        self.data = &self.data[N..];
+        Ok(out)
+    }
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+    fn u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_be_bytes(self.take()?))
+    }
+    fn bytes32(&mut self) -> std::io::Result<[u8; 32]> {
+        self.take()
+    }
+    fn bytes(&mut self) -> std::io::Result<Vec<u8>> {
+        let length = u32::from_be_bytes(self.take()?) as usize;
+        if self.data.len() < length {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let (out, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(out.to_vec())
+    }
 }
 
 #[cfg(test)]
@@ -54,4 +577,299 @@ mod test {
         let payload2 = Payload::try_from_slice(&ser_p[..]).unwrap();
         assert_eq!(payload.data, payload2.data);
     }
+    #[test]
+    fn test_payload_new() {
+        let payload = Payload::new(1, vec![]).unwrap();
+        assert_eq!(payload.try_to_vec().unwrap(), vec![1, 0, 0]);
+        assert_eq!(
+            Payload::try_from_slice(&payload.try_to_vec().unwrap()).unwrap(),
+            payload
+        );
+
+        let payload = Payload::new(1, vec![7; MAX_PAYLOAD_LEN]).unwrap();
+        let ser_p = payload.try_to_vec().unwrap();
+        assert_eq!(ser_p.len(), MAX_PAYLOAD_LEN + 3);
+        assert_eq!(Payload::try_from_slice(&ser_p).unwrap(), payload);
+
+        assert_eq!(
+            Payload::new(1, vec![7; MAX_PAYLOAD_LEN + 1]).unwrap_err(),
+            PayloadError::TooLarge(MAX_PAYLOAD_LEN + 1)
+        );
+        let oversized = Payload {
+            payload_id: 1,
+            data: vec![7; MAX_PAYLOAD_LEN + 1],
+        };
+        assert_eq!(
+            oversized.try_to_vec().unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+    #[test]
+    fn test_versioned_payload() {
+        let versioned = VersionedPayload {
+            version: 2,
+            payload: Payload::new(1, b"Hello World".to_vec()).unwrap(),
+        };
+        let ser_p = versioned.try_to_vec().unwrap();
+        assert_eq!(ser_p[0], 2);
+        assert_eq!(ser_p[1..], versioned.payload.try_to_vec().unwrap()[..]);
+        assert_eq!(
+            VersionedPayload::try_from_slice(&ser_p, 1..=2).unwrap(),
+            versioned
+        );
+
+        let err = VersionedPayload::try_from_slice(&ser_p, 3..).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "unsupported payload version 2");
+        assert!(VersionedPayload::try_from_slice(&[], ..).is_err());
+    }
+    #[test]
+    fn test_payload_borsh_framing() {
+        let payload = Payload::new(1, b"Hello World".to_vec()).unwrap();
+        let ser = (payload.clone(), 69_u64).try_to_vec().unwrap();
+        let (payload2, trailing) = <(Payload, u64)>::try_from_slice(&ser).unwrap();
+        assert_eq!(payload2, payload);
+        assert_eq!(trailing, 69);
+    }
+    #[test]
+    fn test_expect_id() {
+        let payload = Payload::new(3, b"Hello World".to_vec()).unwrap();
+        assert_eq!(payload.expect_id(3), Ok(&payload));
+        assert_eq!(
+            payload.expect_id(4),
+            Err(UnexpectedPayloadId {
+                expected: 4,
+                actual: 3
+            })
+        );
+    }
+    #[test]
+    fn test_payload_truncated() {
+        for input in [&[][..], &[1, 0][..], &[1, 0, 4, 1, 2, 3][..]] {
+            assert_eq!(
+                Payload::try_from_slice(input).unwrap_err().kind(),
+                std::io::ErrorKind::InvalidData
+            );
+        }
+    }
+    #[test]
+    fn test_payload_hex() {
+        let payload = Payload {
+            payload_id: 1,
+            data: b"Hello World".to_vec(),
+        };
+        let encoded = payload.to_hex().unwrap();
+        assert_eq!(encoded, "01000b48656c6c6f20576f726c64");
+        assert_eq!(Payload::from_hex(&encoded).unwrap(), payload);
+        assert_eq!(Payload::from_hex(&format!("0x{encoded}")).unwrap(), payload);
+
+        let err = Payload::from_hex("01000bzz").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let err = Payload::from_hex("01000b48").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let oversized = Payload {
+            payload_id: 1,
+            data: vec![7_u8; MAX_PAYLOAD_LEN + 1],
+        };
+        let err = oversized.to_hex().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    #[test]
+    fn test_fragment_roundtrip() {
+        for len in [1, MAX_PAYLOAD_LEN, 5000] {
+            let data = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+            let fragments = Payload::fragment(7, &data);
+            assert_eq!(
+                fragments.len(),
+                len.div_ceil(MAX_PAYLOAD_LEN - FRAGMENT_HEADER_LEN)
+            );
+            for fragment in &fragments {
+                assert_eq!(fragment.payload_id, 7);
+                assert!(fragment.data.len() <= MAX_PAYLOAD_LEN);
+                // every fragment can be published as a regular payload
+                let ser_p = fragment.try_to_vec().unwrap();
+                assert_eq!(&Payload::try_from_slice(&ser_p).unwrap(), fragment);
+            }
+            assert_eq!(Payload::reassemble(&fragments).unwrap(), data);
+        }
+        let fragments = Payload::fragment(7, &[]);
+        assert_eq!(fragments.len(), 1);
+        assert_eq!(Payload::reassemble(&fragments).unwrap(), Vec::<u8>::new());
+    }
+    #[test]
+    fn test_reassemble_errors() {
+        let fragments = Payload::fragment(7, &[1_u8; 5000]);
+        assert_eq!(fragments.len(), 5);
+        assert_eq!(Payload::reassemble(&[]), Err(FragmentError::Empty));
+
+        let mut swapped = fragments.clone();
+        swapped.swap(1, 2);
+        assert_eq!(
+            Payload::reassemble(&swapped),
+            Err(FragmentError::OutOfOrder {
+                expected: 1,
+                actual: 2
+            })
+        );
+        let mut missing = fragments.clone();
+        missing.remove(2);
+        assert_eq!(
+            Payload::reassemble(&missing),
+            Err(FragmentError::Missing {
+                received: 4,
+                total: 5
+            })
+        );
+        assert_eq!(
+            Payload::reassemble(&fragments[..4]),
+            Err(FragmentError::Missing {
+                received: 4,
+                total: 5
+            })
+        );
+        let mut mixed = fragments.clone();
+        mixed[3].payload_id = 8;
+        assert_eq!(
+            Payload::reassemble(&mixed),
+            Err(FragmentError::PayloadIdMismatch {
+                position: 3,
+                expected: 7,
+                actual: 8
+            })
+        );
+        let mut other = fragments.clone();
+        other[4] = Payload::fragment(7, &[1_u8; 100])[0].clone();
+        assert_eq!(
+            Payload::reassemble(&other),
+            Err(FragmentError::TotalMismatch {
+                position: 4,
+                expected: 5,
+                actual: 1
+            })
+        );
+        let mut malformed = fragments;
+        malformed[1].data.truncate(3);
+        assert_eq!(
+            Payload::reassemble(&malformed),
+            Err(FragmentError::Malformed(1))
+        );
+    }
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+    #[test]
+    fn test_decode_any() {
+        let v1 = Payload::new(1, b"Hello World".to_vec()).unwrap();
+        assert_eq!(
+            Payload::decode_any(&v1.try_to_vec().unwrap()).unwrap(),
+            AnyPayload::V1(v1)
+        );
+
+        let v2 = PayloadV2::new(1, b"Hello World".to_vec()).unwrap();
+        let ser_p = v2.try_to_vec().unwrap();
+        assert_eq!(ser_p[0], PAYLOAD_V2_VERSION);
+        assert_eq!(ser_p.len(), 1 + 3 + 11 + 4);
+        assert_eq!(ser_p[ser_p.len() - 4..], v2.checksum().to_be_bytes());
+        let decoded = Payload::decode_any(&ser_p).unwrap();
+        assert_eq!(decoded.payload_id(), 1);
+        assert_eq!(decoded.data(), b"Hello World");
+        assert_eq!(decoded, AnyPayload::V2(v2));
+
+        // corrupted data fails the checksum
+        let mut corrupted = ser_p.clone();
+        corrupted[4] ^= 1;
+        assert_eq!(
+            Payload::decode_any(&corrupted).unwrap_err().to_string(),
+            "payload checksum mismatch"
+        );
+        assert!(Payload::decode_any(&ser_p[..ser_p.len() - 1]).is_err());
+        // other reserved first bytes are rejected instead of decoded as v1
+        let mut unknown = ser_p.clone();
+        unknown[0] = 0xf3;
+        assert_eq!(
+            Payload::decode_any(&unknown).unwrap_err().to_string(),
+            "unsupported payload version 243"
+        );
+        assert!(Payload::decode_any(&[]).is_err());
+    }
+    #[test]
+    fn test_reserved_payload_ids() {
+        assert_eq!(
+            Payload::new(0xf0, vec![]),
+            Err(PayloadError::ReservedPayloadId(0xf0))
+        );
+        assert!(Payload::new(0xef, vec![]).is_ok());
+        let reserved = Payload {
+            payload_id: PAYLOAD_V2_VERSION,
+            data: b"Hello World".to_vec(),
+        };
+        assert_eq!(
+            reserved.try_to_vec().unwrap_err().to_string(),
+            "payload id 242 is reserved for identifying payload formats"
+        );
+        let mut ser_p = Payload::new(1, b"Hello World".to_vec())
+            .unwrap()
+            .try_to_vec()
+            .unwrap();
+        ser_p[0] = 0xff;
+        assert_eq!(
+            Payload::try_from_slice(&ser_p).unwrap_err().to_string(),
+            "payload id 255 is reserved for identifying payload formats"
+        );
+    }
+    #[test]
+    fn test_payload_v2_length_limit() {
+        let oversized = PayloadV2 {
+            payload_id: 1,
+            data: vec![7_u8; MAX_PAYLOAD_LEN + 1],
+        };
+        assert!(oversized.try_to_vec().is_err());
+        // a v2 payload declaring more data than can be serialized is rejected, even when the
+        // data and checksum are present
+        let mut ser_p = vec![PAYLOAD_V2_VERSION, 1];
+        ser_p.extend_from_slice(&(oversized.data.len() as u16).to_be_bytes());
+        ser_p.extend_from_slice(&oversized.data);
+        ser_p.extend_from_slice(&oversized.checksum().to_be_bytes());
+        assert_eq!(
+            PayloadV2::try_from_slice(&ser_p).unwrap_err().to_string(),
+            "payload data is 1025 bytes, exceeding the 1024 byte limit"
+        );
+        let max = PayloadV2::new(1, vec![7_u8; MAX_PAYLOAD_LEN]).unwrap();
+        assert_eq!(
+            PayloadV2::try_from_slice(&max.try_to_vec().unwrap()).unwrap(),
+            max
+        );
+    }
+    #[test]
+    fn test_delivery_instruction_hand_encoded() {
+        // hand assembled to the WormholeRelayer delivery instruction layout (sender on chain 30
+        // targeting chain 2 with one vaa message key), not taken from an on-chain SendEvent
+        let instruction_hex = "0100020000000000000000000000003ee18b2214aff97000d974cf647e7c347e8fa5850000000b48656c6c6f20576f726c6400000000000000000000000000000000000000000000000000038d7ea4c680000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000003d09000000000000000000000000000000000000000000000000000000005d21dba00001e0000000000000000000000008a6a5dd21bbe2a1f4e2b6a4b5a0d8f1e7c3b2a190000000000000000000000007a0a53847776f7e94cc35742971acb2217b0db810000000000000000000000007a0a53847776f7e94cc35742971acb2217b0db810000000000000000000000008a6a5dd21bbe2a1f4e2b6a4b5a0d8f1e7c3b2a190101001e0000000000000000000000008a6a5dd21bbe2a1f4e2b6a4b5a0d8f1e7c3b2a190000000000001092";
+        let raw = hex::decode(instruction_hex).unwrap();
+        let instruction = DeliveryInstruction::try_from_payload(&raw).unwrap();
+        assert_eq!(instruction.target_chain, 2);
+        assert_eq!(
+            hex::encode(instruction.target_address),
+            "0000000000000000000000003ee18b2214aff97000d974cf647e7c347e8fa585"
+        );
+        assert_eq!(instruction.payload, b"Hello World".to_vec());
+        assert_eq!(instruction.refund_chain, 30);
+        assert_eq!(
+            instruction.message_keys,
+            vec![MessageKey::Vaa {
+                chain_id: 30,
+                emitter_address: instruction.sender_address,
+                sequence: 4242,
+            }]
+        );
+        // truncated payloads must error instead of panicking
+        assert!(DeliveryInstruction::try_from_payload(&raw[..raw.len() - 1]).is_err());
+        // redelivery instructions are rejected
+        let mut redelivery = raw.clone();
+        redelivery[0] = RELAYER_PAYLOAD_ID_REDELIVERY_INSTRUCTION;
+        assert!(DeliveryInstruction::try_from_payload(&redelivery).is_err());
+    }
 }
@@ -0,0 +1,95 @@
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    program_error::ProgramError,
+    program_pack::{self, IsInitialized, Sealed},
+    pubkey::Pubkey,
+};
+
+/// account marking a vaa as consumed by the program owning it, see `claim_vaa`
+///
+/// the account only exists once the vaa is claimed, so its existence is what prevents
+/// replays, the fields identify the claimed vaa for auditing
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Claim {
+    /// chain which emitted the vaa
+    pub emitter_chain: u16,
+    /// address of the emitter, in the 32 byte wormhole format
+    pub emitter_address: [u8; 32],
+    /// sequence of the vaa
+    pub sequence: u64,
+}
+
+impl Claim {
+    /// derives the claim pda of the vaa, see `derive_claim`
+    pub fn derive(&self, program_id: Pubkey) -> (Pubkey, u8) {
+        crate::utils::derivations::derive_claim(
+            program_id,
+            self.emitter_chain,
+            &self.emitter_address,
+            self.sequence,
+        )
+    }
+}
+
+impl Sealed for Claim {}
+impl IsInitialized for Claim {
+    fn is_initialized(&self) -> bool {
+        // chain id 0 never emits a vaa
+        self.emitter_chain != 0
+    }
+}
+
+impl program_pack::Pack for Claim {
+    const LEN: usize = 42;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 42];
+        let (emitter_chain, emitter_address, sequence) = array_refs![src, 2, 32, 8];
+        Ok(Self {
+            emitter_chain: u16::from_le_bytes(*emitter_chain),
+            emitter_address: *emitter_address,
+            sequence: u64::from_le_bytes(*sequence),
+        })
+    }
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 42];
+        let (_emitter_chain, _emitter_address, _sequence) = mut_array_refs![dst, 2, 32, 8];
+        _emitter_chain.copy_from_slice(&self.emitter_chain.to_le_bytes());
+        _emitter_address.copy_from_slice(&self.emitter_address);
+        _sequence.copy_from_slice(&self.sequence.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use solana_program::program_pack::Pack;
+
+    use super::*;
+    #[test]
+    fn test_claim_pack_unpack() {
+        let claim = Claim {
+            emitter_chain: 2,
+            emitter_address: [7_u8; 32],
+            sequence: 69,
+        };
+        let mut buffer = [0_u8; 42];
+        Claim::pack(claim, &mut buffer).unwrap();
+        assert_eq!(Claim::unpack(&buffer).unwrap(), claim);
+        assert_eq!(buffer[..2], 2_u16.to_le_bytes());
+        assert_eq!(buffer[34..], 69_u64.to_le_bytes());
+        // a zeroed account is not a claim
+        assert_eq!(
+            Claim::unpack(&[0_u8; 42]),
+            Err(ProgramError::UninitializedAccount)
+        );
+        assert_eq!(
+            claim.derive(crate::WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID),
+            crate::utils::derivations::derive_claim(
+                crate::WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID,
+                2,
+                &[7_u8; 32],
+                69
+            )
+        );
+    }
+}
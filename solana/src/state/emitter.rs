@@ -1,12 +1,16 @@
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use solana_program::{
+    program_error::ProgramError,
     program_pack::{self, IsInitialized, Sealed},
     pubkey::Pubkey,
 };
 use wormhole_anchor_sdk::wormhole::SEED_PREFIX_EMITTER;
 
+use crate::error::WormholeLiteError;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "client", derive(serde::Serialize, serde::Deserialize))]
 /// account used for signing and publishing messages to wormhole
 pub struct Emitter {
     /// program which owns the emitter account
@@ -26,6 +30,23 @@ impl Emitter {
     pub fn seed() -> &'static [u8] {
         SEED_PREFIX_EMITTER
     }
+    /// unpacks the emitter from the data of a fetched emitter account
+    ///
+    /// the `Pack` layout is authoritative, the serde derives are only intended for display
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        <Self as program_pack::Pack>::unpack(data)
+    }
+    /// unpacks the emitter from exactly `Emitter::LEN` bytes, returning
+    /// `ProgramError::InvalidAccountData` on any other length instead of panicking on short
+    /// input or ignoring trailing bytes like `unpack_from_slice`
+    ///
+    /// unlike `from_account_data` an uninitialized (zeroed) emitter is returned as is
+    pub fn try_unpack_exact(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != <Self as program_pack::Pack>::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        <Self as program_pack::Pack>::unpack_from_slice(src)
+    }
     /// derive the sequence account which uses the emitter account as a seed
     pub fn derive_sequence(&self) -> (Pubkey, u8) {
         let (emitter_pda, _) = self.derive();
@@ -36,6 +57,39 @@ impl Emitter {
     pub fn derive(&self) -> (Pubkey, u8) {
         crate::utils::derivations::derive_emitter(self.owner)
     }
+    /// derives the message pda the emitter will next publish to, see `derive_message_pda`
+    pub fn next_message_pda(&self, executing_program_id: Pubkey) -> (Pubkey, u8) {
+        crate::utils::derivations::derive_message_pda(
+            executing_program_id,
+            self.next_publishable_nonce,
+        )
+    }
+    /// derives the pda of an indexed emitter, see `derive_indexed_emitter`
+    pub fn derive_indexed(&self, emitter_index: u16) -> (Pubkey, u8) {
+        crate::utils::derivations::derive_indexed_emitter(self.owner, emitter_index)
+    }
+    /// derive the sequence account of an indexed emitter
+    pub fn derive_indexed_sequence(&self, emitter_index: u16) -> (Pubkey, u8) {
+        let (emitter_pda, _) = self.derive_indexed(emitter_index);
+        crate::utils::derivations::derive_sequence(emitter_pda)
+    }
+    /// verifies that the stored nonce (bump) and owner derive `expected_address`, catching
+    /// corrupted or spoofed emitter accounts
+    ///
+    /// only applies to the default emitter, not indexed emitters
+    pub fn verify_self(&self, expected_address: Pubkey) -> Result<(), ProgramError> {
+        match Pubkey::create_program_address(&[Self::seed(), &[self.nonce]], &self.owner) {
+            Ok(address) if address == expected_address => Ok(()),
+            _ => Err(WormholeLiteError::InvalidEmitter.into()),
+        }
+    }
+    /// returns true if the reserved padding is all zero
+    ///
+    /// the padding carries no meaning, so a non-zero padding indicates a corrupted account or
+    /// one written by an incompatible layout
+    pub fn validate_padding(&self) -> bool {
+        self.padding.iter().all(|byte| *byte == 0)
+    }
     /// given a slice of bytes, extract the last published nonce for "zero copy access"
     ///
     /// VALIDATE THE SLICE OF BYTES BEFORE CALLING
@@ -44,8 +98,27 @@ impl Emitter {
         data.copy_from_slice(&input[33..41]);
         u64::from_le_bytes(data)
     }
+    /// increments the nonce used when next publishing a message, returning an error
+    /// instead of overflowing
+    pub fn try_increment_publishable_nonce(&mut self) -> Result<(), ProgramError> {
+        self.next_publishable_nonce = match self.next_publishable_nonce.checked_add(1) {
+            Some(nonce) => nonce,
+            None => return Err(WormholeLiteError::NonceOverflow.log().into()),
+        };
+        Ok(())
+    }
+    /// returns the nonce to publish the next message with and increments the stored nonce in
+    /// one step, so the in-memory emitter is authoritative before the post_message cpi
+    pub fn reserve_next_nonce(&mut self) -> Result<u64, ProgramError> {
+        let nonce = self.next_publishable_nonce;
+        self.try_increment_publishable_nonce()?;
+        Ok(nonce)
+    }
+    /// increments the nonce used when next publishing a message, panicking on overflow
+    ///
+    /// prefer `try_increment_publishable_nonce` on-chain
     pub fn increment_publishable_nonce(&mut self) {
-        self.next_publishable_nonce = self.next_publishable_nonce.checked_add(1).unwrap();
+        self.try_increment_publishable_nonce().unwrap();
     }
 }
 
@@ -132,4 +205,145 @@ mod test {
             "4C33zbgcszH7DqsxQh8Jw3BN3WWfMLAG5nDPENBTZaWX"
         );
     }
+    #[test]
+    fn test_next_message_pda() {
+        let program_id = crate::WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let (_, nonce) = crate::utils::derivations::derive_emitter(program_id);
+        let et = Emitter {
+            owner: program_id,
+            nonce,
+            next_publishable_nonce: 69,
+            padding: [0_u8; 32],
+        };
+        let mut buffer = [0_u8; 73];
+        Emitter::pack(et, &mut buffer).unwrap();
+        // matches the pda derived from the nonce sliced out of the account data
+        assert_eq!(
+            et.next_message_pda(program_id),
+            crate::utils::derivations::derive_message_pda(
+                program_id,
+                Emitter::slice_next_publishable_nonce(&buffer)
+            )
+        );
+        assert_ne!(
+            et.next_message_pda(program_id),
+            crate::utils::derivations::derive_message_pda(program_id, 70)
+        );
+    }
+    #[test]
+    fn test_from_account_data() {
+        let et = Emitter {
+            owner: WORMHOLE_PROGRAM_ID,
+            nonce: 254,
+            next_publishable_nonce: 69,
+            padding: [0_u8; 32],
+        };
+        let mut buffer = [0_u8; 73];
+        Emitter::pack(et, &mut buffer).unwrap();
+        assert_eq!(Emitter::from_account_data(&buffer).unwrap(), et);
+        assert!(Emitter::from_account_data(&buffer[..72]).is_err());
+    }
+    #[test]
+    fn test_try_unpack_exact() {
+        let et = Emitter {
+            owner: WORMHOLE_PROGRAM_ID,
+            nonce: 254,
+            next_publishable_nonce: 69,
+            padding: [0_u8; 32],
+        };
+        let mut buffer = [0_u8; 74];
+        Emitter::pack(et, &mut buffer[..73]).unwrap();
+        assert_eq!(Emitter::try_unpack_exact(&buffer[..73]).unwrap(), et);
+        assert_eq!(
+            Emitter::try_unpack_exact(&buffer[..72]),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            Emitter::try_unpack_exact(&buffer),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            Emitter::try_unpack_exact(&[0_u8; 73]).unwrap().owner,
+            Pubkey::default()
+        );
+    }
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_emitter_json() {
+        let et = Emitter {
+            owner: WORMHOLE_PROGRAM_ID,
+            nonce: 254,
+            next_publishable_nonce: 69,
+            padding: [0_u8; 32],
+        };
+        let json = serde_json::to_string(&et).unwrap();
+        assert!(json.contains("\"next_publishable_nonce\":69"));
+        assert_eq!(serde_json::from_str::<Emitter>(&json).unwrap(), et);
+    }
+    #[test]
+    fn test_verify_self() {
+        let (pda, nonce) = crate::utils::derivations::derive_emitter(WORMHOLE_PROGRAM_ID);
+        let mut et = Emitter {
+            owner: WORMHOLE_PROGRAM_ID,
+            nonce,
+            next_publishable_nonce: 0,
+            padding: [0_u8; 32],
+        };
+        assert!(et.verify_self(pda).is_ok());
+        assert_eq!(
+            et.verify_self(Pubkey::new_unique()),
+            Err(WormholeLiteError::InvalidEmitter.into())
+        );
+        et.nonce = nonce.wrapping_sub(1);
+        assert!(et.verify_self(pda).is_err());
+        et.nonce = nonce;
+        et.owner = system_program::id();
+        assert!(et.verify_self(pda).is_err());
+    }
+    #[test]
+    fn test_validate_padding() {
+        let mut et = Emitter {
+            owner: WORMHOLE_PROGRAM_ID,
+            nonce: 255,
+            next_publishable_nonce: 0,
+            padding: [0_u8; 32],
+        };
+        assert!(et.validate_padding());
+        et.padding[31] = 1;
+        assert!(!et.validate_padding());
+    }
+    #[test]
+    fn test_reserve_next_nonce() {
+        let mut et = Emitter {
+            owner: WORMHOLE_PROGRAM_ID,
+            nonce: 255,
+            next_publishable_nonce: 69,
+            padding: [0_u8; 32],
+        };
+        assert_eq!(et.reserve_next_nonce().unwrap(), 69);
+        assert_eq!(et.reserve_next_nonce().unwrap(), 70);
+        assert_eq!(et.next_publishable_nonce, 71);
+        et.next_publishable_nonce = u64::MAX;
+        assert_eq!(
+            et.reserve_next_nonce(),
+            Err(WormholeLiteError::NonceOverflow.into())
+        );
+        assert_eq!(et.next_publishable_nonce, u64::MAX);
+    }
+    #[test]
+    fn test_try_increment_publishable_nonce_overflow() {
+        let mut et = Emitter {
+            owner: WORMHOLE_PROGRAM_ID,
+            nonce: 255,
+            next_publishable_nonce: u64::MAX - 1,
+            padding: [0_u8; 32],
+        };
+        assert!(et.try_increment_publishable_nonce().is_ok());
+        assert_eq!(et.next_publishable_nonce, u64::MAX);
+        assert_eq!(
+            et.try_increment_publishable_nonce(),
+            Err(WormholeLiteError::NonceOverflow.into())
+        );
+        assert_eq!(et.next_publishable_nonce, u64::MAX);
+    }
 }
@@ -1,5 +1,11 @@
+/// account marking a vaa as consumed by a program
+pub mod claim;
+
 /// account tracking information about published messages
 pub mod emitter;
 
 /// account which stores the vaa on-chain after verification
-pub mod vaa;
\ No newline at end of file
+pub mod vaa;
+
+/// vaas in the signed wire format produced by the guardian network
+pub mod signed_vaa;
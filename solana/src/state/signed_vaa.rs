@@ -0,0 +1,512 @@
+use thiserror::Error;
+
+/// the only vaa version currently defined by wormhole
+pub const VAA_VERSION: u8 = 1;
+/// the length of the fixed header fields (version, guardian_set_index, signature count)
+pub const VAA_HEADER_LEN: usize = 1 + 4 + 1;
+/// the length of a single encoded guardian signature (guardian index + 65 byte signature)
+pub const VAA_SIGNATURE_LEN: usize = 1 + 65;
+/// the length of the fixed body fields, excluding the payload
+pub const VAA_BODY_FIXED_LEN: usize = 4 + 4 + 2 + 32 + 8 + 1;
+
+/// errors returned while parsing a signed vaa
+#[derive(Debug, Error, PartialEq)]
+pub enum VaaParseError {
+    #[error("vaa truncated: needed {needed} bytes but only {remaining} remain")]
+    Truncated { needed: usize, remaining: usize },
+    #[error("unsupported vaa version {0}")]
+    UnsupportedVersion(u8),
+    #[error("vaa declares {declared} signatures, more than the limit of {max}")]
+    TooManySignatures { declared: usize, max: usize },
+    #[error("vaa payload of {len} bytes exceeds the limit of {max}")]
+    PayloadTooLarge { len: usize, max: usize },
+}
+
+/// caps on the variable length fields of an untrusted vaa, checked before anything is
+/// allocated for them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaaParseLimits {
+    pub max_signatures: usize,
+    pub max_payload: usize,
+}
+
+impl Default for VaaParseLimits {
+    /// allows a signature from every guardian of a full guardian set, and payloads of up to 4KB
+    fn default() -> Self {
+        Self {
+            max_signatures: 19,
+            max_payload: 4 * 1024,
+        }
+    }
+}
+
+impl VaaParseLimits {
+    /// returns an upper bound of the bytes allocated while parsing a vaa within these limits
+    pub fn max_allocation(&self) -> usize {
+        self.max_signatures * std::mem::size_of::<GuardianSignature>() + self.max_payload
+    }
+}
+
+/// errors returned while serializing a signed vaa
+#[derive(Debug, Error, PartialEq)]
+pub enum VaaSerializeError {
+    #[error("vaa has {0} signatures, more than the {max} the header can encode", max = u8::MAX)]
+    TooManySignatures(usize),
+}
+
+/// errors returned when a vaa does not survive a serialize/parse roundtrip
+#[derive(Debug, Error, PartialEq)]
+pub enum RoundtripError {
+    #[error("failed to serialize vaa: {0}")]
+    Serialize(#[from] VaaSerializeError),
+    #[error("failed to parse serialized vaa: {0}")]
+    Parse(#[from] VaaParseError),
+    #[error("vaa fields changed after roundtrip")]
+    FieldMismatch,
+    #[error("vaa digest changed after roundtrip")]
+    DigestMismatch,
+}
+
+/// a guardian's signature of the vaa digest, whereby `guardian_set_index` is the
+/// guardian's element index from the overall guardian set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GuardianSignature {
+    pub guardian_set_index: u8,
+    /// 64 byte signature followed by the recovery id
+    pub signature: [u8; 65],
+}
+
+/// the header of a signed vaa, containing the guardian signatures
+#[derive(Clone, Debug, PartialEq)]
+pub struct VaaHeader {
+    pub version: u8,
+    pub guardian_set_index: u32,
+    pub signatures: Vec<GuardianSignature>,
+}
+
+/// the body of a signed vaa, which is the data signed by the guardian network
+#[derive(Clone, Debug, PartialEq)]
+pub struct VaaBody {
+    pub timestamp: u32,
+    pub nonce: u32,
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub sequence: u64,
+    pub consistency_level: u8,
+    pub payload: Vec<u8>,
+}
+
+/// a vaa in the wire format produced by the guardian network
+///
+/// all integers are big-endian
+/// ```text
+/// header: version (u8), guardian_set_index (u32), signature count (u8),
+///         signatures ([guardian index (u8), signature (65 bytes)])
+/// body:   timestamp (u32), nonce (u32), emitter_chain (u16), emitter_address (32 bytes),
+///         sequence (u64), consistency_level (u8), payload (remaining bytes)
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedVaa {
+    pub header: VaaHeader,
+    pub body: VaaBody,
+}
+
+/// alias of `SignedVaa` used by the off-chain verification helpers
+pub type ParsedVaa = SignedVaa;
+
+/// parses a signed vaa from its wire format
+pub fn parse_vaa(bytes: &[u8]) -> Result<ParsedVaa, VaaParseError> {
+    SignedVaa::parse(bytes)
+}
+
+impl GuardianSignature {
+    /// returns the signature without the recovery id
+    pub fn raw_sig(&self) -> [u8; 64] {
+        let mut out = [0_u8; 64];
+        out.copy_from_slice(&self.signature[..64]);
+        out
+    }
+    /// returns the recovery id of the signature
+    pub fn recovery_id(&self) -> u8 {
+        self.signature[64]
+    }
+}
+
+impl VaaBody {
+    /// serializes the body, this is what is hashed and signed by the guardians
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(VAA_BODY_FIXED_LEN + self.payload.len());
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out.extend_from_slice(&self.emitter_chain.to_be_bytes());
+        out.extend_from_slice(&self.emitter_address);
+        out.extend_from_slice(&self.sequence.to_be_bytes());
+        out.push(self.consistency_level);
+        out.extend_from_slice(&self.payload);
+        out
+    }
+    /// returns the keccak256 hash of the serialized body, matching `post_vaa::hash_vaa`
+    pub fn digest(&self) -> [u8; 32] {
+        use sha3::Digest;
+        sha3::Keccak256::digest(self.to_bytes()).into()
+    }
+}
+
+impl From<&SignedVaa> for crate::instructions::post_vaa::PostVAADataIx {
+    fn from(vaa: &SignedVaa) -> Self {
+        Self {
+            version: vaa.header.version,
+            guardian_set_index: vaa.header.guardian_set_index,
+            timestamp: vaa.body.timestamp,
+            nonce: vaa.body.nonce,
+            emitter_chain: vaa.body.emitter_chain,
+            emitter_address: vaa.body.emitter_address,
+            sequence: vaa.body.sequence,
+            consistency_level: vaa.body.consistency_level,
+            payload: vaa.body.payload.clone(),
+        }
+    }
+}
+
+impl SignedVaa {
+    /// decodes the vaa payload as a `Payload`
+    pub fn decode_payload(&self) -> std::io::Result<crate::message_payload::Payload> {
+        borsh::BorshDeserialize::try_from_slice(&self.body.payload)
+    }
+    /// returns the sorted, deduplicated guardian indices which signed the vaa
+    pub fn signing_guardian_indices(&self) -> Vec<u8> {
+        let mut indices: Vec<u8> = self
+            .header
+            .signatures
+            .iter()
+            .map(|signature| signature.guardian_set_index)
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+    /// returns true if distinct guardians of a set of `guardian_set_size` guardians signed the
+    /// vaa often enough to reach quorum, see `utils::guardians::quorum`
+    ///
+    /// the signatures themselves are not verified, see `utils::guardians::verify_vaa_offchain`
+    pub fn quorum_met(&self, guardian_set_size: usize) -> bool {
+        let signers = self
+            .signing_guardian_indices()
+            .into_iter()
+            .filter(|index| (*index as usize) < guardian_set_size)
+            .count();
+        signers >= crate::utils::guardians::quorum(guardian_set_size)
+    }
+    /// parses a signed vaa from its wire format, within the default `VaaParseLimits`
+    pub fn parse(bytes: &[u8]) -> Result<Self, VaaParseError> {
+        Self::parse_with_limits(bytes, VaaParseLimits::default())
+    }
+    /// parses a signed vaa from its wire format, rejecting vaas whose signature count or
+    /// payload exceed `limits`
+    pub fn parse_with_limits(bytes: &[u8], limits: VaaParseLimits) -> Result<Self, VaaParseError> {
+        let mut reader = VaaReader { data: bytes };
+        let version = reader.u8()?;
+        if version != VAA_VERSION {
+            return Err(VaaParseError::UnsupportedVersion(version));
+        }
+        let guardian_set_index = u32::from_be_bytes(reader.take()?);
+        let num_signatures = reader.u8()? as usize;
+        if num_signatures > limits.max_signatures {
+            return Err(VaaParseError::TooManySignatures {
+                declared: num_signatures,
+                max: limits.max_signatures,
+            });
+        }
+        let mut signatures = Vec::with_capacity(num_signatures);
+        for _ in 0..num_signatures {
+            signatures.push(GuardianSignature {
+                guardian_set_index: reader.u8()?,
+                signature: reader.take()?,
+            });
+        }
+        if reader.data.len() > VAA_BODY_FIXED_LEN + limits.max_payload {
+            return Err(VaaParseError::PayloadTooLarge {
+                len: reader.data.len().saturating_sub(VAA_BODY_FIXED_LEN),
+                max: limits.max_payload,
+            });
+        }
+        let body = VaaBody {
+            timestamp: u32::from_be_bytes(reader.take()?),
+            nonce: u32::from_be_bytes(reader.take()?),
+            emitter_chain: u16::from_be_bytes(reader.take()?),
+            emitter_address: reader.take()?,
+            sequence: u64::from_be_bytes(reader.take()?),
+            consistency_level: reader.u8()?,
+            payload: reader.data.to_vec(),
+        };
+        Ok(Self {
+            header: VaaHeader {
+                version,
+                guardian_set_index,
+                signatures,
+            },
+            body,
+        })
+    }
+    /// serializes the vaa into its wire format, failing if there are more signatures than
+    /// the u8 signature count can encode
+    pub fn to_signed_vaa_bytes(&self) -> Result<Vec<u8>, VaaSerializeError> {
+        let num_signatures = u8::try_from(self.header.signatures.len())
+            .map_err(|_| VaaSerializeError::TooManySignatures(self.header.signatures.len()))?;
+        let body = self.body.to_bytes();
+        let mut out = Vec::with_capacity(
+            VAA_HEADER_LEN + self.header.signatures.len() * VAA_SIGNATURE_LEN + body.len(),
+        );
+        out.push(self.header.version);
+        out.extend_from_slice(&self.header.guardian_set_index.to_be_bytes());
+        out.push(num_signatures);
+        for signature in &self.header.signatures {
+            out.push(signature.guardian_set_index);
+            out.extend_from_slice(&signature.signature);
+        }
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+    /// serializes and re-parses the vaa, confirming that all fields and the digest are preserved,
+    /// used as a self-consistency check when ingesting untrusted vaas
+    pub fn validate_roundtrip(&self) -> Result<(), RoundtripError> {
+        let reparsed = Self::parse(&self.to_signed_vaa_bytes()?)?;
+        if reparsed.body.digest() != self.body.digest() {
+            return Err(RoundtripError::DigestMismatch);
+        }
+        if reparsed.ne(self) {
+            return Err(RoundtripError::FieldMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// reads big-endian fields out of a signed vaa
+struct VaaReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> VaaReader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], VaaParseError> {
+        if self.data.len() < N {
+            return Err(VaaParseError::Truncated {
+                needed: N,
+                remaining: self.data.len(),
+            });
+        }
+        let mut out = [0_u8; N];
+        out.copy_from_slice(&self.data[..N]);
+        self.data = &self.data[N..];
+        Ok(out)
+    }
+    fn u8(&mut self) -> Result<u8, VaaParseError> {
+        Ok(self.take::<1>()?[0])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::instructions::post_vaa::{hash_vaa, PostVAADataIx};
+    fn signed_vaa() -> SignedVaa {
+        SignedVaa {
+            header: VaaHeader {
+                version: 1,
+                guardian_set_index: 3,
+                signatures: (0..13)
+                    .map(|i| GuardianSignature {
+                        guardian_set_index: i,
+                        signature: [i + 1; 65],
+                    })
+                    .collect(),
+            },
+            body: VaaBody {
+                timestamp: 1700000069,
+                nonce: 42,
+                emitter_chain: 1,
+                emitter_address: [7_u8; 32],
+                sequence: 69,
+                consistency_level: 32,
+                payload: b"Hello World".to_vec(),
+            },
+        }
+    }
+    #[test]
+    fn test_parse_vaa() {
+        let vaa = signed_vaa();
+        let bytes = vaa.to_signed_vaa_bytes().unwrap();
+        assert_eq!(
+            bytes.len(),
+            VAA_HEADER_LEN + 13 * VAA_SIGNATURE_LEN + VAA_BODY_FIXED_LEN + 11
+        );
+        let parsed = parse_vaa(&bytes).unwrap();
+        assert_eq!(parsed, vaa);
+        assert_eq!(parsed.header.signatures[12].recovery_id(), 13);
+        assert_eq!(parsed.header.signatures[12].raw_sig(), [13_u8; 64]);
+        let post_vaa = PostVAADataIx {
+            version: 1,
+            guardian_set_index: 3,
+            timestamp: 1700000069,
+            nonce: 42,
+            emitter_chain: 1,
+            emitter_address: [7_u8; 32],
+            sequence: 69,
+            consistency_level: 32,
+            payload: b"Hello World".to_vec(),
+        };
+        assert_eq!(parsed.body.digest(), hash_vaa(&post_vaa));
+        assert_eq!(PostVAADataIx::from(&parsed), post_vaa);
+
+        assert_eq!(
+            parse_vaa(&bytes[..VAA_HEADER_LEN + 10]),
+            Err(VaaParseError::Truncated {
+                needed: 65,
+                remaining: 9
+            })
+        );
+        let mut bad_version = bytes.clone();
+        bad_version[0] = 2;
+        assert_eq!(
+            parse_vaa(&bad_version),
+            Err(VaaParseError::UnsupportedVersion(2))
+        );
+    }
+    #[test]
+    fn test_parse_vaa_hex() {
+        let vaa =
+            SignedVaa::parse(&hex::decode(crate::testing::TOKEN_TRANSFER_VAA).unwrap()).unwrap();
+        assert_eq!(vaa.header.version, 1);
+        assert_eq!(vaa.header.guardian_set_index, 3);
+        assert_eq!(vaa.header.signatures.len(), 2);
+        assert_eq!(vaa.body.timestamp, 1700000000);
+        assert_eq!(vaa.body.emitter_chain, 2);
+        assert_eq!(
+            hex::encode(vaa.body.emitter_address),
+            "0000000000000000000000003ee18b2214aff97000d974cf647e7c347e8fa585"
+        );
+        assert_eq!(vaa.body.sequence, 140000);
+        assert_eq!(vaa.body.consistency_level, 1);
+        assert_eq!(vaa.body.payload.len(), 133);
+        // matches the hash stored alongside the fixture
+        assert_eq!(
+            hex::encode(vaa.body.digest()),
+            "885d2e9aa653e2ceb4f009d3e595eea7348a6e6af30a53f9cd419ad96404b056"
+        );
+        let guardians = crate::testing::mock_guardians(2);
+        for signature in &vaa.header.signatures {
+            let guardian = &guardians[signature.guardian_set_index as usize];
+            assert!(crate::utils::guardians::verify_single_signature(
+                vaa.body.digest(),
+                signature.raw_sig(),
+                signature.recovery_id(),
+                guardian.eth_address,
+            )
+            .unwrap());
+        }
+        assert_eq!(
+            hex::encode(vaa.to_signed_vaa_bytes().unwrap()),
+            crate::testing::TOKEN_TRANSFER_VAA
+        );
+    }
+    #[test]
+    fn test_parse_limits() {
+        // the signature count is a u8, so the largest count a header can claim is 255. the
+        // limit is checked before the signatures are read, so only the header is needed
+        let header = [1, 0, 0, 0, 3, 255];
+        assert_eq!(
+            SignedVaa::parse(&header),
+            Err(VaaParseError::TooManySignatures {
+                declared: 255,
+                max: 19
+            })
+        );
+        let limits = VaaParseLimits {
+            max_signatures: 12,
+            max_payload: 10,
+        };
+        let vaa = signed_vaa();
+        assert_eq!(
+            SignedVaa::parse_with_limits(&vaa.to_signed_vaa_bytes().unwrap(), limits),
+            Err(VaaParseError::TooManySignatures {
+                declared: 13,
+                max: 12
+            })
+        );
+        let limits = VaaParseLimits {
+            max_signatures: 13,
+            ..limits
+        };
+        assert_eq!(
+            SignedVaa::parse_with_limits(&vaa.to_signed_vaa_bytes().unwrap(), limits),
+            Err(VaaParseError::PayloadTooLarge { len: 11, max: 10 })
+        );
+        let limits = VaaParseLimits {
+            max_payload: 11,
+            ..limits
+        };
+        assert_eq!(
+            SignedVaa::parse_with_limits(&vaa.to_signed_vaa_bytes().unwrap(), limits),
+            Ok(vaa)
+        );
+        assert_eq!(limits.max_allocation(), 13 * 66 + 11);
+        assert!(VaaParseLimits::default().max_allocation() < 8 * 1024);
+    }
+    #[test]
+    fn test_quorum_met() {
+        // 13 of 19 guardians is quorum
+        let mut vaa = signed_vaa();
+        vaa.header.signatures.reverse();
+        assert_eq!(vaa.signing_guardian_indices(), (0..13).collect::<Vec<u8>>());
+        assert!(vaa.quorum_met(19));
+
+        // duplicate signatures of a guardian count once
+        vaa.header.signatures[0].guardian_set_index = 0;
+        assert_eq!(vaa.signing_guardian_indices(), (0..12).collect::<Vec<u8>>());
+        assert!(!vaa.quorum_met(19));
+
+        // as do indices outside of the guardian set
+        vaa.header.signatures[0].guardian_set_index = 19;
+        assert_eq!(vaa.signing_guardian_indices().last(), Some(&19));
+        assert!(!vaa.quorum_met(19));
+
+        vaa.header.signatures[0].guardian_set_index = 18;
+        assert!(vaa.quorum_met(19));
+    }
+    #[test]
+    fn test_validate_roundtrip() {
+        let vaa = signed_vaa();
+        assert_eq!(vaa.validate_roundtrip(), Ok(()));
+
+        let mut unsupported = vaa.clone();
+        unsupported.header.version = 2;
+        assert_eq!(
+            unsupported.validate_roundtrip(),
+            Err(RoundtripError::Parse(VaaParseError::UnsupportedVersion(2)))
+        );
+
+        // the signature count is encoded as a u8, so excess signatures can't be serialized
+        let mut too_many_signatures = vaa;
+        too_many_signatures.header.signatures = vec![
+            GuardianSignature {
+                guardian_set_index: 0,
+                signature: [0_u8; 65],
+            };
+            256
+        ];
+        assert_eq!(
+            too_many_signatures.validate_roundtrip(),
+            Err(RoundtripError::Serialize(
+                VaaSerializeError::TooManySignatures(256)
+            ))
+        );
+        assert_eq!(
+            too_many_signatures
+                .to_signed_vaa_bytes()
+                .unwrap_err()
+                .to_string(),
+            "vaa has 256 signatures, more than the 255 the header can encode"
+        );
+        too_many_signatures.header.signatures.pop();
+        assert_eq!(too_many_signatures.to_signed_vaa_bytes().unwrap()[5], 255);
+    }
+}
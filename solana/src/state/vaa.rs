@@ -2,7 +2,7 @@ use std::fmt::Write;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Serialize, Deserialize};
-use solana_program::pubkey::Pubkey;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 
 #[repr(transparent)]
 #[derive(Default)]
@@ -44,15 +44,115 @@ pub struct MessageData {
     pub payload: Vec<u8>,
 }
 
-#[repr(transparent)]
+impl MessageData {
+    /// deserializes the message stored by a posted message or posted vaa account, rejecting
+    /// accounts whose declared payload length exceeds `max_payload` before allocating it
+    ///
+    /// accepts the "vaa", "msg" and "msu" magic prefixes, like `PostedVAAData`
+    pub fn try_from_account_data(data: &[u8], max_payload: usize) -> Result<Self, ProgramError> {
+        if data.len() < PostedMessageData::FIXED_LEN
+            || PostedKind::from_magic(&data[0..3]).is_none()
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let len_offset = PostedMessageData::FIXED_LEN - 4;
+        let mut payload_len = [0_u8; 4];
+        payload_len.copy_from_slice(&data[len_offset..PostedMessageData::FIXED_LEN]);
+        let payload_len = u32::from_le_bytes(payload_len) as usize;
+        if payload_len > max_payload || data.len() - PostedMessageData::FIXED_LEN < payload_len {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        <Self as BorshDeserialize>::deserialize(
+            &mut &data[3..PostedMessageData::FIXED_LEN + payload_len],
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)
+    }
+    /// deserializes the message payload as `T`, which must consume the entire payload
+    pub fn decode_payload<T: BorshDeserialize>(&self) -> std::io::Result<T> {
+        T::try_from_slice(&self.payload)
+    }
+    /// decodes the application `Payload` relayed by the message, see `decode_payload`
+    pub fn decode_app_payload(&self) -> std::io::Result<crate::message_payload::Payload> {
+        self.decode_payload()
+    }
+}
+
+impl PostedMessageData {
+    /// the size of the fixed length fields of a posted message account, including the magic
+    /// prefix and the u32 length prefix of the payload
+    pub const FIXED_LEN: usize = 3 + 1 + 1 + 4 + 32 + 4 + 4 + 8 + 2 + 32 + 4;
+    /// returns the account size needed to store a posted message with a payload of `payload_len` bytes
+    pub fn space(payload_len: usize) -> usize {
+        Self::FIXED_LEN + payload_len
+    }
+}
+
+/// the kind of account a `PostedVAAData` was read from, identified by its magic prefix
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PostedKind {
+    /// a posted vaa, verified by the guardians
+    #[default]
+    Vaa,
+    /// a message posted by an emitter, which has not been signed by the guardians
+    Msg,
+    /// an unreliable message posted by an emitter, whose account may be reused
+    Msu,
+}
+
+impl PostedKind {
+    /// returns the magic prefix of accounts of this kind
+    pub fn magic(self) -> &'static [u8; 3] {
+        match self {
+            Self::Vaa => b"vaa",
+            Self::Msg => b"msg",
+            Self::Msu => b"msu",
+        }
+    }
+    /// returns the kind identified by `magic`, if any
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            b"vaa" => Some(Self::Vaa),
+            b"msg" => Some(Self::Msg),
+            b"msu" => Some(Self::Msu),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct PostedVAAData {
     pub message: MessageData,
+    /// the kind of account the data was read from
+    kind: PostedKind,
+}
+
+impl PostedVAAData {
+    /// creates a posted vaa, which is serialized with the "vaa" magic
+    pub fn new(message: MessageData) -> Self {
+        Self {
+            message,
+            kind: PostedKind::Vaa,
+        }
+    }
+    /// returns the kind of account the data was read from, as posted messages can be
+    /// read as `PostedVAAData` too
+    pub fn kind(&self) -> PostedKind {
+        self.kind
+    }
+    /// decodes the application `Payload` carried by the vaa, see `MessageData::decode_app_payload`
+    pub fn app_payload(&self) -> std::io::Result<crate::message_payload::Payload> {
+        self.message.decode_app_payload()
+    }
+    /// returns the payload id of the application `Payload` without decoding it, or `None` if
+    /// the payload is empty
+    pub fn payload_id(&self) -> Option<u8> {
+        self.message.payload.first().copied()
+    }
 }
 
 impl BorshSerialize for PostedVAAData {
     fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_all(b"vaa")?;
+        writer.write_all(self.kind.magic())?;
         BorshSerialize::serialize(&self.message, writer)
     }
 }
@@ -67,14 +167,18 @@ impl BorshDeserialize for PostedVAAData {
         }
 
         // We accept "vaa", "msg", or "msu" because it's convenient to read all of these as PostedVAAData
-        let expected: [&[u8]; 3] = [b"vaa", b"msg", b"msu"];
-        let magic: &[u8] = &buf[0..3];
-        if !expected.contains(&magic) {
-            println!("magic mismatch");
-            return Err(std::io::ErrorKind::InvalidData.into());
+        let Some(kind) = PostedKind::from_magic(&buf[0..3]) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "magic mismatch, expected \"vaa\", \"msg\" or \"msu\" but got \"{}\"",
+                    buf[0..3].escape_ascii()
+                ),
+            ));
         };
         Ok(PostedVAAData {
             message: <MessageData as BorshDeserialize>::deserialize(&mut &buf[3..])?,
+            kind,
         })
     }
 }
@@ -97,6 +201,7 @@ impl Clone for PostedVAAData {
     fn clone(&self) -> Self {
         PostedVAAData {
             message: self.message.clone(),
+            kind: self.kind,
         }
     }
 }
@@ -120,11 +225,14 @@ impl BorshDeserialize for PostedMessageData {
         let expected = b"msg";
         let magic: &[u8] = &buf[0..3];
         if magic != expected {
-            println!(
-                "Magic mismatch. Expected {:?} but got {:?}",
-                expected, magic
-            );
-            return Err(std::io::ErrorKind::InvalidData.into());
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "magic mismatch, expected \"{}\" but got \"{}\"",
+                    expected.escape_ascii(),
+                    magic.escape_ascii()
+                ),
+            ));
         };
         Ok(PostedMessageData {
             message: <MessageData as BorshDeserialize>::deserialize(&mut &buf[3..])?,
@@ -141,3 +249,188 @@ impl std::ops::Deref for PostedMessageData {
         &self.message
     }
 }
+
+impl std::ops::DerefMut for PostedMessageData {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.message
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_message_data_try_from_account_data() {
+        let message = PostedVAAData::new(MessageData {
+            vaa_version: 1,
+            consistency_level: 32,
+            emitter_chain: 2,
+            emitter_address: [7_u8; 32],
+            sequence: 69,
+            payload: b"Hello World".to_vec(),
+            ..Default::default()
+        });
+        let data = message.try_to_vec().unwrap();
+        assert_eq!(data.len(), PostedMessageData::space(11));
+
+        let parsed = MessageData::try_from_account_data(&data, 1024).unwrap();
+        assert_eq!(parsed.payload, b"Hello World".to_vec());
+        assert_eq!(parsed.sequence, 69);
+        assert_eq!(
+            MessageData::try_from_account_data(&data, 10).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+
+        // a declared payload length far larger than the account
+        let mut oversized = data.clone();
+        oversized[PostedMessageData::FIXED_LEN - 4..PostedMessageData::FIXED_LEN]
+            .copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            MessageData::try_from_account_data(&oversized, usize::MAX).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+        assert_eq!(
+            MessageData::try_from_account_data(&oversized, 1024).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+
+        let mut bad_magic = data;
+        bad_magic[0..3].copy_from_slice(b"abc");
+        assert_eq!(
+            MessageData::try_from_account_data(&bad_magic, 1024).unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+    #[test]
+    fn test_posted_message_data_roundtrip() {
+        let mut posted = PostedMessageData {
+            message: MessageData {
+                vaa_version: 1,
+                consistency_level: 1,
+                vaa_signature_account: Pubkey::new_unique(),
+                submission_time: 1700000069,
+                nonce: 42,
+                emitter_chain: 1,
+                emitter_address: [7_u8; 32],
+                ..Default::default()
+            },
+        };
+        // fields are writable through DerefMut, like PostedVAAData
+        posted.sequence = 69;
+        posted.payload = b"Hello World".to_vec();
+        let data = posted.try_to_vec().unwrap();
+        assert_eq!(&data[0..3], b"msg");
+        assert_eq!(data.len(), PostedMessageData::space(11));
+
+        let parsed = PostedMessageData::try_from_slice(&data).unwrap();
+        assert_eq!(parsed.try_to_vec().unwrap(), data);
+        assert_eq!(parsed.vaa_version, 1);
+        assert_eq!(parsed.consistency_level, 1);
+        assert_eq!(parsed.vaa_signature_account, posted.vaa_signature_account);
+        assert_eq!(parsed.submission_time, 1700000069);
+        assert_eq!(parsed.nonce, 42);
+        assert_eq!(parsed.sequence, 69);
+        assert_eq!(parsed.emitter_chain, 1);
+        assert_eq!(parsed.emitter_address, [7_u8; 32]);
+        assert_eq!(parsed.payload, b"Hello World".to_vec());
+
+        // only the "msg" magic is accepted
+        let mut vaa = data;
+        vaa[0..3].copy_from_slice(b"vaa");
+        assert!(matches!(
+            PostedMessageData::try_from_slice(&vaa),
+            Err(err) if err.kind() == std::io::ErrorKind::InvalidData
+        ));
+    }
+    #[test]
+    fn test_magic_mismatch_error() {
+        let mut data = PostedVAAData::new(MessageData::default())
+            .try_to_vec()
+            .unwrap();
+        data[0..3].copy_from_slice(b"ab\xff");
+        let err = PostedVAAData::try_from_slice(&data).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "magic mismatch, expected \"vaa\", \"msg\" or \"msu\" but got \"ab\\xff\""
+        );
+        data[0..3].copy_from_slice(b"vaa");
+        let err = PostedMessageData::try_from_slice(&data).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "magic mismatch, expected \"msg\" but got \"vaa\""
+        );
+    }
+    #[test]
+    fn test_decode_payload() {
+        use crate::message_payload::Payload;
+        let payload = Payload::new(3, b"Hello World".to_vec()).unwrap();
+        let message = MessageData {
+            payload: payload.try_to_vec().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(message.decode_app_payload().unwrap(), payload);
+        assert_eq!(message.decode_payload::<Payload>().unwrap(), payload);
+        // the whole payload must be consumed
+        assert!(message.decode_payload::<u8>().is_err());
+
+        let message = MessageData {
+            payload: 69_u64.to_le_bytes().to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(message.decode_payload::<u64>().unwrap(), 69);
+        assert!(message.decode_app_payload().is_err());
+    }
+    #[test]
+    fn test_app_payload() {
+        use crate::message_payload::Payload;
+        let payload = Payload::new(3, b"Hello World".to_vec()).unwrap();
+        let data = PostedVAAData::new(MessageData {
+            payload: payload.try_to_vec().unwrap(),
+            ..Default::default()
+        })
+        .try_to_vec()
+        .unwrap();
+        let mut posted = PostedVAAData::try_from_slice(&data).unwrap();
+        assert_eq!(posted.payload_id(), Some(3));
+        assert_eq!(posted.app_payload().unwrap(), payload);
+
+        // the payload id is peeked even when the payload doesn't decode
+        posted.payload.truncate(5);
+        assert_eq!(posted.payload_id(), Some(3));
+        assert_eq!(
+            posted.app_payload().unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        posted.payload.clear();
+        assert_eq!(posted.payload_id(), None);
+        assert!(posted.app_payload().is_err());
+    }
+    #[test]
+    fn test_posted_kind() {
+        let message = MessageData {
+            sequence: 69,
+            payload: b"Hello World".to_vec(),
+            ..Default::default()
+        };
+        let data = PostedVAAData::new(message.clone()).try_to_vec().unwrap();
+        assert_eq!(&data[0..3], b"vaa");
+        for kind in [PostedKind::Vaa, PostedKind::Msg, PostedKind::Msu] {
+            let mut data = data.clone();
+            data[0..3].copy_from_slice(kind.magic());
+            let posted = PostedVAAData::try_from_slice(&data).unwrap();
+            assert_eq!(posted.kind(), kind);
+            assert_eq!(posted.sequence, 69);
+            // the observed magic is preserved when serializing again
+            assert_eq!(posted.try_to_vec().unwrap(), data);
+        }
+        let posted = PostedMessageData { message }.try_to_vec().unwrap();
+        assert_eq!(
+            PostedVAAData::try_from_slice(&posted).unwrap().kind(),
+            PostedKind::Msg
+        );
+        assert_eq!(PostedKind::from_magic(b"abc"), None);
+    }
+}
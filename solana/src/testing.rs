@@ -0,0 +1,407 @@
+//! helpers for producing deterministic test data, available to this crate's tests
+//! and to downstream crates through the `testing` feature
+
+use solana_program::{
+    account_info::AccountInfo, instruction::AccountMeta, program_pack::Pack, pubkey::Pubkey,
+    system_program, sysvar,
+};
+use wormhole_core_bridge_solana::state::GuardianSet;
+
+use crate::{
+    instructions::send_message::{
+        TransactionAccountKeys, BRIDGE_CONFIG_FEE_OFFSET, BRIDGE_CONFIG_LEN,
+    },
+    state::emitter::Emitter,
+    WORMHOLE_PROGRAM_ID,
+};
+
+/// owner of native programs such as the system program
+pub const NATIVE_LOADER_ID: Pubkey =
+    solana_program::pubkey!("NativeLoader1111111111111111111111111111111");
+
+/// returns a deterministic 32 byte digest for the given seed, computed as the
+/// keccak256 hash of the seed's little-endian bytes
+///
+/// useful when a test needs a stable vaa digest without hardcoding a 32 byte literal
+pub fn test_vaa_digest(seed: u64) -> [u8; 32] {
+    use sha3::Digest;
+    let mut h = sha3::Keccak256::default();
+    h.update(seed.to_le_bytes());
+    h.finalize().into()
+}
+
+/// returns the data of a core bridge emitter sequence account (see `derive_sequence`)
+/// tracking `sequence`
+///
+/// the account holds nothing but the sequence of the next message the emitter will
+/// publish, as an 8 byte little-endian u64 with no discriminator
+pub fn mock_sequence_account(sequence: u64) -> Vec<u8> {
+    sequence.to_le_bytes().to_vec()
+}
+
+/// returns a guardian set with the given index and keys, which expires at `expiration_time`
+/// (0 meaning the set never expires)
+pub fn mock_guardian_set(index: u32, keys: Vec<[u8; 20]>, expiration_time: u32) -> GuardianSet {
+    GuardianSet {
+        index,
+        keys,
+        creation_time: 0.into(),
+        expiration_time: expiration_time.into(),
+    }
+}
+
+/// owns the state of the six accounts used by the core bridge verify_signature instruction,
+/// in the order expected by `create_verify_signature_ix`
+///
+/// the accounts are in the state the runtime would present them at the start of the
+/// instruction, with the payer and the (not yet created) signature set signing
+pub struct VerifySignatureFixture {
+    pub keys: [Pubkey; 6],
+    pub owners: [Pubkey; 6],
+    pub lamports: [u64; 6],
+    pub data: [Vec<u8>; 6],
+}
+
+impl VerifySignatureFixture {
+    /// the (is_signer, is_writable) flags of each account
+    pub const FLAGS: [(bool, bool); 6] = [
+        (true, true),
+        (false, false),
+        (true, true),
+        (false, false),
+        (false, false),
+        (false, false),
+    ];
+    pub fn new(payer: Pubkey, guardian_set_index: u32, signature_set: Pubkey) -> Self {
+        let (guardian_set, _) = crate::utils::derivations::derive_guardian_set(guardian_set_index);
+        Self {
+            keys: [
+                payer,
+                guardian_set,
+                signature_set,
+                sysvar::instructions::id(),
+                sysvar::rent::id(),
+                system_program::id(),
+            ],
+            owners: [
+                system_program::id(),
+                WORMHOLE_PROGRAM_ID,
+                system_program::id(),
+                sysvar::id(),
+                sysvar::id(),
+                NATIVE_LOADER_ID,
+            ],
+            lamports: [1_000_000_000, 1_000_000, 0, 1, 1, 1],
+            data: [vec![], vec![0; 64], vec![], vec![0; 8], vec![0; 17], vec![]],
+        }
+    }
+    /// returns the fixture as account infos borrowing from the fixture's state
+    pub fn account_infos(&mut self) -> Vec<AccountInfo<'_>> {
+        self.keys
+            .iter()
+            .zip(self.owners.iter())
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .zip(Self::FLAGS)
+            .map(
+                |((((key, owner), lamports), data), (is_signer, is_writable))| {
+                    AccountInfo::new(
+                        key,
+                        is_signer,
+                        is_writable,
+                        lamports,
+                        data,
+                        owner,
+                        key.eq(&system_program::id()),
+                        0,
+                    )
+                },
+            )
+            .collect()
+    }
+}
+
+/// owns the state of the ten accounts used by `send_message`, in the order of
+/// `send_message::TransactionAccountKeys::to_account_metas`
+///
+/// by default the core bridge config charges no fee, and the emitter account holds the
+/// emitter of `program_id` at nonce 0. the keys may be replaced between sends through
+/// `set_keys`, the account state persists so the emitter nonce carries over
+pub struct SendMessageFixture {
+    pub metas: Vec<AccountMeta>,
+    pub owners: [Pubkey; 10],
+    pub lamports: [u64; 10],
+    pub data: [Vec<u8>; 10],
+}
+
+impl SendMessageFixture {
+    pub fn new(program_id: Pubkey, keys: TransactionAccountKeys) -> Self {
+        let mut data: [Vec<u8>; 10] = Default::default();
+        data[0] = vec![0_u8; BRIDGE_CONFIG_LEN];
+        Self {
+            metas: keys.to_account_metas(),
+            owners: [
+                WORMHOLE_PROGRAM_ID,
+                system_program::id(),
+                program_id,
+                WORMHOLE_PROGRAM_ID,
+                system_program::id(),
+                WORMHOLE_PROGRAM_ID,
+                sysvar::id(),
+                NATIVE_LOADER_ID,
+                sysvar::id(),
+                WORMHOLE_PROGRAM_ID,
+            ],
+            lamports: [1_000_000; 10],
+            data,
+        }
+        .with_emitter(Emitter {
+            owner: program_id,
+            nonce: crate::utils::derivations::derive_emitter(program_id).1,
+            next_publishable_nonce: 0,
+            padding: [0_u8; 32],
+        })
+    }
+    /// replaces the account keys, keeping the state of the accounts
+    pub fn set_keys(&mut self, keys: TransactionAccountKeys) {
+        self.metas = keys.to_account_metas();
+    }
+    /// replaces the core bridge config account data
+    pub fn with_bridge_config(mut self, data: Vec<u8>) -> Self {
+        self.data[0] = data;
+        self
+    }
+    /// sets the message fee charged by the core bridge config
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.data[0][BRIDGE_CONFIG_FEE_OFFSET..BRIDGE_CONFIG_FEE_OFFSET + 8]
+            .copy_from_slice(&fee.to_le_bytes());
+        self
+    }
+    /// replaces the state held by the emitter account
+    pub fn with_emitter(mut self, emitter: Emitter) -> Self {
+        self.data[2] = vec![0_u8; Emitter::LEN];
+        Emitter::pack(emitter, &mut self.data[2]).unwrap();
+        self
+    }
+    /// returns the state held by the emitter account
+    pub fn emitter(&self) -> Emitter {
+        Emitter::unpack(&self.data[2]).unwrap()
+    }
+    /// returns the fixture as account infos borrowing from the fixture's state
+    pub fn account_infos(&mut self) -> Vec<AccountInfo<'_>> {
+        self.metas
+            .iter()
+            .zip(self.owners.iter())
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .map(|(((meta, owner), lamports), data)| {
+                AccountInfo::new(
+                    &meta.pubkey,
+                    meta.is_signer,
+                    meta.is_writable,
+                    lamports,
+                    data,
+                    owner,
+                    false,
+                    0,
+                )
+            })
+            .collect()
+    }
+}
+
+/// a guardian key used to sign mock vaas
+#[cfg(test)]
+pub struct MockGuardian {
+    pub secret_key: libsecp256k1::SecretKey,
+    pub eth_address: [u8; 20],
+}
+
+/// returns `count` deterministic guardian keys
+#[cfg(test)]
+pub fn mock_guardians(count: u8) -> Vec<MockGuardian> {
+    (1..=count)
+        .map(|seed| {
+            let secret_key = libsecp256k1::SecretKey::parse(&[seed; 32]).unwrap();
+            let pubkey = libsecp256k1::PublicKey::from_secret_key(&secret_key).serialize();
+            let pubkey_hash = solana_program::keccak::hash(&pubkey[1..]);
+            let mut eth_address = [0_u8; 20];
+            eth_address.copy_from_slice(&pubkey_hash.0[12..]);
+            MockGuardian {
+                secret_key,
+                eth_address,
+            }
+        })
+        .collect()
+}
+
+/// signs `digest` (the keccak256 hash of a vaa body) the same way guardians do, returning
+/// the 64 byte signature followed by the recovery id
+#[cfg(test)]
+pub fn mock_guardian_sign(guardian: &MockGuardian, digest: [u8; 32]) -> [u8; 65] {
+    let hash = solana_program::keccak::hash(&digest);
+    let (signature, recovery_id) =
+        libsecp256k1::sign(&libsecp256k1::Message::parse(&hash.0), &guardian.secret_key);
+    let mut out = [0_u8; 65];
+    out[..64].copy_from_slice(&signature.serialize());
+    out[64] = recovery_id.serialize();
+    out
+}
+
+/// returns a vaa signed by the guardians at the `signers` indices of `guardians`
+#[cfg(test)]
+pub fn mock_guardian_signed_vaa(
+    guardian_set_index: u32,
+    guardians: &[MockGuardian],
+    signers: &[u8],
+) -> crate::state::signed_vaa::SignedVaa {
+    use crate::state::signed_vaa::{GuardianSignature, SignedVaa, VaaBody, VaaHeader};
+    let body = VaaBody {
+        timestamp: 1700000069,
+        nonce: 42,
+        emitter_chain: 1,
+        emitter_address: [7_u8; 32],
+        sequence: 69,
+        consistency_level: 32,
+        payload: b"Hello World".to_vec(),
+    };
+    let digest = body.digest();
+    SignedVaa {
+        header: VaaHeader {
+            version: 1,
+            guardian_set_index,
+            signatures: signers
+                .iter()
+                .map(|index| GuardianSignature {
+                    guardian_set_index: *index,
+                    signature: mock_guardian_sign(&guardians[*index as usize], digest),
+                })
+                .collect(),
+        },
+        body,
+    }
+}
+
+/// returns `guardian_count` mock guardians, guardian set `guardian_set_index` holding their
+/// keys, and a vaa signed by the guardians at the `signers` indices
+#[cfg(test)]
+pub fn mock_quorum_vaa(
+    guardian_set_index: u32,
+    guardian_count: u8,
+    signers: &[u8],
+) -> (
+    Vec<MockGuardian>,
+    GuardianSet,
+    crate::state::signed_vaa::SignedVaa,
+) {
+    let guardians = mock_guardians(guardian_count);
+    let guardian_set = mock_guardian_set(
+        guardian_set_index,
+        guardians
+            .iter()
+            .map(|guardian| guardian.eth_address)
+            .collect(),
+        0,
+    );
+    let vaa = mock_guardian_signed_vaa(guardian_set_index, &guardians, signers);
+    (guardians, guardian_set, vaa)
+}
+
+/// wire format token transfer vaa (the `ethereum_token_transfer` fixture body), signed by
+/// the first two mock guardians
+#[cfg(test)]
+pub const TOKEN_TRANSFER_VAA: &str = concat!(
+    "010000000302009ba619e6bca580b7a32d45160f3d06ee96e207aa189bcfe64a12e349b4420aff7a",
+    "2419f231f48b76df2eed77e65cc29dc3aaff88521f5ec2d18a5645ecb8c4570001558d55cf06c652",
+    "7db9d9afe1300fd7586ea42bea5ea23031e208d85c125dcd8c67fc758614df8c6e6a635a99c0a974",
+    "e36960676842e2795c410db8e7f8319f14016553f100000000000002000000000000000000000000",
+    "3ee18b2214aff97000d974cf647e7c347e8fa58500000000000222e0010100000000000000000000",
+    "00000000000000000000000000000000000005f5e100000000000000000000000000a0b86991c621",
+    "8b36c1d19d4a2e9eb0ce3606eb4800023f9a2ab3b0ec5d05a5c6b8d6e2ab1bdf0e4a2f1d4c8a6b3e",
+    "2d1c0b9a8f7e6d5c0001000000000000000000000000000000000000000000000000000000000000",
+    "0000",
+);
+
+/// a vaa body captured to disk alongside its expected hash, used as a regression corpus
+#[cfg(test)]
+#[derive(serde::Deserialize)]
+struct VaaFixture {
+    version: u8,
+    guardian_set_index: u32,
+    timestamp: u32,
+    nonce: u32,
+    emitter_chain: u16,
+    /// hex encoded 32 byte emitter address
+    emitter_address: String,
+    sequence: u64,
+    consistency_level: u8,
+    /// hex encoded payload
+    payload: String,
+    /// hex encoded keccak256 hash of the vaa body
+    hash: String,
+}
+
+/// parses every `.json` vaa fixture in `path`, recomputes its hash and compares it against the
+/// stored hash, returning the number of fixtures verified
+///
+/// panics with the offending file name on any parse failure or hash mismatch
+#[cfg(test)]
+pub fn verify_fixture_dir(path: impl AsRef<std::path::Path>) -> usize {
+    use crate::instructions::post_vaa::PostVAADataIx;
+    let mut verified = 0;
+    for entry in std::fs::read_dir(path).unwrap() {
+        let file = entry.unwrap().path();
+        if file.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let name = file.display();
+        let fixture: VaaFixture = serde_json::from_slice(&std::fs::read(&file).unwrap())
+            .unwrap_or_else(|err| panic!("{name}: failed to parse fixture {err:#?}"));
+        let emitter_address: [u8; 32] = hex::decode(&fixture.emitter_address)
+            .ok()
+            .and_then(|address| address.try_into().ok())
+            .unwrap_or_else(|| panic!("{name}: invalid emitter address"));
+        let vaa = PostVAADataIx {
+            version: fixture.version,
+            guardian_set_index: fixture.guardian_set_index,
+            timestamp: fixture.timestamp,
+            nonce: fixture.nonce,
+            emitter_chain: fixture.emitter_chain,
+            emitter_address,
+            sequence: fixture.sequence,
+            consistency_level: fixture.consistency_level,
+            payload: hex::decode(&fixture.payload)
+                .unwrap_or_else(|err| panic!("{name}: invalid payload {err:#?}")),
+        };
+        assert_eq!(
+            hex::encode(vaa.hash_vaa()),
+            fixture.hash,
+            "{name}: hash mismatch"
+        );
+        verified += 1;
+    }
+    verified
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_test_vaa_digest() {
+        assert_eq!(test_vaa_digest(69), test_vaa_digest(69));
+        assert_ne!(test_vaa_digest(69), test_vaa_digest(70));
+        assert_ne!(test_vaa_digest(0), [0_u8; 32]);
+    }
+    #[test]
+    fn test_mock_sequence_account() {
+        use borsh::BorshDeserialize;
+        let data = mock_sequence_account(69);
+        assert_eq!(data.len(), 8);
+        assert_eq!(u64::try_from_slice(&data).unwrap(), 69);
+    }
+    #[test]
+    fn test_verify_fixture_dir() {
+        let verified = verify_fixture_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/vaas"));
+        assert!(verified >= 2);
+    }
+}
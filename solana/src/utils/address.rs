@@ -0,0 +1,207 @@
+use std::{fmt, str::FromStr};
+
+use solana_program::pubkey::Pubkey;
+use thiserror::Error;
+
+use super::chain::Chain;
+
+/// errors returned when parsing the textual form of an emitter address
+#[derive(Debug, Error, PartialEq)]
+pub enum EmitterAddressError {
+    #[error("invalid evm address {0:?}, expected 20 hex encoded bytes")]
+    InvalidEvm(String),
+    #[error("invalid solana address {0:?}")]
+    InvalidSolana(String),
+    #[error("invalid wormhole address {0:?}, expected 32 hex encoded bytes")]
+    InvalidWormhole(String),
+}
+
+/// converts a solana account into its 32 byte wormhole address, which for solana
+/// is simply the bytes of the public key
+pub fn pubkey_to_wormhole(pubkey: &Pubkey) -> [u8; 32] {
+    pubkey.to_bytes()
+}
+
+/// a 32 byte address in the universal wormhole format, whereby addresses shorter than
+/// 32 bytes (ie: evm addresses) are left-padded with zeroes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WormholeAddress(pub [u8; 32]);
+
+impl WormholeAddress {
+    /// returns the wormhole address of a solana account
+    pub fn from_pubkey(pubkey: &Pubkey) -> Self {
+        Self(pubkey_to_wormhole(pubkey))
+    }
+    /// returns the wormhole address of a 20 byte evm address
+    pub fn from_eth(address: [u8; 20]) -> Self {
+        let mut out = [0_u8; 32];
+        out[12..].copy_from_slice(&address);
+        Self(out)
+    }
+    /// returns the lowercase hex encoding of the address, without a `0x` prefix
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+    /// returns the raw address bytes
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+/// parses a 20 byte hex encoded evm address, optionally prefixed with `0x`, into its
+/// left-padded 32 byte wormhole address
+pub fn emitter_address_from_evm(addr: &str) -> Result<[u8; 32], EmitterAddressError> {
+    let address =
+        decode_hex(addr).ok_or_else(|| EmitterAddressError::InvalidEvm(addr.to_string()))?;
+    Ok(WormholeAddress::from_eth(address).to_bytes())
+}
+
+/// returns the wormhole address of a solana emitter
+pub fn emitter_address_from_solana(pubkey: &Pubkey) -> [u8; 32] {
+    pubkey_to_wormhole(pubkey)
+}
+
+/// parses an emitter address of `chain` from the textual form native to that chain
+///
+/// solana and pythnet addresses are base58 public keys, evm chains use 20 byte hex addresses,
+/// and every other chain is expected to use the 32 byte hex encoded wormhole address
+pub fn normalize_emitter_address(chain: u16, input: &str) -> Result<[u8; 32], EmitterAddressError> {
+    match Chain::from(chain) {
+        Chain::Solana | Chain::Pythnet => Pubkey::from_str(input)
+            .map(|pubkey| emitter_address_from_solana(&pubkey))
+            .map_err(|_| EmitterAddressError::InvalidSolana(input.to_string())),
+        Chain::Ethereum
+        | Chain::Bsc
+        | Chain::Polygon
+        | Chain::Avalanche
+        | Chain::Oasis
+        | Chain::Aurora
+        | Chain::Fantom
+        | Chain::Karura
+        | Chain::Acala
+        | Chain::Klaytn
+        | Chain::Celo
+        | Chain::Moonbeam
+        | Chain::Neon
+        | Chain::Arbitrum
+        | Chain::Optimism
+        | Chain::Gnosis
+        | Chain::Base
+        | Chain::Rootstock
+        | Chain::Scroll
+        | Chain::Sepolia => emitter_address_from_evm(input),
+        _ => {
+            decode_hex(input).ok_or_else(|| EmitterAddressError::InvalidWormhole(input.to_string()))
+        }
+    }
+}
+
+/// decodes exactly `N` hex encoded bytes, optionally prefixed with `0x`
+fn decode_hex<const N: usize>(input: &str) -> Option<[u8; N]> {
+    hex::decode(input.trim_start_matches("0x"))
+        .ok()?
+        .try_into()
+        .ok()
+}
+
+impl From<[u8; 32]> for WormholeAddress {
+    fn from(value: [u8; 32]) -> Self {
+        Self(value)
+    }
+}
+
+impl From<WormholeAddress> for [u8; 32] {
+    fn from(value: WormholeAddress) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for WormholeAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WORMHOLE_PROGRAM_ID;
+    #[test]
+    fn test_wormhole_address_from_pubkey() {
+        let address = WormholeAddress::from_pubkey(&WORMHOLE_PROGRAM_ID);
+        assert_eq!(address.to_bytes(), WORMHOLE_PROGRAM_ID.to_bytes());
+        assert_eq!(
+            address.to_hex(),
+            hex::encode(pubkey_to_wormhole(&WORMHOLE_PROGRAM_ID))
+        );
+        assert_eq!(address.to_string(), address.to_hex());
+    }
+    #[test]
+    fn test_wormhole_address_from_eth() {
+        let mut eth = [0_u8; 20];
+        eth.copy_from_slice(&hex::decode("3ee18b2214aff97000d974cf647e7c347e8fa585").unwrap());
+        let address = WormholeAddress::from_eth(eth);
+        assert_eq!(
+            address.to_hex(),
+            "0000000000000000000000003ee18b2214aff97000d974cf647e7c347e8fa585"
+        );
+        assert_eq!(format!("{address}"), address.to_hex());
+    }
+    #[test]
+    fn test_emitter_address_from_evm() {
+        let expected = "0000000000000000000000003ee18b2214aff97000d974cf647e7c347e8fa585";
+        for input in [
+            "3ee18b2214aff97000d974cf647e7c347e8fa585",
+            "0x3ee18b2214aff97000d974cf647e7c347e8fa585",
+            "0x3EE18B2214AFF97000D974CF647E7C347E8FA585",
+        ] {
+            assert_eq!(
+                hex::encode(emitter_address_from_evm(input).unwrap()),
+                expected
+            );
+            // ethereum and base are both evm chains
+            assert_eq!(
+                hex::encode(normalize_emitter_address(2, input).unwrap()),
+                expected
+            );
+            assert_eq!(
+                hex::encode(normalize_emitter_address(30, input).unwrap()),
+                expected
+            );
+        }
+        // a 32 byte address is not an evm address
+        assert_eq!(
+            emitter_address_from_evm(expected),
+            Err(EmitterAddressError::InvalidEvm(expected.to_string()))
+        );
+        assert!(emitter_address_from_evm("0xzz").is_err());
+    }
+    #[test]
+    fn test_emitter_address_from_solana() {
+        assert_eq!(
+            emitter_address_from_solana(&WORMHOLE_PROGRAM_ID),
+            WORMHOLE_PROGRAM_ID.to_bytes()
+        );
+        assert_eq!(
+            normalize_emitter_address(1, &WORMHOLE_PROGRAM_ID.to_string()).unwrap(),
+            WORMHOLE_PROGRAM_ID.to_bytes()
+        );
+        // solana addresses are base58, not hex
+        let hex_address = hex::encode(WORMHOLE_PROGRAM_ID.to_bytes());
+        assert_eq!(
+            normalize_emitter_address(1, &hex_address),
+            Err(EmitterAddressError::InvalidSolana(hex_address.clone()))
+        );
+        // other chains use the 32 byte wormhole address
+        assert_eq!(
+            normalize_emitter_address(21, &hex_address).unwrap(),
+            WORMHOLE_PROGRAM_ID.to_bytes()
+        );
+        assert_eq!(
+            normalize_emitter_address(21, &WORMHOLE_PROGRAM_ID.to_string()),
+            Err(EmitterAddressError::InvalidWormhole(
+                WORMHOLE_PROGRAM_ID.to_string()
+            ))
+        );
+    }
+}
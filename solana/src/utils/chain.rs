@@ -250,6 +250,20 @@ impl FromStr for Chain {
     }
 }
 
+impl Chain {
+    /// returns the chain with the wormhole chain id `id`, or `None` if the id is unknown
+    pub fn from_u16(id: u16) -> Option<Self> {
+        match Self::from(id) {
+            Chain::Unknown(_) => None,
+            chain => Some(chain),
+        }
+    }
+    /// returns the wormhole chain id of the chain
+    pub fn as_u16(&self) -> u16 {
+        (*self).into()
+    }
+}
+
 impl Default for Chain {
     fn default() -> Self {
         Self::Any
@@ -285,6 +299,23 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_from_u16() {
+        for (id, chain) in [
+            (0, Chain::Any),
+            (1, Chain::Solana),
+            (2, Chain::Ethereum),
+            (10, Chain::Fantom),
+            (30, Chain::Base),
+            (3104, Chain::Wormchain),
+        ] {
+            assert_eq!(Chain::from_u16(id), Some(chain));
+            assert_eq!(chain.as_u16(), id);
+        }
+        assert_eq!(Chain::from_u16(27), None);
+        assert_eq!(Chain::Unknown(27).as_u16(), 27);
+    }
+
     #[test]
     fn isomorphic_display() {
         for i in 0u16..=u16::MAX {
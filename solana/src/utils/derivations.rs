@@ -1,7 +1,17 @@
+//! pda derivations for the wormhole core, token and nft bridges
+//!
+//! integers are not encoded consistently across seeds, each derivation uses the encoding
+//! expected by the program owning the account: the core bridge guardian set index and the
+//! token/nft bridge chain ids are big endian, while the message nonces, emitter indices and
+//! claim seeds used by this crate are little endian. when adding a derivation, copy the
+//! encoding from the owning program rather than from a neighbouring function
+
 use solana_program::pubkey::Pubkey;
 use wormhole_anchor_sdk::wormhole::SEED_PREFIX_EMITTER;
 
-use crate::WORMHOLE_PROGRAM_ID;
+use crate::{
+    WORMHOLE_NFT_BRIDGE_PROGRAM_ID, WORMHOLE_PROGRAM_ID, WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID,
+};
 
 /// derives the message PDA, with the nonce being the sequence number
 /// of the sequence used when publishing a message.
@@ -21,11 +31,47 @@ pub fn derive_sequence(emitter_pda: Pubkey) -> (Pubkey, u8) {
     )
 }
 
+/// derives the core emitter sequence account of the emitter owned by executing_program_id,
+/// equivalent to `derive_sequence(derive_emitter(executing_program_id).0)`
+pub fn derive_sequence_for_program(executing_program_id: Pubkey) -> (Pubkey, u8) {
+    derive_sequence(derive_emitter(executing_program_id).0)
+}
+
 /// derive the emitter pda, where executing_program_id is the program
 /// that will be using the emitter to sign cpi instructions
 pub fn derive_emitter(executing_program_id: Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[SEED_PREFIX_EMITTER], &executing_program_id)
 }
+/// derives one of several emitter pdas owned by executing_program_id, allowing a program to
+/// publish through multiple emitters, each of which has its own independent wormhole sequence
+///
+/// seed: [b"emitter", emitter_index (little-endian)]
+pub fn derive_indexed_emitter(executing_program_id: Pubkey, emitter_index: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SEED_PREFIX_EMITTER, &emitter_index.to_le_bytes()],
+        &executing_program_id,
+    )
+}
+
+/// derives the message PDA used when publishing through an indexed emitter, the emitter index
+/// is included in the seeds so that emitters sharing a nonce don't collide on the same account
+///
+/// seed: [b"message", emitter_index (little-endian), nonce (little-endian)]
+pub fn derive_indexed_message_pda(
+    program_id: Pubkey,
+    emitter_index: u16,
+    nonce: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"message",
+            &emitter_index.to_le_bytes(),
+            &nonce.to_le_bytes(),
+        ],
+        &program_id,
+    )
+}
+
 /// derives the address of the core bridge config program
 pub fn derive_core_bridge_config() -> (Pubkey, u8) {
     Pubkey::find_program_address(&[b"Bridge"], &WORMHOLE_PROGRAM_ID)
@@ -37,6 +83,8 @@ pub fn derive_core_fee_collector() -> (Pubkey, u8) {
 }
 
 /// derives the guardian set pda
+///
+/// seed: [b"GuardianSet", guardian_set_index (big-endian)], program: core bridge
 pub fn derive_guardian_set(guardian_set_index: u32) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[b"GuardianSet", &guardian_set_index.to_be_bytes()[..]],
@@ -49,12 +97,227 @@ pub fn derive_posted_vaa(payload_hash: &[u8]) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[b"PostedVAA", &payload_hash], &WORMHOLE_PROGRAM_ID)
 }
 
+/// derives the claim account marking the vaa emitted by `emitter_chain`/`emitter_address`
+/// with `sequence` as consumed by program_id
+///
+/// seed: [b"claim", emitter_chain (little-endian), emitter_address, sequence (little-endian)]
+pub fn derive_claim(
+    program_id: Pubkey,
+    emitter_chain: u16,
+    emitter_address: &[u8; 32],
+    sequence: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"claim",
+            &emitter_chain.to_le_bytes(),
+            emitter_address,
+            &sequence.to_le_bytes(),
+        ],
+        &program_id,
+    )
+}
+
+/// derives the token bridge config account
+///
+/// seed: [b"config"], program: token bridge
+pub fn derive_token_bridge_config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID)
+}
+
+/// derives the emitter used by the token bridge to publish transfer messages
+///
+/// seed: [b"emitter"], program: token bridge
+pub fn derive_token_bridge_emitter() -> (Pubkey, u8) {
+    derive_emitter(WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID)
+}
+
+/// derives the core bridge sequence account of the token bridge emitter
+///
+/// seed: [b"Sequence", token_bridge_emitter], program: core bridge
+pub fn derive_token_bridge_sequence() -> (Pubkey, u8) {
+    derive_sequence(derive_token_bridge_emitter().0)
+}
+
+/// derives the nft bridge config account
+///
+/// seed: [b"config"], program: nft bridge
+pub fn derive_nft_bridge_config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &WORMHOLE_NFT_BRIDGE_PROGRAM_ID)
+}
+
+/// derives the emitter used by the nft bridge to publish transfer messages
+///
+/// seed: [b"emitter"], program: nft bridge
+pub fn derive_nft_bridge_emitter() -> (Pubkey, u8) {
+    derive_emitter(WORMHOLE_NFT_BRIDGE_PROGRAM_ID)
+}
+
+/// derives the core bridge sequence account of the nft bridge emitter
+///
+/// seed: [b"Sequence", nft_bridge_emitter], program: core bridge
+pub fn derive_nft_bridge_sequence() -> (Pubkey, u8) {
+    derive_sequence(derive_nft_bridge_emitter().0)
+}
+
+/// derives the mint created by the nft bridge for a wrapped foreign nft, the nft bridge
+/// creates one mint per token id
+///
+/// seed: [b"wrapped", token_chain (big-endian), token_address, token_id], program: nft bridge
+pub fn derive_nft_wrapped_mint(
+    token_chain: u16,
+    token_address: [u8; 32],
+    token_id: [u8; 32],
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"wrapped",
+            &token_chain.to_be_bytes(),
+            &token_address,
+            &token_id,
+        ],
+        &WORMHOLE_NFT_BRIDGE_PROGRAM_ID,
+    )
+}
+
+/// derives the account storing the origin (chain, address and token id) of a wrapped nft,
+/// which is keyed off the wrapped mint (see `derive_nft_wrapped_mint`)
+///
+/// seed: [b"meta", wrapped_mint], program: nft bridge
+pub fn derive_nft_wrapped_meta(
+    token_chain: u16,
+    token_address: [u8; 32],
+    token_id: [u8; 32],
+) -> (Pubkey, u8) {
+    let (wrapped_mint, _) = derive_nft_wrapped_mint(token_chain, token_address, token_id);
+    Pubkey::find_program_address(
+        &[b"meta", wrapped_mint.as_ref()],
+        &WORMHOLE_NFT_BRIDGE_PROGRAM_ID,
+    )
+}
+
+/// every wormhole related address used by a program publishing through its emitter,
+/// displayed as labelled base58 addresses for pasting into explorers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProgramDerivations {
+    pub program_id: Pubkey,
+    /// seed: [b"emitter"], program: program_id
+    pub emitter: Pubkey,
+    /// seed: [b"Sequence", emitter], program: core bridge
+    pub sequence: Pubkey,
+    /// seed: [b"Bridge"], program: core bridge
+    pub core_bridge_config: Pubkey,
+    /// seed: [b"fee_collector"], program: core bridge
+    pub core_fee_collector: Pubkey,
+}
+
+impl std::fmt::Display for ProgramDerivations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "program id:         {}", self.program_id)?;
+        writeln!(f, "emitter:            {}", self.emitter)?;
+        writeln!(f, "sequence:           {}", self.sequence)?;
+        writeln!(f, "core bridge config: {}", self.core_bridge_config)?;
+        write!(f, "core fee collector: {}", self.core_fee_collector)
+    }
+}
+
+/// derives every wormhole related address for program_id, see `ProgramDerivations`
+pub fn derive_all_for_program(program_id: Pubkey) -> ProgramDerivations {
+    let (emitter, _) = derive_emitter(program_id);
+    ProgramDerivations {
+        program_id,
+        emitter,
+        sequence: derive_sequence(emitter).0,
+        core_bridge_config: derive_core_bridge_config().0,
+        core_fee_collector: derive_core_fee_collector().0,
+    }
+}
+
+/// the pdas used by `send_message` to publish the message with a given nonce, see
+/// `derive_send_message_pdas`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SendMessagePdas {
+    /// seed: [b"emitter"], program: executing program
+    pub emitter: Pubkey,
+    pub emitter_bump: u8,
+    /// seed: [b"Sequence", emitter], program: core bridge
+    pub sequence: Pubkey,
+    pub sequence_bump: u8,
+    /// seed: [b"message", nonce (little-endian)], program: executing program
+    pub message: Pubkey,
+    pub message_bump: u8,
+}
+
+/// derives the emitter, sequence and message pdas needed to send the message with `nonce`
+/// from executing_program_id
+pub fn derive_send_message_pdas(executing_program_id: Pubkey, nonce: u64) -> SendMessagePdas {
+    let (emitter, emitter_bump) = derive_emitter(executing_program_id);
+    let (sequence, sequence_bump) = derive_sequence(emitter);
+    let (message, message_bump) = derive_message_pda(executing_program_id, nonce);
+    SendMessagePdas {
+        emitter,
+        emitter_bump,
+        sequence,
+        sequence_bump,
+        message,
+        message_bump,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use solana_program::system_program;
 
     use super::*;
     #[test]
+    fn test_derive_guardian_set() {
+        // mainnet guardian set accounts, the index is big endian so 3 and 4 would derive
+        // different accounts if it were little endian
+        for (index, expected) in [
+            (0, "DS7qfSAgYsonPpKoAjcGhX9VFjXdGkiHjEDkTidf8H2P"),
+            (3, "6d3w8mGjJauf6gCAg7WfLezbaPmUHYGuoNutnfYF1RYM"),
+            (4, "AFEXK4A1BU7BZfi8niAmker98LH9EARB544wKGPXwMyy"),
+        ] {
+            assert_eq!(
+                derive_guardian_set(index).0.to_string(),
+                expected,
+                "guardian set {index}"
+            );
+        }
+        assert_ne!(
+            derive_guardian_set(3).0,
+            Pubkey::find_program_address(
+                &[b"GuardianSet", &3_u32.to_le_bytes()[..]],
+                &WORMHOLE_PROGRAM_ID
+            )
+            .0
+        );
+    }
+    #[test]
+    fn test_derive_claim() {
+        let program_id = WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let (pda, nonce) = derive_claim(program_id, 2, &[7_u8; 32], 69);
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[
+                    b"claim",
+                    &2_u16.to_le_bytes()[..],
+                    &[7_u8; 32],
+                    &69_u64.to_le_bytes()[..],
+                    &[nonce]
+                ],
+                &program_id
+            )
+            .unwrap(),
+            pda
+        );
+        // every component of the vaa id is part of the seeds
+        assert_ne!(derive_claim(program_id, 1, &[7_u8; 32], 69).0, pda);
+        assert_ne!(derive_claim(program_id, 2, &[8_u8; 32], 69).0, pda);
+        assert_ne!(derive_claim(program_id, 2, &[7_u8; 32], 70).0, pda);
+        assert_ne!(derive_claim(WORMHOLE_PROGRAM_ID, 2, &[7_u8; 32], 69).0, pda);
+    }
+    #[test]
     fn test_derive_emitter() {
         let (pda, nonce) = derive_emitter(system_program::id());
         assert_eq!(
@@ -79,6 +342,53 @@ mod test {
         assert_eq!(nonce, 254);
     }
     #[test]
+    fn test_derive_sequence_for_program() {
+        for program_id in [
+            system_program::id(),
+            WORMHOLE_PROGRAM_ID,
+            WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID,
+            Pubkey::new_unique(),
+        ] {
+            let (emitter_pda, _) = derive_emitter(program_id);
+            assert_eq!(
+                derive_sequence_for_program(program_id),
+                derive_sequence(emitter_pda)
+            );
+        }
+    }
+    #[test]
+    fn test_derive_all_for_program() {
+        let derivations = derive_all_for_program(system_program::id());
+        assert_eq!(derivations.emitter, derive_emitter(system_program::id()).0);
+        assert_eq!(
+            derivations.sequence,
+            derive_sequence_for_program(system_program::id()).0
+        );
+        let display = derivations.to_string();
+        assert_eq!(display.lines().count(), 5);
+        assert!(
+            display.contains("emitter:            6TsAgEkaXfrUMW3hcLgiZXUehUhcaRkaRY3fjhrfadye")
+        );
+    }
+    #[test]
+    fn test_derive_send_message_pdas() {
+        for program_id in [system_program::id(), WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID] {
+            let pdas = derive_send_message_pdas(program_id, 69);
+            assert_eq!(
+                (pdas.emitter, pdas.emitter_bump),
+                derive_emitter(program_id)
+            );
+            assert_eq!(
+                (pdas.sequence, pdas.sequence_bump),
+                derive_sequence_for_program(program_id)
+            );
+            assert_eq!(
+                (pdas.message, pdas.message_bump),
+                derive_message_pda(program_id, 69)
+            );
+        }
+    }
+    #[test]
     fn test_derive_message_pda() {
         let (pda, nonce) = derive_message_pda(system_program::id(), 69);
         assert_eq!(
@@ -87,4 +397,174 @@ mod test {
         );
         assert_eq!(nonce, 254);
     }
+    #[test]
+    fn test_derive_token_bridge() {
+        let (pda, _) = derive_token_bridge_config();
+        assert_eq!(
+            pda.to_string(),
+            "DapiQYH3BGonhN8cngWcXQ6SrqSm3cwysoznoHr6Sbsx"
+        );
+        let (pda, _) = derive_token_bridge_emitter();
+        assert_eq!(
+            pda.to_string(),
+            "Gv1KWf8DT1jKv5pKBmGaTmVszqa56Xn8YGx2Pg7i7qAk"
+        );
+        let (pda, _) = derive_token_bridge_sequence();
+        assert_eq!(
+            pda.to_string(),
+            "GF2ghkjwsR9CHkGk1RvuZrApPZGBZynxMm817VNi51Nf"
+        );
+    }
+    #[test]
+    fn test_derive_nft_bridge() {
+        let (pda, _) = derive_nft_bridge_config();
+        assert_eq!(
+            pda.to_string(),
+            "Bkk7m34cJGTpgEQQFhQsMXdjMNr3eCe9w87kWni3B9SR"
+        );
+        // mainnet nft bridge emitter
+        let (pda, _) = derive_nft_bridge_emitter();
+        assert_eq!(
+            pda.to_string(),
+            "wPkMzrFNdXFtATPFYDMh9EMJNZyAd4un7TCezG7AgY2"
+        );
+        let (pda, _) = derive_nft_bridge_sequence();
+        assert_eq!(
+            pda.to_string(),
+            "5XmLqL7RBW4zFrVcwJpxTktyaDZ4H3D6qTVrGb2KpS9b"
+        );
+        let (pda, _) = derive_nft_wrapped_mint(2, [7_u8; 32], [1_u8; 32]);
+        assert_eq!(
+            pda.to_string(),
+            "CWsJg4id8ZmUva2261Q1uxv9pMaP7QaFEVPempaUWtp7"
+        );
+        let (pda, _) = derive_nft_wrapped_meta(2, [7_u8; 32], [1_u8; 32]);
+        assert_eq!(
+            pda.to_string(),
+            "ECrQRmwjjvaxznVWAxVXv3bSLz28cp5tq3UPgsQur4ox"
+        );
+    }
+}
+
+/// pins the byte order of the integer seeds of each derivation against known addresses, so
+/// that swapping a `to_le_bytes` for a `to_be_bytes` (or the reverse) fails the tests
+#[cfg(test)]
+mod endianness_guards {
+    use solana_program::system_program;
+
+    use super::*;
+    use crate::{instructions::post_vaa::serialize_vaa, state::signed_vaa::SignedVaa};
+    #[test]
+    fn test_message_nonce_is_little_endian() {
+        let program_id = system_program::id();
+        let (pda, _) = derive_message_pda(program_id, 69);
+        assert_eq!(
+            pda.to_string(),
+            "7ivBfWmf54DHwNp437fZtfXDd5TtXfWo5Q4YGnk7xrRB"
+        );
+        assert_eq!(
+            pda,
+            Pubkey::find_program_address(&[b"message", &69_u64.to_le_bytes()], &program_id).0
+        );
+        assert_ne!(
+            pda,
+            Pubkey::find_program_address(&[b"message", &69_u64.to_be_bytes()], &program_id).0
+        );
+
+        let (pda, _) = derive_indexed_message_pda(program_id, 3, 69);
+        assert_eq!(
+            pda.to_string(),
+            "EcinxbFdd8V3e7NgyZ8H2QsWHVo9A82rMxMYEEWGs8qw"
+        );
+        assert_ne!(
+            pda,
+            Pubkey::find_program_address(
+                &[b"message", &3_u16.to_be_bytes(), &69_u64.to_be_bytes()],
+                &program_id
+            )
+            .0
+        );
+    }
+    #[test]
+    fn test_sequence_has_no_integer_seeds() {
+        // the sequence account is keyed by the emitter alone, the sequence number is stored
+        // little endian in its data
+        let (pda, _) = derive_sequence_for_program(system_program::id());
+        assert_eq!(
+            pda.to_string(),
+            "3PqEpt2V26bEkCjcef9crtzLtkHYBMQMCBedneayXXPd"
+        );
+        let (pda, _) = derive_sequence_for_program(WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID);
+        assert_eq!(
+            pda.to_string(),
+            "GF2ghkjwsR9CHkGk1RvuZrApPZGBZynxMm817VNi51Nf"
+        );
+    }
+    #[test]
+    fn test_guardian_set_index_is_big_endian() {
+        let (pda, _) = derive_guardian_set(4);
+        assert_eq!(
+            pda.to_string(),
+            "AFEXK4A1BU7BZfi8niAmker98LH9EARB544wKGPXwMyy"
+        );
+        assert_eq!(
+            pda,
+            Pubkey::find_program_address(
+                &[b"GuardianSet", &4_u32.to_be_bytes()],
+                &WORMHOLE_PROGRAM_ID
+            )
+            .0
+        );
+        assert_ne!(
+            pda,
+            Pubkey::find_program_address(
+                &[b"GuardianSet", &4_u32.to_le_bytes()],
+                &WORMHOLE_PROGRAM_ID
+            )
+            .0
+        );
+    }
+    #[test]
+    fn test_posted_vaa_body_is_big_endian() {
+        let vaa =
+            SignedVaa::parse(&hex::decode(crate::testing::TOKEN_TRANSFER_VAA).unwrap()).unwrap();
+        let post_vaa = crate::instructions::post_vaa::PostVAADataIx::from(&vaa);
+        let body = serialize_vaa(&post_vaa);
+        assert_eq!(body[..4], vaa.body.timestamp.to_be_bytes());
+        assert_eq!(body[8..10], vaa.body.emitter_chain.to_be_bytes());
+        assert_eq!(body[42..50], vaa.body.sequence.to_be_bytes());
+        let (pda, _) = derive_posted_vaa(&vaa.body.digest());
+        assert_eq!(
+            pda.to_string(),
+            "8Gy5BWThgDBjsbcxu9fMj7RC5T3ngGYvjuFEVbx8XL2s"
+        );
+
+        let mut little_endian_body = body.clone();
+        little_endian_body[42..50].copy_from_slice(&vaa.body.sequence.to_le_bytes());
+        assert_ne!(
+            pda,
+            derive_posted_vaa(&solana_program::keccak::hash(&little_endian_body).0).0
+        );
+    }
+    #[test]
+    fn test_claim_seeds_are_little_endian() {
+        let (pda, _) = derive_claim(system_program::id(), 2, &[7_u8; 32], 69);
+        assert_eq!(
+            pda.to_string(),
+            "5epgqjGkW7KVyjtHe5TeoF6UPU131cNTcFkas1TzNMWW"
+        );
+        assert_ne!(
+            pda,
+            Pubkey::find_program_address(
+                &[
+                    b"claim",
+                    &2_u16.to_be_bytes(),
+                    &[7_u8; 32],
+                    &69_u64.to_be_bytes()
+                ],
+                &system_program::id()
+            )
+            .0
+        );
+    }
 }
@@ -0,0 +1,264 @@
+use solana_program::secp256k1_recover::{secp256k1_recover, Secp256k1RecoverError};
+use thiserror::Error;
+use wormhole_core_bridge_solana::state::GuardianSet;
+
+use crate::state::signed_vaa::ParsedVaa;
+
+/// errors returned when verifying a vaa's signatures off-chain
+#[derive(Debug, Error, PartialEq)]
+pub enum OfflineVerifyError {
+    #[error("vaa was signed by guardian set {actual} but guardian set {expected} was expected")]
+    GuardianSetMismatch { expected: u32, actual: u32 },
+    #[error("failed to recover the signer of signature {index}")]
+    InvalidSignature { index: usize },
+    #[error("signature {index} was not signed by a guardian in the expected set")]
+    UnknownSigner { index: usize },
+    #[error("signature {index} duplicates a previous guardian's signature")]
+    DuplicateSigner { index: usize },
+    #[error("vaa has {signatures} valid signatures but {required} are required for quorum")]
+    NoQuorum { signatures: usize, required: usize },
+}
+
+/// the guardians added and removed between two guardian sets, used to monitor guardian set upgrades
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GuardianSetDiff {
+    /// guardians present in the new set but not the old set
+    pub added: Vec<[u8; 20]>,
+    /// guardians present in the old set but not the new set
+    pub removed: Vec<[u8; 20]>,
+}
+
+impl GuardianSetDiff {
+    /// computes the guardians added and removed when moving from `old` to `new`
+    pub fn between(old: &[[u8; 20]], new: &[[u8; 20]]) -> Self {
+        Self {
+            added: new
+                .iter()
+                .filter(|key| !old.contains(key))
+                .copied()
+                .collect(),
+            removed: old
+                .iter()
+                .filter(|key| !new.contains(key))
+                .copied()
+                .collect(),
+        }
+    }
+    /// returns true if both sets contain the same guardians
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl std::fmt::Display for GuardianSetDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} guardians added, {} guardians removed",
+            self.added.len(),
+            self.removed.len()
+        )?;
+        for key in &self.added {
+            write!(f, "\n+ 0x{}", hex::encode(key))?;
+        }
+        for key in &self.removed {
+            write!(f, "\n- 0x{}", hex::encode(key))?;
+        }
+        Ok(())
+    }
+}
+
+/// returns the number of guardian signatures needed to reach quorum, which is
+/// more than two thirds of the guardian set
+pub fn quorum(guardian_count: usize) -> usize {
+    (guardian_count * 2) / 3 + 1
+}
+
+/// recovers the eth address of the guardian which signed `digest`, where `digest` is the
+/// keccak256 hash of the vaa body (see `VaaBody::digest`)
+///
+/// guardians sign the keccak256 hash of the digest, so it is hashed once more before recovery
+pub fn recover_eth_address(
+    digest: [u8; 32],
+    sig64: [u8; 64],
+    recovery_id: u8,
+) -> Result<[u8; 20], Secp256k1RecoverError> {
+    let hash = solana_program::keccak::hash(&digest);
+    let pubkey = secp256k1_recover(&hash.0, recovery_id, &sig64)?;
+    let pubkey_hash = solana_program::keccak::hash(&pubkey.to_bytes());
+    let mut eth_address = [0_u8; 20];
+    eth_address.copy_from_slice(&pubkey_hash.0[12..]);
+    Ok(eth_address)
+}
+
+/// returns whether the signature of `digest` recovers to `expected_eth`, see `recover_eth_address`
+pub fn verify_single_signature(
+    digest: [u8; 32],
+    sig64: [u8; 64],
+    recovery_id: u8,
+    expected_eth: [u8; 20],
+) -> Result<bool, Secp256k1RecoverError> {
+    Ok(recover_eth_address(digest, sig64, recovery_id)? == expected_eth)
+}
+
+/// returns the indices of the guardians in a set of `guardian_count` guardians which have
+/// not signed `vaa`, in ascending order
+pub fn missing_guardian_indices(vaa: &ParsedVaa, guardian_count: usize) -> Vec<usize> {
+    let mut signed = vec![false; guardian_count];
+    for signature in &vaa.header.signatures {
+        if let Some(signed) = signed.get_mut(signature.guardian_set_index as usize) {
+            *signed = true;
+        }
+    }
+    signed
+        .iter()
+        .enumerate()
+        .filter(|(_, signed)| !**signed)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// verifies that `vaa` was signed by a quorum of `expected_set`, recovering each signature and
+/// confirming it belongs to the guardian at the signature's index, without any duplicates
+pub fn verify_vaa_offchain(
+    vaa: &ParsedVaa,
+    expected_set: &GuardianSet,
+) -> Result<(), OfflineVerifyError> {
+    if vaa.header.guardian_set_index != expected_set.index {
+        return Err(OfflineVerifyError::GuardianSetMismatch {
+            expected: expected_set.index,
+            actual: vaa.header.guardian_set_index,
+        });
+    }
+    let digest = vaa.body.digest();
+    let mut signed = vec![false; expected_set.keys.len()];
+    for (index, signature) in vaa.header.signatures.iter().enumerate() {
+        let eth_address = recover_eth_address(digest, signature.raw_sig(), signature.recovery_id())
+            .map_err(|_| OfflineVerifyError::InvalidSignature { index })?;
+        let guardian_index = signature.guardian_set_index as usize;
+        if expected_set.keys.get(guardian_index) != Some(&eth_address) {
+            return Err(OfflineVerifyError::UnknownSigner { index });
+        }
+        if std::mem::replace(&mut signed[guardian_index], true) {
+            return Err(OfflineVerifyError::DuplicateSigner { index });
+        }
+    }
+    let required = quorum(expected_set.keys.len());
+    if vaa.header.signatures.len() < required {
+        return Err(OfflineVerifyError::NoQuorum {
+            signatures: vaa.header.signatures.len(),
+            required,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{
+        mock_guardian_set, mock_guardian_sign, mock_guardian_signed_vaa, mock_guardians,
+        mock_quorum_vaa,
+    };
+    #[test]
+    fn test_quorum() {
+        assert_eq!(quorum(19), 13);
+        assert_eq!(quorum(1), 1);
+        assert_eq!(quorum(3), 3);
+    }
+    #[test]
+    fn test_verify_single_signature() {
+        let guardians = mock_guardians(2);
+        let digest = crate::testing::test_vaa_digest(69);
+        let signature = mock_guardian_sign(&guardians[0], digest);
+        let mut sig64 = [0_u8; 64];
+        sig64.copy_from_slice(&signature[..64]);
+        let recovery_id = signature[64];
+        assert_eq!(
+            verify_single_signature(digest, sig64, recovery_id, guardians[0].eth_address),
+            Ok(true)
+        );
+        assert_eq!(
+            verify_single_signature(digest, sig64, recovery_id, guardians[1].eth_address),
+            Ok(false)
+        );
+        // a signature over another digest recovers to some other address
+        assert_eq!(
+            verify_single_signature(
+                crate::testing::test_vaa_digest(70),
+                sig64,
+                recovery_id,
+                guardians[0].eth_address
+            ),
+            Ok(false)
+        );
+        assert!(verify_single_signature(digest, sig64, 4, guardians[0].eth_address).is_err());
+    }
+    #[test]
+    fn test_missing_guardian_indices() {
+        let guardians = mock_guardians(19);
+        let signers = (0..19)
+            .filter(|index| ![2, 9, 17].contains(index))
+            .collect::<Vec<_>>();
+        let vaa = mock_guardian_signed_vaa(3, &guardians, &signers);
+        assert_eq!(missing_guardian_indices(&vaa, 19), vec![2, 9, 17]);
+        assert!(missing_guardian_indices(&vaa, 2).is_empty());
+    }
+    #[test]
+    fn test_guardian_set_diff() {
+        let old = [[1_u8; 20], [2_u8; 20], [3_u8; 20]];
+        let new = [[1_u8; 20], [3_u8; 20], [4_u8; 20], [5_u8; 20]];
+        let diff = GuardianSetDiff::between(&old, &new);
+        assert_eq!(diff.added, vec![[4_u8; 20], [5_u8; 20]]);
+        assert_eq!(diff.removed, vec![[2_u8; 20]]);
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.to_string(),
+            format!(
+                "2 guardians added, 1 guardians removed\n+ 0x{}\n+ 0x{}\n- 0x{}",
+                hex::encode([4_u8; 20]),
+                hex::encode([5_u8; 20]),
+                hex::encode([2_u8; 20])
+            )
+        );
+        assert!(GuardianSetDiff::between(&old, &old).is_empty());
+    }
+    #[test]
+    fn test_verify_vaa_offchain() {
+        let signers = (0..13).collect::<Vec<_>>();
+        let (guardians, guardian_set, vaa) = mock_quorum_vaa(3, 19, &signers);
+        assert_eq!(verify_vaa_offchain(&vaa, &guardian_set), Ok(()));
+
+        let below_quorum = mock_guardian_signed_vaa(3, &guardians, &signers[..12]);
+        assert_eq!(
+            verify_vaa_offchain(&below_quorum, &guardian_set),
+            Err(OfflineVerifyError::NoQuorum {
+                signatures: 12,
+                required: 13
+            })
+        );
+
+        let mut duplicated = vaa.clone();
+        duplicated.header.signatures[12] = duplicated.header.signatures[0];
+        assert_eq!(
+            verify_vaa_offchain(&duplicated, &guardian_set),
+            Err(OfflineVerifyError::DuplicateSigner { index: 12 })
+        );
+
+        let mut wrong_guardian = vaa.clone();
+        wrong_guardian.header.signatures[0].guardian_set_index = 18;
+        assert_eq!(
+            verify_vaa_offchain(&wrong_guardian, &guardian_set),
+            Err(OfflineVerifyError::UnknownSigner { index: 0 })
+        );
+
+        let other_set = mock_guardian_set(4, guardian_set.keys.clone(), 0);
+        assert_eq!(
+            verify_vaa_offchain(&vaa, &other_set),
+            Err(OfflineVerifyError::GuardianSetMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+    }
+}
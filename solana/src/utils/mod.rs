@@ -1,4 +1,8 @@
+/// conversions between solana/foreign addresses and the 32 byte wormhole address format
+pub mod address;
 /// chain identifiers from https://github.com/wormhole-foundation/wormhole/blob/main/sdk/rust/core/src/chain.rs#L9
 pub mod chain;
 /// utilities for deriving pda's
 pub mod derivations;
+/// helpers for verifying guardian signatures and quorum
+pub mod guardians;
@@ -0,0 +1,122 @@
+//! thin wrappers exposing the pda derivations and `Payload` encoding to javascript, so dapps
+//! compute the same addresses and messages as the program
+//!
+//! public keys are passed as base58 strings and nonces as `bigint`
+
+use std::str::FromStr;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+use wasm_bindgen::prelude::*;
+
+use crate::{message_payload::Payload, utils::derivations};
+
+/// a program derived address and its bump seed
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pda {
+    address: Pubkey,
+    bump: u8,
+}
+
+#[wasm_bindgen]
+impl Pda {
+    /// the base58 encoded address
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> String {
+        self.address.to_string()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn bump(&self) -> u8 {
+        self.bump
+    }
+}
+
+impl From<(Pubkey, u8)> for Pda {
+    fn from((address, bump): (Pubkey, u8)) -> Self {
+        Self { address, bump }
+    }
+}
+
+/// a decoded `Payload`
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedPayload {
+    payload_id: u8,
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl DecodedPayload {
+    #[wasm_bindgen(getter, js_name = payloadId)]
+    pub fn payload_id(&self) -> u8 {
+        self.payload_id
+    }
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+fn parse_pubkey(key: &str) -> Result<Pubkey, JsError> {
+    Pubkey::from_str(key).map_err(|err| JsError::new(&format!("invalid public key {key}: {err}")))
+}
+
+/// derives the emitter pda of `executing_program_id`, see `derive_emitter`
+#[wasm_bindgen(js_name = deriveEmitter)]
+pub fn derive_emitter(executing_program_id: &str) -> Result<Pda, JsError> {
+    Ok(derivations::derive_emitter(parse_pubkey(executing_program_id)?).into())
+}
+
+/// derives the message pda of `program_id` for `nonce`, see `derive_message_pda`
+#[wasm_bindgen(js_name = deriveMessagePda)]
+pub fn derive_message_pda(program_id: &str, nonce: u64) -> Result<Pda, JsError> {
+    Ok(derivations::derive_message_pda(parse_pubkey(program_id)?, nonce).into())
+}
+
+/// serializes a `Payload`, failing if `data` exceeds `MAX_PAYLOAD_LEN` bytes
+#[wasm_bindgen(js_name = serializePayload)]
+pub fn serialize_payload(payload_id: u8, data: Vec<u8>) -> Result<Vec<u8>, JsError> {
+    Ok(Payload::new(payload_id, data)?.try_to_vec()?)
+}
+
+/// deserializes a `Payload`, ignoring any trailing bytes
+#[wasm_bindgen(js_name = deserializePayload)]
+pub fn deserialize_payload(bytes: &[u8]) -> Result<DecodedPayload, JsError> {
+    let payload = Payload::deserialize(&mut &bytes[..])?;
+    Ok(DecodedPayload {
+        payload_id: payload.payload_id,
+        data: payload.data,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    // JsError can only be constructed on wasm targets, so only the success paths run here
+    #[test]
+    fn test_wasm_wrappers() {
+        let program_id = crate::WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID;
+        let emitter = derive_emitter(&program_id.to_string()).unwrap();
+        assert_eq!((emitter.address(), emitter.bump()), {
+            let (address, bump) = derivations::derive_emitter(program_id);
+            (address.to_string(), bump)
+        });
+        assert_eq!(
+            derive_message_pda(&program_id.to_string(), 69).unwrap(),
+            derivations::derive_message_pda(program_id, 69).into()
+        );
+
+        let bytes = serialize_payload(1, b"hello".to_vec()).unwrap();
+        assert_eq!(
+            bytes,
+            Payload::new(1, b"hello".to_vec())
+                .unwrap()
+                .try_to_vec()
+                .unwrap()
+        );
+        let payload = deserialize_payload(&bytes).unwrap();
+        assert_eq!(payload.payload_id(), 1);
+        assert_eq!(payload.data(), b"hello");
+    }
+}
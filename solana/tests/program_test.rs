@@ -0,0 +1,413 @@
+//! integration tests running the on-chain instructions through a wrapper program on the
+//! solana-program-test runtime, with a mock core bridge standing in for wormhole
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
+    program::invoke, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, rent::Rent,
+    system_instruction, system_program,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use wormhole_solana_lite::{
+    error::WormholeLiteError,
+    instructions::{
+        claim_vaa::{claim_vaa, TransactionAccountKeys as ClaimVaaKeys},
+        create_emitter::{initialize_emitter, TransactionAccountKeys as InitializeEmitterKeys},
+        send_message::{send_message, TransactionAccountKeys},
+    },
+    message_payload::Payload,
+    state::{claim::Claim, emitter::Emitter},
+    utils::derivations::{derive_core_bridge_config, derive_core_fee_collector, derive_emitter},
+    WORMHOLE_PROGRAM_ID,
+};
+
+/// program id of the wrapper program
+const WRAPPER_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("Wrapper111111111111111111111111111111111111");
+
+/// fee the wrapper pays to the fee collector for every message
+const MESSAGE_FEE: u64 = 1_000;
+
+/// instructions of the wrapper program
+#[derive(BorshSerialize, BorshDeserialize)]
+enum WrapperInstruction {
+    InitializeEmitter,
+    SendMessage {
+        batch_id: u32,
+        payload_id: u8,
+        data: Vec<u8>,
+    },
+    ClaimVaa {
+        emitter_chain: u16,
+        emitter_address: [u8; 32],
+        sequence: u64,
+    },
+}
+
+fn wrapper_processor(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match WrapperInstruction::try_from_slice(data)? {
+        WrapperInstruction::InitializeEmitter => initialize_emitter(*program_id, accounts),
+        WrapperInstruction::SendMessage {
+            batch_id,
+            payload_id,
+            data,
+        } => send_message(
+            *program_id,
+            accounts,
+            batch_id,
+            Payload::new(payload_id, data).map_err(|_| ProgramError::InvalidInstructionData)?,
+            MESSAGE_FEE,
+        ),
+        WrapperInstruction::ClaimVaa {
+            emitter_chain,
+            emitter_address,
+            sequence,
+        } => claim_vaa(
+            *program_id,
+            accounts,
+            emitter_chain,
+            emitter_address,
+            sequence,
+        ),
+    }
+}
+
+/// stands in for the core bridge post_message instruction, creating the message account
+/// and storing the raw payload in it
+fn mock_core_bridge_processor(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let wormhole_anchor_sdk::wormhole::Instruction::PostMessage { payload, .. } =
+        wormhole_anchor_sdk::wormhole::Instruction::try_from_slice(data)?
+    else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let message = &accounts[1];
+    let emitter = &accounts[2];
+    let payer = &accounts[4];
+    if !message.is_signer || !emitter.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    invoke(
+        &system_instruction::create_account(
+            payer.key,
+            message.key,
+            Rent::default().minimum_balance(payload.len()),
+            payload.len() as u64,
+            program_id,
+        ),
+        &[payer.clone(), message.clone()],
+    )?;
+    message.data.borrow_mut().copy_from_slice(&payload);
+    Ok(())
+}
+
+fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "wormhole_lite_wrapper",
+        WRAPPER_PROGRAM_ID,
+        processor!(wrapper_processor),
+    );
+    program_test.add_program(
+        "mock_core_bridge",
+        WORMHOLE_PROGRAM_ID,
+        processor!(mock_core_bridge_processor),
+    );
+    // only the owner of the config is checked
+    program_test.add_account(
+        derive_core_bridge_config().0,
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0_u8; 80],
+            owner: WORMHOLE_PROGRAM_ID,
+            ..Account::default()
+        },
+    );
+    program_test.add_account(
+        derive_core_fee_collector().0,
+        Account {
+            lamports: 1_000_000_000,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    program_test
+}
+
+fn initialize_emitter_ix(payer: Pubkey) -> Instruction {
+    Instruction {
+        program_id: WRAPPER_PROGRAM_ID,
+        accounts: InitializeEmitterKeys {
+            payer,
+            emitter: derive_emitter(WRAPPER_PROGRAM_ID).0,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(),
+        data: WrapperInstruction::InitializeEmitter.try_to_vec().unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn test_initialize_emitter() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[initialize_emitter_ix(payer.pubkey())],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    let (emitter_pda, emitter_nonce) = derive_emitter(WRAPPER_PROGRAM_ID);
+    let account = banks_client
+        .get_account(emitter_pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, WRAPPER_PROGRAM_ID);
+    assert_eq!(account.data.len(), Emitter::LEN);
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+    let emitter = Emitter::unpack(&account.data).unwrap();
+    assert_eq!(emitter.owner, WRAPPER_PROGRAM_ID);
+    assert_eq!(emitter.nonce, emitter_nonce);
+    assert_eq!(emitter.next_publishable_nonce, 0);
+    assert!(emitter.validate_padding());
+
+    // the emitter can only be initialized once
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            // differentiates the transaction from the first one
+            system_instruction::transfer(&payer.pubkey(), &payer.pubkey(), 1),
+            initialize_emitter_ix(payer.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    assert!(banks_client.process_transaction(tx).await.is_err());
+}
+
+fn send_message_ix(payer: Pubkey, nonce: u64, data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: WRAPPER_PROGRAM_ID,
+        accounts: TransactionAccountKeys::for_program(WRAPPER_PROGRAM_ID, payer, nonce)
+            .to_account_metas(),
+        data: WrapperInstruction::SendMessage {
+            batch_id: 69,
+            payload_id: 1,
+            data,
+        }
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn test_send_message() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let fee_collector = derive_core_fee_collector().0;
+    let fee_collector_balance = banks_client.get_balance(fee_collector).await.unwrap();
+    let mut instructions = vec![initialize_emitter_ix(payer.pubkey())];
+    for (nonce, data) in [b"hello".to_vec(), b"world".to_vec()]
+        .into_iter()
+        .enumerate()
+    {
+        instructions.push(send_message_ix(payer.pubkey(), nonce as u64, data));
+    }
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+
+    // every message was posted to the message pda of its nonce
+    for (nonce, data) in [b"hello".to_vec(), b"world".to_vec()]
+        .into_iter()
+        .enumerate()
+    {
+        let keys =
+            TransactionAccountKeys::for_program(WRAPPER_PROGRAM_ID, payer.pubkey(), nonce as u64);
+        let message = banks_client
+            .get_account(keys.core_message_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.owner, WORMHOLE_PROGRAM_ID);
+        assert_eq!(
+            message.data,
+            Payload::new(1, data).unwrap().try_to_vec().unwrap()
+        );
+    }
+    let emitter = banks_client
+        .get_account(derive_emitter(WRAPPER_PROGRAM_ID).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Emitter::unpack(&emitter.data)
+            .unwrap()
+            .next_publishable_nonce,
+        2
+    );
+    assert_eq!(
+        banks_client.get_balance(fee_collector).await.unwrap(),
+        fee_collector_balance + 2 * MESSAGE_FEE
+    );
+
+    // the message pda of a used nonce is rejected
+    let tx = Transaction::new_signed_with_payer(
+        &[send_message_ix(payer.pubkey(), 0, b"again".to_vec())],
+        Some(&payer.pubkey()),
+        &[&payer],
+        banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    assert!(banks_client.process_transaction(tx).await.is_err());
+}
+
+/// the emitter starts at nonce 0, and every send publishes to the message pda derived from
+/// the current nonce before incrementing it, so three sends in separate transactions create
+/// the message accounts for nonces 0, 1 and 2 and leave the emitter at nonce 3
+#[tokio::test]
+async fn test_send_message_nonce_progression() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[initialize_emitter_ix(payer.pubkey())],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+    for nonce in 0..3 {
+        let tx = Transaction::new_signed_with_payer(
+            &[send_message_ix(payer.pubkey(), nonce, vec![nonce as u8])],
+            Some(&payer.pubkey()),
+            &[&payer],
+            banks_client.get_latest_blockhash().await.unwrap(),
+        );
+        banks_client.process_transaction(tx).await.unwrap();
+    }
+
+    let emitter = banks_client
+        .get_account(derive_emitter(WRAPPER_PROGRAM_ID).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Emitter::unpack(&emitter.data)
+            .unwrap()
+            .next_publishable_nonce,
+        3
+    );
+    let mut message_accounts = Vec::new();
+    for nonce in 0..3 {
+        let message_account =
+            TransactionAccountKeys::for_program(WRAPPER_PROGRAM_ID, payer.pubkey(), nonce)
+                .core_message_account;
+        let message = banks_client
+            .get_account(message_account)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            message.data,
+            Payload::new(1, vec![nonce as u8])
+                .unwrap()
+                .try_to_vec()
+                .unwrap()
+        );
+        message_accounts.push(message_account);
+    }
+    message_accounts.dedup();
+    assert_eq!(message_accounts.len(), 3);
+}
+
+fn claim_vaa_ix(payer: Pubkey, sequence: u64) -> Instruction {
+    Instruction {
+        program_id: WRAPPER_PROGRAM_ID,
+        accounts: ClaimVaaKeys::for_vaa(WRAPPER_PROGRAM_ID, payer, 2, &[7_u8; 32], sequence)
+            .to_account_metas(),
+        data: WrapperInstruction::ClaimVaa {
+            emitter_chain: 2,
+            emitter_address: [7_u8; 32],
+            sequence,
+        }
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn test_claim_vaa() {
+    let mut program_test = program_test();
+    // anyone may fund the claim pda of a vaa before it is claimed
+    let prefunded_claim =
+        ClaimVaaKeys::for_vaa(WRAPPER_PROGRAM_ID, Pubkey::default(), 2, &[7_u8; 32], 70).claim;
+    program_test.add_account(
+        prefunded_claim,
+        Account {
+            lamports: 1,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            claim_vaa_ix(payer.pubkey(), 69),
+            claim_vaa_ix(payer.pubkey(), 70),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(tx).await.unwrap();
+    for sequence in [69, 70] {
+        let claim =
+            ClaimVaaKeys::for_vaa(WRAPPER_PROGRAM_ID, payer.pubkey(), 2, &[7_u8; 32], sequence)
+                .claim;
+        let account = banks_client.get_account(claim).await.unwrap().unwrap();
+        assert_eq!(account.owner, WRAPPER_PROGRAM_ID);
+        assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+        assert_eq!(
+            Claim::unpack(&account.data).unwrap(),
+            Claim {
+                emitter_chain: 2,
+                emitter_address: [7_u8; 32],
+                sequence,
+            }
+        );
+    }
+
+    // claiming the vaa again is a replay
+    let tx = Transaction::new_signed_with_payer(
+        &[claim_vaa_ix(payer.pubkey(), 69)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    assert_eq!(
+        banks_client
+            .process_transaction(tx)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(WormholeLiteError::VaaAlreadyClaimed.code())
+        )
+    );
+}